@@ -0,0 +1,368 @@
+/*
+ * evil25519 – Unsecure X25519 and Ed25519 implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Ed25519 signatures (RFC 8032, 5.1)
+//!
+//! The scalars modulo the group order L are calculated with the
+//! [`BigUint`] of evilrng, which is everything but constant time.
+
+use evilrng::{BigUint, Random, RngError};
+use evilsecret::{wipe, SecretKey};
+use evilsha::{sha_2, ShaVersion};
+
+use crate::edwards::Point;
+use crate::CurveError;
+
+/// The order L of the base point, 2^252 +
+/// 27742317777372353535851937790883648493
+const ORDER: &str =
+    "1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed";
+
+fn order() -> BigUint
+{
+    BigUint::from_str_radix(ORDER, 16).expect("Valid hexadecimal number")
+}
+
+/// Reads a little-endian number
+fn from_le(bytes: &[u8]) -> BigUint
+{
+    let mut bytes = bytes.to_vec();
+    bytes.reverse();
+
+    BigUint::from_bytes_be(&bytes)
+}
+
+/// Writes a scalar below L as 32 little-endian bytes
+fn to_le(x: &BigUint) -> [u8; 32]
+{
+    let mut rv = [0; 32];
+    let bytes = x.to_bytes_be_padded(32).expect("Reduced modulo L");
+    for (x, y) in rv.iter_mut().zip(bytes.iter().rev())
+    {
+        *x = *y;
+    }
+
+    rv
+}
+
+/// SHA-512 of all `parts`, reduced modulo L
+fn hash_to_scalar(parts: &[&[u8]]) -> BigUint
+{
+    let input = parts.concat();
+
+    from_le(sha_2(&input, ShaVersion::Sha512).as_bytes()) % order()
+}
+
+/// An Ed25519 public key
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VerifyingKey
+{
+    bytes: [u8; 32],
+    point: Point,
+}
+
+impl VerifyingKey
+{
+    /// Loads a public key
+    ///
+    /// # Errors
+    /// It returns [`CurveError::InvalidPoint`] if `bytes` isn't the
+    /// encoding of a point.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Result<Self, CurveError>
+    {
+        let point = Point::decode(bytes).ok_or(CurveError::InvalidPoint)?;
+
+        Ok(Self {
+            bytes: *bytes,
+            point,
+        })
+    }
+
+    /// The 32 bytes of the key
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 32]
+    {
+        &self.bytes
+    }
+
+    /// Verifies the `signature` of `message` (RFC 8032, 5.1.7)
+    ///
+    /// Only the canonical encoding of the signature is accepted, and the
+    /// check is done without multiplying with the cofactor.
+    ///
+    /// # Errors
+    /// It returns [`CurveError::Verification`] if the signature is
+    /// invalid.
+    pub fn verify(
+        &self,
+        message: &[u8],
+        signature: &[u8; 64],
+    ) -> Result<(), CurveError>
+    {
+        let (r, s) = signature.split_at(32);
+        let s = from_le(s);
+        if s >= order()
+        {
+            return Err(CurveError::Verification);
+        }
+        let k = hash_to_scalar(&[r, &self.bytes, message]);
+
+        // [S]B - [k]A has to be R.
+        let check = Point::base()
+            .mul(&to_le(&s))
+            .add(self.point.neg().mul(&to_le(&k)));
+        if check.encode() != r
+        {
+            return Err(CurveError::Verification);
+        }
+
+        Ok(())
+    }
+}
+
+/// An Ed25519 secret key
+///
+/// ```
+/// # use evil25519::{CurveError, SigningKey};
+/// # use evilrng::RngSource;
+/// # fn main() -> Result<(), CurveError>
+/// # {
+/// let mut rng = RngSource::new()?;
+/// let key = SigningKey::generate(&mut rng)?;
+///
+/// let signature = key.sign(b"evil");
+/// key.verifying_key().verify(b"evil", &signature)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SigningKey
+{
+    seed: SecretKey<32>,
+    scalar: SecretKey<32>,
+    prefix: SecretKey<32>,
+    public: VerifyingKey,
+}
+
+impl SigningKey
+{
+    /// Generates a random secret key
+    ///
+    /// # Errors
+    /// It returns an error if `rng` couldn't be (re)seeded.
+    pub fn generate<R: Random + ?Sized>(rng: &mut R)
+        -> Result<Self, RngError>
+    {
+        let mut seed = SecretKey::new([0; 32]);
+        rng.fill_bytes(seed.expose_mut())?;
+
+        Ok(Self::from_seed(seed))
+    }
+
+    /// Derives the key from its 32 byte seed (RFC 8032, 5.1.5)
+    #[must_use]
+    pub fn from_seed(seed: SecretKey<32>) -> Self
+    {
+        let mut h = [0; 64];
+        h.copy_from_slice(sha_2(seed.expose(), ShaVersion::Sha512).as_bytes());
+        let mut scalar = [0; 32];
+        let mut prefix = [0; 32];
+        scalar.copy_from_slice(&h[..32]);
+        prefix.copy_from_slice(&h[32..]);
+        wipe(&mut h);
+
+        scalar[0] &= 0xf8;
+        scalar[31] &= 0x7f;
+        scalar[31] |= 0x40;
+
+        let point = Point::base().mul(&scalar);
+        let public = VerifyingKey {
+            bytes: point.encode(),
+            point,
+        };
+
+        Self {
+            seed,
+            scalar: SecretKey::from_mut(&mut scalar),
+            prefix: SecretKey::from_mut(&mut prefix),
+            public,
+        }
+    }
+
+    /// The seed the key was derived from
+    #[must_use]
+    pub const fn seed(&self) -> &SecretKey<32>
+    {
+        &self.seed
+    }
+
+    /// The public key to verify the signatures with
+    #[must_use]
+    pub const fn verifying_key(&self) -> &VerifyingKey
+    {
+        &self.public
+    }
+
+    /// Signs `message` (RFC 8032, 5.1.6)
+    ///
+    /// The signature is deterministic, no random numbers are needed.
+    #[must_use]
+    pub fn sign(&self, message: &[u8]) -> [u8; 64]
+    {
+        let r = hash_to_scalar(&[self.prefix.expose(), message]);
+        let big_r = Point::base().mul(&to_le(&r)).encode();
+        let k = hash_to_scalar(&[&big_r, &self.public.bytes, message]);
+        let s = (r + k * from_le(self.scalar.expose())) % order();
+
+        let mut rv = [0; 64];
+        rv[..32].copy_from_slice(&big_r);
+        rv[32..].copy_from_slice(&to_le(&s));
+
+        rv
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use evilsecret::SecretKey;
+    use evilsha::{sha_2, ShaVersion};
+
+    use super::{from_le, order, to_le, SigningKey, VerifyingKey};
+    use crate::tests::hex;
+    use crate::CurveError;
+
+    fn check(seed: &str, public: &str, message: &[u8], signature: &str)
+    {
+        let key = SigningKey::from_seed(SecretKey::new(hex(seed)));
+        let signature = hex(signature);
+
+        assert_eq!(key.verifying_key().as_bytes(), &hex(public));
+        assert_eq!(key.sign(message), signature);
+        assert!(key.verifying_key().verify(message, &signature).is_ok());
+    }
+
+    #[test]
+    fn rfc_8032_test()
+    {
+        // TEST 1, 2, 3, 1024 and SHA(abc) of section 7.1
+        check(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            b"",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+             5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        );
+        check(
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            &[0x72],
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+             085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        );
+        check(
+            "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            &[0xaf, 0x82],
+            "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac\
+             18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+        );
+        check(
+            "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+            "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+            sha_2(b"abc", ShaVersion::Sha512).as_bytes(),
+            "dc2a4459e7369633a52b1bf277839a00201009a3efbf3ecb69bea2186c26b589\
+             09351fc9ac90b3ecfdfbc7c66431e0303dca179c138ac17ad9bef1177331a704",
+        );
+        // TEST 1024
+        let message: [u8; 1023] = hex(
+            "08b8b2b733424243760fe426a4b54908632110a66c2f6591eabd3345e3e4eb98\
+             fa6e264bf09efe12ee50f8f54e9f77b1e355f6c50544e23fb1433ddf73be84d8\
+             79de7c0046dc4996d9e773f4bc9efe5738829adb26c81b37c93a1b270b20329d\
+             658675fc6ea534e0810a4432826bf58c941efb65d57a338bbd2e26640f89ffbc\
+             1a858efcb8550ee3a5e1998bd177e93a7363c344fe6b199ee5d02e82d522c4fe\
+             ba15452f80288a821a579116ec6dad2b3b310da903401aa62100ab5d1a36553e\
+             06203b33890cc9b832f79ef80560ccb9a39ce767967ed628c6ad573cb116dbef\
+             efd75499da96bd68a8a97b928a8bbc103b6621fcde2beca1231d206be6cd9ec7\
+             aff6f6c94fcd7204ed3455c68c83f4a41da4af2b74ef5c53f1d8ac70bdcb7ed1\
+             85ce81bd84359d44254d95629e9855a94a7c1958d1f8ada5d0532ed8a5aa3fb2\
+             d17ba70eb6248e594e1a2297acbbb39d502f1a8c6eb6f1ce22b3de1a1f40cc24\
+             554119a831a9aad6079cad88425de6bde1a9187ebb6092cf67bf2b13fd65f270\
+             88d78b7e883c8759d2c4f5c65adb7553878ad575f9fad878e80a0c9ba63bcbcc\
+             2732e69485bbc9c90bfbd62481d9089beccf80cfe2df16a2cf65bd92dd597b07\
+             07e0917af48bbb75fed413d238f5555a7a569d80c3414a8d0859dc65a46128ba\
+             b27af87a71314f318c782b23ebfe808b82b0ce26401d2e22f04d83d1255dc51a\
+             ddd3b75a2b1ae0784504df543af8969be3ea7082ff7fc9888c144da2af58429e\
+             c96031dbcad3dad9af0dcbaaaf268cb8fcffead94f3c7ca495e056a9b47acdb7\
+             51fb73e666c6c655ade8297297d07ad1ba5e43f1bca32301651339e22904cc8c\
+             42f58c30c04aafdb038dda0847dd988dcda6f3bfd15c4b4c4525004aa06eeff8\
+             ca61783aacec57fb3d1f92b0fe2fd1a85f6724517b65e614ad6808d6f6ee34df\
+             f7310fdc82aebfd904b01e1dc54b2927094b2db68d6f903b68401adebf5a7e08\
+             d78ff4ef5d63653a65040cf9bfd4aca7984a74d37145986780fc0b16ac451649\
+             de6188a7dbdf191f64b5fc5e2ab47b57f7f7276cd419c17a3ca8e1b939ae49e4\
+             88acba6b965610b5480109c8b17b80e1b7b750dfc7598d5d5011fd2dcc5600a3\
+             2ef5b52a1ecc820e308aa342721aac0943bf6686b64b2579376504ccc493d97e\
+             6aed3fb0f9cd71a43dd497f01f17c0e2cb3797aa2a2f256656168e6c496afc5f\
+             b93246f6b1116398a346f1a641f3b041e989f7914f90cc2c7fff357876e506b5\
+             0d334ba77c225bc307ba537152f3f1610e4eafe595f6d9d90d11faa933a15ef1\
+             369546868a7f3a45a96768d40fd9d03412c091c6315cf4fde7cb68606937380d\
+             b2eaaa707b4c4185c32eddcdd306705e4dc1ffc872eeee475a64dfac86aba41c\
+             0618983f8741c5ef68d3a101e8a3b8cac60c905c15fc910840b94c00a0b9d0",
+        );
+        check(
+            "f5e5767cf153319517630f226876b86c8160cc583bc013744c6bf255f5cc0ee5",
+            "278117fc144c72340f67d0f2316e8386ceffbf2b2428c9c51fef7c597f1d426e",
+            &message,
+            "0aab4c900501b3e24d7cdf4663326a3a87df5e4843b2cbdb67cbf6e460fec350\
+             aa5371b1508f9f4528ecea23c436d94b5e8fcd4f681e30a6ac00a9704a188a03",
+        );
+    }
+
+    #[test]
+    fn invalid_test() -> Result<(), CurveError>
+    {
+        let key = SigningKey::from_seed(SecretKey::new([3; 32]));
+        let public = key.verifying_key();
+        let signature = key.sign(b"evil");
+        public.verify(b"evil", &signature)?;
+
+        assert!(public.verify(b"evel", &signature).is_err());
+        for i in [0, 31, 32, 63]
+        {
+            let mut tampered = signature;
+            tampered[i] ^= 4;
+            assert!(public.verify(b"evil", &tampered).is_err());
+        }
+
+        // S + L would work without the range check.
+        let mut malleated = signature;
+        let s = from_le(&signature[32..]) + order();
+        malleated[32..].copy_from_slice(&to_le(&s));
+        assert!(public.verify(b"evil", &malleated).is_err());
+
+        let other = SigningKey::from_seed(SecretKey::new([4; 32]));
+        assert!(other.verifying_key().verify(b"evil", &signature).is_err());
+        assert_eq!(VerifyingKey::from_bytes(public.as_bytes())?, *public);
+        assert!(matches!(
+            VerifyingKey::from_bytes(&[2; 32]),
+            Err(CurveError::InvalidPoint)
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+/*
+ * evil25519 – Unsecure X25519 and Ed25519 implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Points on the twisted Edwards curve edwards25519 (RFC 8032, 5.1)
+
+use crate::field::Fe;
+
+/// The encoding of the base point B, with y = 4/5
+const BASE: [u8; 32] = [
+    0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+];
+
+/// A point in extended homogeneous coordinates
+///
+/// x = X/Z, y = Y/Z and x * y = T/Z.
+#[derive(Copy, Clone, Debug)]
+pub struct Point
+{
+    x: Fe,
+    y: Fe,
+    z: Fe,
+    t: Fe,
+}
+
+impl Point
+{
+    /// The neutral element (0, 1)
+    pub const IDENTITY: Self = Self {
+        x: Fe::ZERO,
+        y: Fe::ONE,
+        z: Fe::ONE,
+        t: Fe::ZERO,
+    };
+
+    /// The base point B
+    pub fn base() -> Self
+    {
+        Self::decode(&BASE).expect("The base point is valid")
+    }
+
+    /// Decodes a point (RFC 8032, 5.1.3)
+    ///
+    /// Returns [`None`] if `bytes` isn't the one encoding of a point.
+    pub fn decode(bytes: &[u8; 32]) -> Option<Self>
+    {
+        let sign = bytes[31] >> 7 == 1;
+        let y = Fe::from_bytes(bytes);
+        let mut canonical = *bytes;
+        canonical[31] &= 0x7f;
+        if y.to_bytes() != canonical
+        {
+            return None;
+        }
+
+        // x^2 = (y^2 - 1) / (d y^2 + 1) = u / v
+        let y2 = y * y;
+        let u = y2 - Fe::ONE;
+        let v = Fe::D * y2 + Fe::ONE;
+        let v3 = v * v * v;
+        let mut x = u * v3 * (u * v3 * v3 * v).pow_p58();
+
+        let vx2 = v * x * x;
+        if vx2 == -u
+        {
+            x = x * Fe::SQRT_M1;
+        }
+        else if vx2 != u
+        {
+            return None;
+        }
+
+        if x.is_zero() && sign
+        {
+            return None;
+        }
+        if x.is_negative() != sign
+        {
+            x = -x;
+        }
+
+        Some(Self {
+            x,
+            y,
+            z: Fe::ONE,
+            t: x * y,
+        })
+    }
+
+    /// Encodes the point (RFC 8032, 5.1.2)
+    pub fn encode(self) -> [u8; 32]
+    {
+        let z = self.z.invert();
+        let mut rv = (self.y * z).to_bytes();
+        rv[31] |= u8::from((self.x * z).is_negative()) << 7;
+
+        rv
+    }
+
+    /// Adds two points (RFC 8032, 5.1.4)
+    ///
+    /// The formulas are complete, so they work for doubling as well.
+    // Named like in RFC 8032.
+    #[allow(clippy::many_single_char_names)]
+    pub fn add(self, other: Self) -> Self
+    {
+        let a = (self.y - self.x) * (other.y - other.x);
+        let b = (self.y + self.x) * (other.y + other.x);
+        let c = self.t * Fe::D2 * other.t;
+        let d = self.z * (other.z + other.z);
+        let (e, f, g, h) = (b - a, d - c, d + c, b + a);
+
+        Self {
+            x: e * f,
+            y: g * h,
+            z: f * g,
+            t: e * h,
+        }
+    }
+
+    /// The negated point (-x, y)
+    pub fn neg(self) -> Self
+    {
+        Self {
+            x: -self.x,
+            t: -self.t,
+            ..self
+        }
+    }
+
+    /// Multiplies with a little-endian scalar
+    ///
+    /// Always adds, so that only the selection depends on the bits.
+    pub fn mul(self, scalar: &[u8; 32]) -> Self
+    {
+        let mut rv = Self::IDENTITY;
+
+        for i in (0..256).rev()
+        {
+            rv = rv.add(rv);
+            let mut sum = rv.add(self);
+            let bit = u64::from(scalar[i / 8] >> (i % 8) & 1);
+            Fe::swap(&mut rv.x, &mut sum.x, bit);
+            Fe::swap(&mut rv.y, &mut sum.y, bit);
+            Fe::swap(&mut rv.z, &mut sum.z, bit);
+            Fe::swap(&mut rv.t, &mut sum.t, bit);
+        }
+
+        rv
+    }
+}
+
+impl PartialEq for Point
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.x * other.z == other.x * self.z
+            && self.y * other.z == other.y * self.z
+    }
+}
+
+impl Eq for Point {}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{Point, BASE};
+
+    /// The order of the base point, little-endian
+    const L: [u8; 32] = [
+        0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7,
+        0xa2, 0xde, 0xf9, 0xde, 0x14, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0x10,
+    ];
+
+    fn scalar(x: u8) -> [u8; 32]
+    {
+        let mut rv = [0; 32];
+        rv[0] = x;
+
+        rv
+    }
+
+    #[test]
+    fn group_test()
+    {
+        let base = Point::base();
+
+        assert_eq!(base.encode(), BASE);
+        assert_eq!(base.mul(&L), Point::IDENTITY);
+        assert_eq!(base.add(base.neg()), Point::IDENTITY);
+        assert_eq!(base.mul(&scalar(2)), base.add(base));
+        assert_eq!(
+            base.mul(&scalar(5)),
+            base.add(base).add(base.add(base)).add(base)
+        );
+        assert_eq!(base.mul(&scalar(0)), Point::IDENTITY);
+
+        let point = base.mul(&scalar(77));
+        assert_eq!(Point::decode(&point.encode()), Some(point));
+        assert_eq!(Point::decode(&point.neg().encode()), Some(point.neg()));
+    }
+
+    #[test]
+    fn decode_test()
+    {
+        // y = 2 isn't on the curve.
+        assert_eq!(Point::decode(&scalar(2)), None);
+        // y = p + 1 isn't canonical.
+        let mut bytes = [0xff; 32];
+        bytes[0] = 0xee;
+        bytes[31] = 0x7f;
+        assert_eq!(Point::decode(&bytes), None);
+        // The identity with x = -0
+        let mut bytes = scalar(1);
+        bytes[31] = 0x80;
+        assert_eq!(Point::decode(&bytes), None);
+        assert_eq!(Point::decode(&scalar(1)), Some(Point::IDENTITY));
+    }
+}
@@ -0,0 +1,390 @@
+/*
+ * evil25519 – Unsecure X25519 and Ed25519 implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Arithmetic modulo p = 2^255 - 19
+//!
+//! An element is five limbs of 51 bits, so that the products fit in a
+//! `u128` and the carries can be delayed.  Nothing here branches on
+//! the values, except [`Fe::is_zero`] and the comparisons, which only
+//! look at public values.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+const MASK: u64 = (1 << 51) - 1;
+
+/// An element of the field, not necessarily fully reduced
+#[derive(Copy, Clone, Debug)]
+pub struct Fe([u64; 5]);
+
+/// Reads eight bytes as a little-endian number
+fn load(bytes: &[u8]) -> u64
+{
+    let mut rv = [0; 8];
+    rv.copy_from_slice(&bytes[..8]);
+
+    u64::from_le_bytes(rv)
+}
+
+// 16 * p, added before subtracting so that nothing underflows
+const SIXTEEN_P: [u64; 5] = [
+    36_028_797_018_963_664,
+    36_028_797_018_963_952,
+    36_028_797_018_963_952,
+    36_028_797_018_963_952,
+    36_028_797_018_963_952,
+];
+
+impl Fe
+{
+    /// Zero
+    pub const ZERO: Self = Self([0; 5]);
+    /// One
+    pub const ONE: Self = Self([1, 0, 0, 0, 0]);
+    /// d = -121665 / 121666 of the Edwards curve
+    pub const D: Self = Self([
+        929_955_233_495_203,
+        466_365_720_129_213,
+        1_662_059_464_998_953,
+        2_033_849_074_728_123,
+        1_442_794_654_840_575,
+    ]);
+    /// 2 * d
+    pub const D2: Self = Self([
+        1_859_910_466_990_425,
+        932_731_440_258_426,
+        1_072_319_116_312_658,
+        1_815_898_335_770_999,
+        633_789_495_995_903,
+    ]);
+    /// A square root of -1, 2^((p - 1) / 4)
+    pub const SQRT_M1: Self = Self([
+        1_718_705_420_411_056,
+        234_908_883_556_509,
+        2_233_514_472_574_048,
+        2_117_202_627_021_982,
+        765_476_049_583_133,
+    ]);
+
+    /// A small number
+    pub const fn from_u64(x: u64) -> Self
+    {
+        Self([x & MASK, x >> 51, 0, 0, 0])
+    }
+
+    /// Reads a little-endian number, ignoring the highest bit
+    pub fn from_bytes(bytes: &[u8; 32]) -> Self
+    {
+        Self([
+            load(&bytes[0..]) & MASK,
+            (load(&bytes[6..]) >> 3) & MASK,
+            (load(&bytes[12..]) >> 6) & MASK,
+            (load(&bytes[19..]) >> 1) & MASK,
+            (load(&bytes[24..]) >> 12) & MASK,
+        ])
+    }
+
+    /// Writes the fully reduced value as little-endian number
+    // Only whole bytes are taken from the accumulator.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_bytes(self) -> [u8; 32]
+    {
+        let mut limbs = self.carry().0;
+
+        // Adds 19 to find out whether the value is at least p, in which
+        // case it's subtracted by dropping the 2^255.
+        let mut q = (limbs[0] + 19) >> 51;
+        for limb in &limbs[1..]
+        {
+            q = (limb + q) >> 51;
+        }
+        limbs[0] += 19 * q;
+        for i in 0..4
+        {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= MASK;
+        }
+        limbs[4] &= MASK;
+
+        let mut rv = [0; 32];
+        let mut acc = 0_u128;
+        let mut bits = 0;
+        let mut i = 0;
+        for limb in limbs
+        {
+            acc |= u128::from(limb) << bits;
+            bits += 51;
+            while bits >= 8
+            {
+                rv[i] = acc as u8;
+                acc >>= 8;
+                bits -= 8;
+                i += 1;
+            }
+        }
+        rv[31] = acc as u8;
+
+        rv
+    }
+
+    /// Carries so that every limb has at most 52 bits
+    // The carry out of the highest limb is below 2^13.
+    #[allow(clippy::cast_possible_truncation)]
+    fn carry(self) -> Self
+    {
+        let mut limbs = self.0;
+        let mut carry = 0;
+
+        for limb in &mut limbs
+        {
+            *limb += carry;
+            carry = *limb >> 51;
+            *limb &= MASK;
+        }
+        limbs[0] += 19 * carry;
+
+        Self(limbs)
+    }
+
+    /// `self^exponent`, where `exponent` is public
+    fn pow(self, exponent: &[u8; 32]) -> Self
+    {
+        let mut rv = Self::ONE;
+
+        for i in (0..256).rev()
+        {
+            rv = rv * rv;
+            if exponent[i / 8] >> (i % 8) & 1 == 1
+            {
+                rv = rv * self;
+            }
+        }
+
+        rv
+    }
+
+    /// The inverse, zero for zero
+    pub fn invert(self) -> Self
+    {
+        // p - 2
+        let mut exponent = [0xff; 32];
+        exponent[0] = 0xeb;
+        exponent[31] = 0x7f;
+
+        self.pow(&exponent)
+    }
+
+    /// `self^((p - 5) / 8)`, which is needed for square roots
+    pub fn pow_p58(self) -> Self
+    {
+        let mut exponent = [0xff; 32];
+        exponent[0] = 0xfd;
+        exponent[31] = 0x0f;
+
+        self.pow(&exponent)
+    }
+
+    /// Whether the reduced value is odd, called negative in RFC 8032
+    pub fn is_negative(self) -> bool
+    {
+        self.to_bytes()[0] & 1 == 1
+    }
+
+    /// Whether it's zero
+    pub fn is_zero(self) -> bool
+    {
+        self.to_bytes() == [0; 32]
+    }
+
+    /// Swaps `a` and `b` if `swap` is 1, without branching
+    pub fn swap(a: &mut Self, b: &mut Self, swap: u64)
+    {
+        let mask = 0_u64.wrapping_sub(swap);
+
+        for (x, y) in a.0.iter_mut().zip(&mut b.0)
+        {
+            let diff = mask & (*x ^ *y);
+            *x ^= diff;
+            *y ^= diff;
+        }
+    }
+}
+
+impl PartialEq for Fe
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+impl Eq for Fe {}
+
+impl Add for Fe
+{
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self
+    {
+        let mut rv = self.0;
+        for (x, y) in rv.iter_mut().zip(&other.0)
+        {
+            *x += y;
+        }
+
+        Self(rv).carry()
+    }
+}
+
+impl Sub for Fe
+{
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self
+    {
+        let mut rv = self.0;
+        for ((x, y), z) in rv.iter_mut().zip(&other.0).zip(&SIXTEEN_P)
+        {
+            *x = *x + z - y;
+        }
+
+        Self(rv).carry()
+    }
+}
+
+impl Neg for Fe
+{
+    type Output = Self;
+
+    fn neg(self) -> Self
+    {
+        Self::ZERO - self
+    }
+}
+
+impl Mul for Fe
+{
+    type Output = Self;
+
+    // The carries are below 2^64, so the casts don't lose anything.
+    #[allow(clippy::cast_possible_truncation)]
+    fn mul(self, other: Self) -> Self
+    {
+        let [a0, a1, a2, a3, a4] = self.0.map(u128::from);
+        let [b0, b1, b2, b3, b4] = other.0.map(u128::from);
+        // 2^255 = 19, so what overflows the highest limb comes back
+        // times 19.
+        let (b1_19, b2_19, b3_19, b4_19) =
+            (19 * b1, 19 * b2, 19 * b3, 19 * b4);
+
+        let c = [
+            a0 * b0 + a1 * b4_19 + a2 * b3_19 + a3 * b2_19 + a4 * b1_19,
+            a0 * b1 + a1 * b0 + a2 * b4_19 + a3 * b3_19 + a4 * b2_19,
+            a0 * b2 + a1 * b1 + a2 * b0 + a3 * b4_19 + a4 * b3_19,
+            a0 * b3 + a1 * b2 + a2 * b1 + a3 * b0 + a4 * b4_19,
+            a0 * b4 + a1 * b3 + a2 * b2 + a3 * b1 + a4 * b0,
+        ];
+
+        let mut rv = [0; 5];
+        let mut carry = 0;
+        for (x, y) in rv.iter_mut().zip(c)
+        {
+            let sum = y + carry;
+            *x = (sum as u64) & MASK;
+            carry = sum >> 51;
+        }
+        rv[0] += 19 * carry as u64;
+
+        Self(rv).carry()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::Fe;
+
+    /// p - 1
+    const MINUS_ONE: [u8; 32] = [
+        0xec, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f,
+    ];
+
+    #[test]
+    fn reduce_test()
+    {
+        let minus_one = Fe::from_bytes(&MINUS_ONE);
+        let mut p = MINUS_ONE;
+        p[0] += 1;
+
+        assert_eq!(minus_one.to_bytes(), MINUS_ONE);
+        assert_eq!(Fe::from_bytes(&p).to_bytes(), [0; 32]);
+        assert_eq!((minus_one + Fe::ONE).to_bytes(), [0; 32]);
+        assert_eq!(-Fe::ONE, minus_one);
+        assert_eq!((Fe::ZERO - Fe::ONE).to_bytes(), MINUS_ONE);
+        assert_eq!((minus_one * minus_one).to_bytes(), Fe::ONE.to_bytes());
+        // The highest bit is ignored.
+        let mut high = [0; 32];
+        high[31] = 0x80;
+        assert!(Fe::from_bytes(&high).is_zero());
+    }
+
+    #[test]
+    fn constants_test()
+    {
+        let d = -(Fe::from_u64(121_665) * Fe::from_u64(121_666).invert());
+
+        assert_eq!(Fe::D, d);
+        assert_eq!(Fe::D2, d + d);
+        assert_eq!(Fe::SQRT_M1 * Fe::SQRT_M1, -Fe::ONE);
+    }
+
+    #[test]
+    fn arithmetic_test()
+    {
+        let mut bytes = [0; 32];
+        let mut x = Fe::from_u64(3);
+
+        for i in 0..50_u8
+        {
+            bytes[usize::from(i) % 32] ^= i.wrapping_mul(97);
+            let y = Fe::from_bytes(&bytes);
+
+            assert_eq!(x * y, y * x);
+            assert_eq!((x + y) - y, x);
+            assert_eq!((x + y) * (x - y), x * x - y * y);
+            if !y.is_zero()
+            {
+                assert_eq!(y * y.invert(), Fe::ONE);
+            }
+            x = x * y + Fe::ONE;
+        }
+        assert!(Fe::ZERO.invert().is_zero());
+    }
+
+    #[test]
+    fn swap_test()
+    {
+        let (mut a, mut b) = (Fe::ONE, Fe::from_u64(2));
+
+        Fe::swap(&mut a, &mut b, 0);
+        assert_eq!((a, b), (Fe::ONE, Fe::from_u64(2)));
+        Fe::swap(&mut a, &mut b, 1);
+        assert_eq!((a, b), (Fe::from_u64(2), Fe::ONE));
+    }
+}
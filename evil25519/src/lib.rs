@@ -0,0 +1,150 @@
+/*
+ * evil25519 – Unsecure X25519 and Ed25519 implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![warn(
+    missing_docs,
+    missing_debug_implementations,
+    missing_copy_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_import_braces,
+    unused_qualifications,
+    rustdoc::missing_crate_level_docs,
+    rust_2018_idioms,
+    clippy::all,
+    clippy::pedantic,
+    clippy::nursery,
+    clippy::cargo
+)]
+#![allow(clippy::suspicious_else_formatting, clippy::match_like_matches_macro)]
+
+//! Unsecure implementation of X25519 and Ed25519 in pure rust
+//!
+//! evil25519 is a implementation of the X25519 key exchange (RFC 7748)
+//! and of Ed25519 signatures (RFC 8032) written by an amateur for the
+//! sole purpose that he learns a bit about cryptography.  It is very
+//! probably *very* vulnerable, so **do not use evil25519**.  The same
+//! applies to all other evil\* crates.
+//!
+//! Both work on the same field modulo 2^255 - 19; X25519 with a
+//! Montgomery ladder on the u-coordinate, Ed25519 on the twisted
+//! Edwards form of the curve.  Keys are generated with evilrng.
+//!
+//! ```
+//! # use evil25519::{CurveError, SigningKey, X25519SecretKey};
+//! # use evilrng::RngSource;
+//! # fn main() -> Result<(), CurveError>
+//! # {
+//! let mut rng = RngSource::new()?;
+//!
+//! let alice = X25519SecretKey::generate(&mut rng)?;
+//! let bob = X25519SecretKey::generate(&mut rng)?;
+//! let shared = alice.diffie_hellman(&bob.public_key())?;
+//! assert_eq!(
+//!     shared.expose(),
+//!     bob.diffie_hellman(&alice.public_key())?.expose()
+//! );
+//!
+//! let key = SigningKey::generate(&mut rng)?;
+//! let signature = key.sign(b"evil");
+//! key.verifying_key().verify(b"evil", &signature)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::error;
+use std::fmt;
+
+use evilrng::RngError;
+
+pub use ed25519::{SigningKey, VerifyingKey};
+pub use x25519::{x25519, X25519PublicKey, X25519SecretKey, X25519_BASEPOINT};
+
+mod ed25519;
+mod edwards;
+mod field;
+mod x25519;
+
+/// Errors from evil25519
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CurveError
+{
+    /// Random numbers couldn't be generated.
+    Rng(RngError),
+    /// The bytes aren't the encoding of a point.
+    InvalidPoint,
+    /// A signature is invalid.
+    Verification,
+    /// The other side's public key has a small order, so the shared
+    /// secret would be zero.
+    LowOrder,
+}
+
+impl fmt::Display for CurveError
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        match self
+        {
+            Self::Rng(err) => write!(fmt, "Random number error: {err}"),
+            Self::InvalidPoint => write!(fmt, "Invalid point"),
+            Self::Verification => write!(fmt, "Invalid signature"),
+            Self::LowOrder => write!(fmt, "Public key of small order"),
+        }
+    }
+}
+
+impl error::Error for CurveError
+{
+    fn source(&self) -> Option<&(dyn error::Error + 'static)>
+    {
+        match self
+        {
+            Self::Rng(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<RngError> for CurveError
+{
+    fn from(err: RngError) -> Self
+    {
+        Self::Rng(err)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    /// Reads a hexadecimal string
+    pub fn hex<const N: usize>(s: &str) -> [u8; N]
+    {
+        let mut rv = [0; N];
+        assert_eq!(s.len(), 2 * N);
+        for (i, x) in rv.iter_mut().enumerate()
+        {
+            *x = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+        }
+
+        rv
+    }
+}
@@ -0,0 +1,303 @@
+/*
+ * evil25519 – Unsecure X25519 and Ed25519 implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The X25519 key exchange (RFC 7748)
+
+use evilrng::{Random, RngError};
+use evilsecret::{barrier, wipe, SecretKey};
+
+use crate::field::Fe;
+use crate::CurveError;
+
+/// The u-coordinate of the base point, 9
+pub const X25519_BASEPOINT: [u8; 32] = [
+    9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0,
+];
+
+/// The X25519 function (RFC 7748, 5)
+///
+/// Multiplies the point with the u-coordinate `u` with the clamped
+/// `scalar` with a Montgomery ladder.  Use [`X25519_BASEPOINT`] as `u`
+/// to get a public key.  Usually [`X25519SecretKey`] is more
+/// convenient.
+// Named like in RFC 7748.
+#[allow(clippy::many_single_char_names)]
+#[must_use]
+pub fn x25519(scalar: &SecretKey<32>, u: &[u8; 32]) -> [u8; 32]
+{
+    let mut k = *scalar.expose();
+    k[0] &= 0xf8;
+    k[31] &= 0x7f;
+    k[31] |= 0x40;
+
+    let x1 = Fe::from_bytes(u);
+    let (mut x2, mut z2) = (Fe::ONE, Fe::ZERO);
+    let (mut x3, mut z3) = (x1, Fe::ONE);
+    let mut swap = 0;
+    let a24 = Fe::from_u64(121_665);
+
+    for t in (0..255).rev()
+    {
+        let bit = u64::from(k[t / 8] >> (t % 8) & 1);
+        swap ^= bit;
+        Fe::swap(&mut x2, &mut x3, swap);
+        Fe::swap(&mut z2, &mut z3, swap);
+        swap = bit;
+
+        let a = x2 + z2;
+        let aa = a * a;
+        let b = x2 - z2;
+        let bb = b * b;
+        let e = aa - bb;
+        let c = x3 + z3;
+        let d = x3 - z3;
+        let da = d * a;
+        let cb = c * b;
+        let sum = da + cb;
+        let diff = da - cb;
+
+        x3 = sum * sum;
+        z3 = x1 * diff * diff;
+        x2 = aa * bb;
+        z2 = e * (aa + a24 * e);
+    }
+    Fe::swap(&mut x2, &mut x3, swap);
+    Fe::swap(&mut z2, &mut z3, swap);
+    wipe(&mut k);
+
+    (x2 * z2.invert()).to_bytes()
+}
+
+/// A X25519 public key, the u-coordinate of a point
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct X25519PublicKey([u8; 32]);
+
+impl X25519PublicKey
+{
+    /// The 32 bytes of the key
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 32]
+    {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for X25519PublicKey
+{
+    fn from(bytes: [u8; 32]) -> Self
+    {
+        Self(bytes)
+    }
+}
+
+/// A X25519 secret key
+///
+/// ```
+/// # use evil25519::{CurveError, X25519SecretKey};
+/// # use evilrng::RngSource;
+/// # fn main() -> Result<(), CurveError>
+/// # {
+/// let mut rng = RngSource::new()?;
+/// let alice = X25519SecretKey::generate(&mut rng)?;
+/// let bob = X25519SecretKey::generate(&mut rng)?;
+///
+/// assert_eq!(
+///     alice.diffie_hellman(&bob.public_key())?.expose(),
+///     bob.diffie_hellman(&alice.public_key())?.expose()
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct X25519SecretKey(SecretKey<32>);
+
+impl X25519SecretKey
+{
+    /// Generates a random secret key
+    ///
+    /// # Errors
+    /// It returns an error if `rng` couldn't be (re)seeded.
+    pub fn generate<R: Random + ?Sized>(rng: &mut R)
+        -> Result<Self, RngError>
+    {
+        let mut key = SecretKey::new([0; 32]);
+        rng.fill_bytes(key.expose_mut())?;
+
+        Ok(Self(key))
+    }
+
+    /// The public key to give to the other side
+    #[must_use]
+    pub fn public_key(&self) -> X25519PublicKey
+    {
+        X25519PublicKey(x25519(&self.0, &X25519_BASEPOINT))
+    }
+
+    /// Calculates the shared secret with the other side's public key
+    ///
+    /// Hash it before using it as key.
+    ///
+    /// # Errors
+    /// It returns [`CurveError::LowOrder`] if the shared secret is
+    /// zero, because `public` is a point of small order, which would
+    /// let the other side choose the secret (RFC 7748, 6.1).
+    pub fn diffie_hellman(
+        &self,
+        public: &X25519PublicKey,
+    ) -> Result<SecretKey<32>, CurveError>
+    {
+        let mut shared = x25519(&self.0, &public.0);
+        let zero = barrier(shared.iter().fold(0, |acc, x| acc | x)) == 0;
+
+        if zero
+        {
+            return Err(CurveError::LowOrder);
+        }
+
+        Ok(SecretKey::from_mut(&mut shared))
+    }
+}
+
+impl From<SecretKey<32>> for X25519SecretKey
+{
+    fn from(key: SecretKey<32>) -> Self
+    {
+        Self(key)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use evilsecret::SecretKey;
+
+    use super::{x25519, X25519SecretKey, X25519_BASEPOINT};
+    use crate::tests::hex;
+    use crate::CurveError;
+
+    #[test]
+    fn rfc_7748_test()
+    {
+        // Section 5.2
+        let k = SecretKey::new(hex(
+            "a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4",
+        ));
+        let u = hex(
+            "e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c",
+        );
+        assert_eq!(
+            x25519(&k, &u),
+            hex("c3da55379de9c6908e94ea4df28d084f\
+                 32eccf03491c71f754b4075577a28552")
+        );
+
+        let k = SecretKey::new(hex(
+            "4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d",
+        ));
+        let u = hex(
+            "e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493",
+        );
+        assert_eq!(
+            x25519(&k, &u),
+            hex("95cbde9476e8907d7aade45cb4b873f8\
+                 8b595a68799fa152e6f8f7647aac7957")
+        );
+    }
+
+    /// Applies the iteration of section 5.2 `n` times
+    fn iterate(n: u32) -> [u8; 32]
+    {
+        let mut k = X25519_BASEPOINT;
+        let mut u = X25519_BASEPOINT;
+
+        for _ in 0..n
+        {
+            let next = x25519(&SecretKey::new(k), &u);
+            u = k;
+            k = next;
+        }
+
+        k
+    }
+
+    #[test]
+    fn iteration_test()
+    {
+        // Section 5.2, without the million iterations
+        assert_eq!(
+            iterate(1),
+            hex("422c8e7a6227d7bca1350b3e2bb7279f\
+                 7897b87bb6854b783c60e80311ae3079")
+        );
+        assert_eq!(
+            iterate(1000),
+            hex("684cf59ba83309552800ef566f2f4d3c\
+                 1c3887c49360e3875f2eb94d99532c51")
+        );
+    }
+
+    #[test]
+    #[ignore = "takes long, run it with --ignored in release mode"]
+    fn million_iteration_test()
+    {
+        // Section 5.2
+        assert_eq!(
+            iterate(1_000_000),
+            hex("7c3911e0ab2586fd864497297e575e6f\
+                 3bc601c0883c30df5f4dd2d24f665424")
+        );
+    }
+
+    #[test]
+    fn diffie_hellman_test() -> Result<(), CurveError>
+    {
+        // Section 6.1
+        let alice = X25519SecretKey::from(SecretKey::new(hex(
+            "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a",
+        )));
+        let bob = X25519SecretKey::from(SecretKey::new(hex(
+            "5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb",
+        )));
+
+        assert_eq!(
+            alice.public_key().as_bytes(),
+            &hex("8520f0098930a754748b7ddcb43ef75a\
+                  0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            bob.public_key().as_bytes(),
+            &hex("de9edb7d7b7dc1b4d35b61c2ece43537\
+                  3f8343c85b78674dadfc7e146f882b4f")
+        );
+
+        let shared = hex(
+            "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742",
+        );
+        assert_eq!(alice.diffie_hellman(&bob.public_key())?.expose(), &shared);
+        assert_eq!(bob.diffie_hellman(&alice.public_key())?.expose(), &shared);
+
+        // u = 0 has order 1.
+        assert!(matches!(
+            alice.diffie_hellman(&[0; 32].into()),
+            Err(CurveError::LowOrder)
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,436 @@
+/*
+ * evilaes – Unsecure AES 256-bit implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![warn(
+    missing_docs,
+    missing_debug_implementations,
+    missing_copy_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_import_braces,
+    unused_qualifications,
+    rust_2018_idioms,
+    clippy::all,
+    clippy::pedantic,
+    clippy::nursery,
+    clippy::cargo
+)]
+#![allow(clippy::suspicious_else_formatting)]
+
+//! Encrypts and decrypts files with AES-256-GCM
+//!
+//! The files are evilaes containers, so they are streamed in chunks
+//! and any size works.  The key is either derived from a passphrase,
+//! which is read from the terminal without echo, or read from a file
+//! with exactly 32 random bytes (e.g. from `head -c 32 /dev/urandom`).
+//! The nonces come from evilrng.
+//!
+//! ```text
+//! evilaes encrypt [--key-file KEY] [--iterations N] [--output OUT] [IN]
+//! evilaes decrypt [--key-file KEY] [--output OUT] [IN]
+//! ```
+//!
+//! Without `IN` or `OUT` (or with `-`) stdin or stdout is used.  The
+//! output is written to a temporary file first, so nothing is left if
+//! something fails; when decrypting to stdout, the plain text is
+//! only printed after the whole file has been authenticated.  Like
+//! everything else here it's **unsecure**, so don't use it for backups
+//! you care about.
+
+use std::convert::TryFrom;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use evilaes::{
+    ContainerReader, ContainerWriter, SecretKey, DEFAULT_ITERATIONS,
+    MAX_ITERATIONS,
+};
+use evilrng::RngSource;
+use evilsecret::wipe;
+
+/// A passphrase that's overwritten with zeros when it's dropped
+struct Passphrase(Vec<u8>);
+
+impl Drop for Passphrase
+{
+    fn drop(&mut self)
+    {
+        self.0.resize(self.0.capacity(), 0);
+        wipe(self.0.as_mut_slice());
+    }
+}
+
+enum Key
+{
+    File(SecretKey<32>),
+    Passphrase(Passphrase),
+}
+
+struct Options
+{
+    encrypt: bool,
+    key_file: Option<PathBuf>,
+    iterations: u32,
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+}
+
+/// Turns echo on again when it's dropped
+#[cfg(unix)]
+struct NoEcho(File);
+
+#[cfg(unix)]
+impl NoEcho
+{
+    fn new(tty: &File) -> io::Result<Self>
+    {
+        let rv = Self(tty.try_clone()?);
+        stty(tty, "-echo")?;
+
+        Ok(rv)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for NoEcho
+{
+    fn drop(&mut self)
+    {
+        // If this fails, there's nothing left to do.
+        let _ = stty(&self.0, "echo");
+    }
+}
+
+#[cfg(unix)]
+fn stty(tty: &File, arg: &str) -> io::Result<()>
+{
+    let status = process::Command::new("stty")
+        .arg(arg)
+        .stdin(tty.try_clone()?)
+        .status()?;
+    if !status.success()
+    {
+        return Err(io::Error::other("stty failed"));
+    }
+
+    Ok(())
+}
+
+/// Reads a passphrase from the terminal without echoing it
+///
+/// It's read from `/dev/tty`, so that stdin is free for the data.
+#[cfg(unix)]
+fn read_passphrase(prompt: &str) -> io::Result<Passphrase>
+{
+    let mut tty =
+        OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let mut rv = Passphrase(Vec::with_capacity(256));
+    {
+        // Before the prompt, so that nothing typed early is shown
+        let _no_echo = NoEcho::new(&tty)?;
+        write!(tty, "{prompt}")?;
+        tty.flush()?;
+        // Byte by byte, so that no copy is left in a buffer
+        let mut byte = [0];
+        while tty.read(&mut byte)? == 1 && byte[0] != b'\n'
+        {
+            rv.0.push(byte[0]);
+        }
+        wipe(&mut byte);
+    }
+    writeln!(tty)?;
+
+    if rv.0.last() == Some(&b'\r')
+    {
+        rv.0.pop();
+    }
+
+    Ok(rv)
+}
+
+#[cfg(not(unix))]
+fn read_passphrase(_prompt: &str) -> io::Result<Passphrase>
+{
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Passphrases can only be read on unix, use --key-file",
+    ))
+}
+
+/// Reads the key file or asks for the passphrase
+fn get_key(options: &Options) -> io::Result<Key>
+{
+    if let Some(path) = &options.key_file
+    {
+        let mut bytes = fs::read(path)?;
+        let key = <&mut [u8; 32]>::try_from(bytes.as_mut_slice())
+            .map(SecretKey::from_mut);
+        wipe(bytes.as_mut_slice());
+
+        return key.map(Key::File).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The key file has to be exactly 32 bytes long",
+            )
+        });
+    }
+
+    let passphrase = read_passphrase("Passphrase: ")?;
+    if options.encrypt
+    {
+        if passphrase.0.is_empty()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The passphrase is empty",
+            ));
+        }
+        if read_passphrase("Repeat passphrase: ")?.0 != passphrase.0
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The passphrases don't match",
+            ));
+        }
+    }
+
+    Ok(Key::Passphrase(passphrase))
+}
+
+fn encrypt<W: Write>(
+    input: &mut dyn Read,
+    output: W,
+    key: &Key,
+    iterations: u32,
+) -> io::Result<W>
+{
+    let mut rng = RngSource::new().map_err(io::Error::other)?;
+    let mut writer = match key
+    {
+        Key::File(key) => ContainerWriter::with_key(output, key, &mut rng)?,
+        Key::Passphrase(passphrase) => ContainerWriter::with_passphrase(
+            output,
+            &passphrase.0,
+            iterations,
+            &mut rng,
+        )?,
+    };
+    io::copy(input, &mut writer)?;
+
+    writer.finish()
+}
+
+fn decrypt<W: Write + ?Sized>(
+    input: &mut dyn Read,
+    output: &mut W,
+    key: &Key,
+) -> io::Result<()>
+{
+    match key
+    {
+        Key::File(key) =>
+        {
+            io::copy(&mut ContainerReader::with_key(input, key)?, output)?;
+        }
+        Key::Passphrase(passphrase) =>
+        {
+            io::copy(
+                &mut ContainerReader::with_passphrase(input, &passphrase.0)?,
+                output,
+            )?;
+        }
+    }
+
+    output.flush()
+}
+
+/// Creates a new file at `path` that only its owner can read
+///
+/// A leftover of a crash is removed first.
+fn create_private(path: &Path) -> io::Result<File>
+{
+    match fs::remove_file(path)
+    {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => (),
+    }
+
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    options.open(path)
+}
+
+/// Writes `path` by `write`ing a temporary file next to it and
+/// renaming that only if everything worked
+fn write_through_temp<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".evilaes-tmp");
+    let temp = path.with_file_name(name);
+
+    let mut file = create_private(&temp)?;
+    let rv = write(&mut file)
+        .and_then(|()| file.sync_all())
+        .and_then(|()| fs::rename(&temp, path));
+    if rv.is_err()
+    {
+        // The error of `write` is more interesting.
+        let _ = fs::remove_file(&temp);
+    }
+
+    rv
+}
+
+/// Decrypts to stdout, but only after everything has been
+/// authenticated
+///
+/// The plain text is collected in a temporary file in the meantime.
+fn decrypt_to_stdout(input: &mut dyn Read, key: &Key) -> io::Result<()>
+{
+    let temp = env::temp_dir().join(format!("evilaes-{}.tmp", process::id()));
+    let mut file = create_private(&temp)?;
+    let rv = decrypt(input, &mut file, key)
+        .and_then(|()| file.seek(SeekFrom::Start(0)))
+        .and_then(|_| {
+            let mut stdout = io::stdout().lock();
+            io::copy(&mut file, &mut stdout)?;
+            stdout.flush()
+        });
+    // Whatever happened, the plain text shouldn't stay there.
+    let _ = fs::remove_file(&temp);
+
+    rv
+}
+
+fn run(options: &Options) -> io::Result<()>
+{
+    let mut input: Box<dyn Read> = match &options.input
+    {
+        Some(path) => Box::new(File::open(path)?),
+        None => Box::new(io::stdin().lock()),
+    };
+    let key = get_key(options)?;
+
+    match (&options.output, options.encrypt)
+    {
+        (Some(path), true) => write_through_temp(path, |file| {
+            encrypt(&mut input, file, &key, options.iterations).map(|_| ())
+        }),
+        (Some(path), false) =>
+        {
+            write_through_temp(path, |file| decrypt(&mut input, file, &key))
+        }
+        (None, true) =>
+        {
+            let stdout = io::stdout().lock();
+            encrypt(&mut input, stdout, &key, options.iterations)?.flush()
+        }
+        (None, false) => decrypt_to_stdout(&mut input, &key),
+    }
+}
+
+fn usage() -> !
+{
+    eprintln!(
+        "Usage: evilaes encrypt [--key-file KEY] [--iterations N] [--output \
+         OUT] [IN]\n       evilaes decrypt [--key-file KEY] [--output OUT] \
+         [IN]"
+    );
+    process::exit(2);
+}
+
+fn parse_args() -> Options
+{
+    let mut args = env::args_os().skip(1);
+    let encrypt = match args.next().as_ref().and_then(|x| x.to_str())
+    {
+        Some("encrypt") => true,
+        Some("decrypt") => false,
+        _ => usage(),
+    };
+    let mut options = Options {
+        encrypt,
+        key_file: None,
+        iterations: DEFAULT_ITERATIONS,
+        input: None,
+        output: None,
+    };
+
+    // `-` is stdin or stdout, like nothing.
+    let path = |arg: Option<OsString>| match arg
+    {
+        Some(arg) if arg == "-" => None,
+        Some(arg) => Some(PathBuf::from(arg)),
+        None => usage(),
+    };
+    let mut input = None;
+    while let Some(arg) = args.next()
+    {
+        match arg.to_str()
+        {
+            Some("--key-file") =>
+            {
+                options.key_file =
+                    Some(args.next().map_or_else(|| usage(), PathBuf::from));
+            }
+            Some("--iterations") if encrypt =>
+            {
+                match args.next().and_then(|x| x.to_str()?.parse().ok())
+                {
+                    Some(x) if (1..=MAX_ITERATIONS).contains(&x) =>
+                    {
+                        options.iterations = x;
+                    }
+                    _ => usage(),
+                }
+            }
+            Some("--output") => options.output = path(args.next()),
+            Some(x) if x.starts_with("--") => usage(),
+            _ if input.is_none() => input = Some(arg),
+            _ => usage(),
+        }
+    }
+    options.input = input.and_then(|x| path(Some(x)));
+
+    options
+}
+
+fn main()
+{
+    let options = parse_args();
+
+    if let Err(err) = run(&options)
+    {
+        eprintln!("evilaes: {err}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,882 @@
+/*
+ * evilaes – Unsecure AES 256-bit implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! ChaCha20, Poly1305 and their AEAD (RFC 8439), and XChaCha20
+//!
+//! Unlike AES there are no tables here, only additions, rotations and
+//! XORs, so it should be constant time without any tricks.
+
+use std::convert::TryInto;
+use std::fmt;
+
+use evilrng::{Random, RngError};
+use evilsecret::{barrier, wipe};
+use eviltraits::Mac;
+
+use crate::{AesError, Nonce, SecretKey};
+
+// "expand 32-byte k"
+const CONSTANTS: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+const fn quarter_round(
+    state: &mut [u32; 16],
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize,
+)
+{
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+// The initial state with the key and the last four words
+fn initial_state(key: &[u8; 32], last: &[u8; 16]) -> [u32; 16]
+{
+    let mut state = [0; 16];
+    state[..4].copy_from_slice(&CONSTANTS);
+
+    for (w, bytes) in state[4..]
+        .iter_mut()
+        .zip(key.chunks_exact(4).chain(last.chunks_exact(4)))
+    {
+        *w = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+
+    state
+}
+
+// The 20 rounds, as ten double rounds
+fn rounds(state: &mut [u32; 16])
+{
+    for _ in 0..10
+    {
+        quarter_round(state, 0, 4, 8, 12);
+        quarter_round(state, 1, 5, 9, 13);
+        quarter_round(state, 2, 6, 10, 14);
+        quarter_round(state, 3, 7, 11, 15);
+        quarter_round(state, 0, 5, 10, 15);
+        quarter_round(state, 1, 6, 11, 12);
+        quarter_round(state, 2, 7, 8, 13);
+        quarter_round(state, 3, 4, 9, 14);
+    }
+}
+
+// The block function (RFC 8439, 2.3)
+fn block(key: &[u8; 32], counter: u32, nonce: &[u8; 12]) -> [u8; 64]
+{
+    let mut last = [0; 16];
+    last[..4].copy_from_slice(&counter.to_le_bytes());
+    last[4..].copy_from_slice(nonce);
+
+    let mut state = initial_state(key, &last);
+    let mut working = state;
+    rounds(&mut working);
+
+    let mut rv = [0; 64];
+    for ((out, w), s) in rv.chunks_exact_mut(4).zip(&working).zip(&state)
+    {
+        out.copy_from_slice(&w.wrapping_add(*s).to_le_bytes());
+    }
+
+    wipe(&mut state);
+    wipe(&mut working);
+
+    rv
+}
+
+fn chacha20_xor(
+    buffer: &mut [u8],
+    key: &[u8; 32],
+    nonce: &[u8; 12],
+    counter: u32,
+)
+{
+    let mut counter = counter;
+
+    for chunk in buffer.chunks_mut(64)
+    {
+        let mut keystream = block(key, counter, nonce);
+
+        for (v, k) in chunk.iter_mut().zip(&keystream)
+        {
+            *v ^= k;
+        }
+
+        wipe(&mut keystream);
+        counter = counter.wrapping_add(1);
+    }
+}
+
+/// Encrypts in place insecurely via ChaCha20
+///
+/// Encrypts `buffer` in place with the ChaCha20 stream cipher (RFC
+/// 8439, 2.4) using `key` and `nonce`, starting with the block
+/// `counter`.  Decrypting is exactly the same operation.  This doesn't
+/// allocate.  Since this isn't authenticated and not cryptographical
+/// secure, **do not use!**
+pub fn chacha20_in_place(
+    buffer: &mut [u8],
+    key: &SecretKey<32>,
+    nonce: &Nonce<12>,
+    counter: u32,
+)
+{
+    chacha20_xor(buffer, key.expose(), nonce.expose(), counter);
+}
+
+/// Derives a subkey with HChaCha20
+///
+/// HChaCha20 is ChaCha20 without the final addition, where only the
+/// first and the last row of the state are used.  It's needed for
+/// XChaCha20, which uses it on the first 16 bytes of its nonce.
+#[must_use]
+pub fn hchacha20(key: &SecretKey<32>, nonce: &Nonce<16>) -> SecretKey<32>
+{
+    let mut state = initial_state(key.expose(), nonce.expose());
+    rounds(&mut state);
+
+    let mut rv = SecretKey::new([0; 32]);
+    for (out, w) in rv
+        .expose_mut()
+        .chunks_exact_mut(4)
+        .zip(state[..4].iter().chain(&state[12..]))
+    {
+        out.copy_from_slice(&w.to_le_bytes());
+    }
+
+    wipe(&mut state);
+
+    rv
+}
+
+// The subkey and the ChaCha20 nonce for a XChaCha20 nonce
+fn xchacha20_subkey(
+    key: &SecretKey<32>,
+    nonce: &Nonce<24>,
+) -> (SecretKey<32>, Nonce<12>)
+{
+    let mut first = [0; 16];
+    first.copy_from_slice(&nonce.expose()[..16]);
+    let mut rest = [0; 12];
+    rest[4..].copy_from_slice(&nonce.expose()[16..]);
+
+    (hchacha20(key, &Nonce::new(first)), Nonce::new(rest))
+}
+
+/// Encrypts in place insecurely via XChaCha20
+///
+/// Like [`chacha20_in_place`], but with a 192-bit nonce, which is long
+/// enough to be chosen randomly without worrying about collisions.
+/// Since this isn't authenticated and not cryptographical secure, **do
+/// not use!**
+pub fn xchacha20_in_place(
+    buffer: &mut [u8],
+    key: &SecretKey<32>,
+    nonce: &Nonce<24>,
+    counter: u32,
+)
+{
+    let (subkey, nonce) = xchacha20_subkey(key, nonce);
+
+    chacha20_in_place(buffer, &subkey, &nonce, counter);
+}
+
+/// Generates a random nonce for XChaCha20
+///
+/// # Errors
+/// It returns an error if no entropy could be read.
+pub fn xchacha20_get_nonce<R: Random + ?Sized>(
+    rng: &mut R,
+) -> Result<Nonce<24>, RngError>
+{
+    let mut nonce = Nonce::new([0; 24]);
+    rng.fill_bytes(nonce.expose_mut())?;
+
+    Ok(nonce)
+}
+
+const MASK_44: u64 = (1 << 44) - 1;
+const MASK_42: u64 = (1 << 42) - 1;
+
+fn load_u64(bytes: &[u8]) -> u64
+{
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+/// Insecure Poly1305
+///
+/// Poly1305 (RFC 8439, 2.5) as streaming [`Mac`]; see also
+/// [`poly1305`].  It's a one-time authenticator, so **never** use the
+/// same key for two messages.  This is not cryptographical secure, **do
+/// not use!**
+// The accumulator is in limbs of 44, 44 and 42 bits, so that the
+// products fit in a `u128`.
+pub struct Poly1305
+{
+    r: [u64; 3],
+    h: [u64; 3],
+    s: [u64; 2],
+    buffer: [u8; 16],
+    filled: usize,
+}
+
+impl Poly1305
+{
+    /// Starts a new message with the one-time `key`
+    #[must_use]
+    pub fn new(key: &SecretKey<32>) -> Self
+    {
+        Self::from_bytes(key.expose())
+    }
+
+    fn from_bytes(key: &[u8; 32]) -> Self
+    {
+        let t0 = load_u64(&key[0..]);
+        let t1 = load_u64(&key[8..]);
+
+        // Includes the clamping of r.
+        Self {
+            r: [
+                t0 & 0xffc0fffffff,
+                ((t0 >> 44) | (t1 << 20)) & 0xfffffc0ffff,
+                (t1 >> 24) & 0x00ffffffc0f,
+            ],
+            h: [0; 3],
+            s: [load_u64(&key[16..]), load_u64(&key[24..])],
+            buffer: [0; 16],
+            filled: 0,
+        }
+    }
+
+    // Processes one block; `high` is 2^128 scaled to the top limb for
+    // whole blocks and zero for the padded last one.
+    fn block(&mut self, block: &[u8; 16], high: u64)
+    {
+        let [r0, r1, r2] = self.r.map(u128::from);
+        // 2^130 = 5, and the top limb is 2 bits shorter
+        let (s1, s2) = (r1 * 20, r2 * 20);
+        let t0 = load_u64(&block[0..]);
+        let t1 = load_u64(&block[8..]);
+
+        let h0 = u128::from(self.h[0] + (t0 & MASK_44));
+        let h1 = u128::from(self.h[1] + (((t0 >> 44) | (t1 << 20)) & MASK_44));
+        let h2 = u128::from(self.h[2] + (((t1 >> 24) & MASK_42) | high));
+
+        let d0 = h0 * r0 + h1 * s2 + h2 * s1;
+        let mut d1 = h0 * r1 + h1 * r0 + h2 * s2;
+        let mut d2 = h0 * r2 + h1 * r1 + h2 * r0;
+
+        d1 += d0 >> 44;
+        d2 += d1 >> 44;
+        let mut h0 = (d0 as u64 & MASK_44) + (d2 >> 42) as u64 * 5;
+        let h1 = (d1 as u64 & MASK_44) + (h0 >> 44);
+        h0 &= MASK_44;
+
+        self.h = [h0, h1, d2 as u64 & MASK_42];
+    }
+
+    // Fills the current block with zeros, like the AEAD wants it after
+    // the AAD and the cipher text.
+    fn pad(&mut self)
+    {
+        if self.filled > 0
+        {
+            self.update(&[0; 16][self.filled..]);
+        }
+    }
+}
+
+impl Mac for Poly1305
+{
+    type Tag = [u8; 16];
+
+    fn update(&mut self, mut data: &[u8])
+    {
+        if self.filled > 0
+        {
+            let len = data.len().min(16 - self.filled);
+            self.buffer[self.filled..self.filled + len]
+                .copy_from_slice(&data[..len]);
+            self.filled += len;
+            data = &data[len..];
+
+            if self.filled < 16
+            {
+                return;
+            }
+            let block = self.buffer;
+            self.block(&block, 1 << 40);
+            self.filled = 0;
+        }
+
+        let mut chunks = data.chunks_exact(16);
+        for chunk in &mut chunks
+        {
+            self.block(chunk.try_into().unwrap(), 1 << 40);
+        }
+
+        let rest = chunks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.filled = rest.len();
+    }
+
+    fn finish(mut self) -> [u8; 16]
+    {
+        if self.filled > 0
+        {
+            let mut block = [0; 16];
+            block[..self.filled].copy_from_slice(&self.buffer[..self.filled]);
+            block[self.filled] = 1;
+            self.block(&block, 0);
+            wipe(&mut block);
+        }
+
+        // Fully carries h …
+        let [mut h0, mut h1, mut h2] = self.h;
+        for _ in 0..2
+        {
+            h1 += h0 >> 44;
+            h0 &= MASK_44;
+            h2 += h1 >> 44;
+            h1 &= MASK_44;
+            h0 += (h2 >> 42) * 5;
+            h2 &= MASK_42;
+        }
+        h1 += h0 >> 44;
+        h0 &= MASK_44;
+
+        // … and subtracts p if h + 5 - 2^130 doesn't underflow.
+        let mut g0 = h0 + 5;
+        let mut g1 = h1 + (g0 >> 44);
+        g0 &= MASK_44;
+        let g2 = (h2 + (g1 >> 44)).wrapping_sub(1 << 42);
+        g1 &= MASK_44;
+        let mask = (g2 >> 63).wrapping_sub(1);
+        h0 = (h0 & !mask) | (g0 & mask);
+        h1 = (h1 & !mask) | (g1 & mask);
+        h2 = (h2 & !mask) | (g2 & mask);
+
+        // Adds s modulo 2^128.
+        let h = u128::from(h0) | u128::from(h1) << 44 | u128::from(h2) << 88;
+        let s = u128::from(self.s[0]) | u128::from(self.s[1]) << 64;
+
+        h.wrapping_add(s).to_le_bytes()
+    }
+}
+
+impl Drop for Poly1305
+{
+    fn drop(&mut self)
+    {
+        wipe(&mut self.r);
+        wipe(&mut self.h);
+        wipe(&mut self.s);
+        wipe(&mut self.buffer);
+    }
+}
+
+impl fmt::Debug for Poly1305
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        write!(fmt, "Poly1305([REDACTED])")
+    }
+}
+
+/// Authenticates `mes` insecurely via Poly1305
+///
+/// Poly1305 (RFC 8439, 2.5) is a one-time authenticator, so **never**
+/// use the same `key` for two messages.  This is not cryptographical
+/// secure, **do not use!**
+#[must_use]
+pub fn poly1305(mes: &[u8], key: &SecretKey<32>) -> [u8; 16]
+{
+    let mut poly = Poly1305::new(key);
+    poly.update(mes);
+
+    poly.finish()
+}
+
+// The Poly1305 tag of the AEAD (RFC 8439, 2.8), with the one-time key
+// from the first block
+fn aead_tag(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], c: &[u8])
+    -> [u8; 16]
+{
+    let mut one_time_key = [0; 32];
+    let mut first = block(key, 0, nonce);
+    one_time_key.copy_from_slice(&first[..32]);
+    wipe(&mut first);
+
+    let mut poly = Poly1305::from_bytes(&one_time_key);
+    wipe(&mut one_time_key);
+
+    let mut lengths = [0; 16];
+    lengths[..8].copy_from_slice(&(aad.len() as u64).to_le_bytes());
+    lengths[8..].copy_from_slice(&(c.len() as u64).to_le_bytes());
+
+    poly.update(aad);
+    poly.pad();
+    poly.update(c);
+    poly.pad();
+    poly.update(&lengths);
+
+    poly.finish()
+}
+
+// Compares two tags without leaving early at the first difference.
+fn tags_equal(a: &[u8; 16], b: &[u8; 16]) -> bool
+{
+    barrier(a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b))) == 0
+}
+
+/// Encrypts insecurely via ChaCha20-Poly1305
+///
+/// This encrypts `input` and authenticates `input` and `aad`
+/// **insecurely** via the ChaCha20-Poly1305 AEAD (RFC 8439) using
+/// `key` and `nonce`.  This is not cryptographical secure, **do not
+/// use!**
+#[must_use]
+pub fn chacha20_poly1305_encrypt(
+    input: &[u8],
+    aad: &[u8],
+    key: &SecretKey<32>,
+    nonce: &Nonce<12>,
+) -> (Vec<u8>, [u8; 16])
+{
+    let mut c = input.to_vec();
+    let t = chacha20_poly1305_encrypt_in_place(&mut c, aad, key, nonce);
+
+    (c, t)
+}
+
+/// Decrypts insecurely via ChaCha20-Poly1305
+///
+/// This decrypts `c` and authenticates `c` and `aad` **insecurely**
+/// via the ChaCha20-Poly1305 AEAD (RFC 8439) using `key` and `nonce`.
+/// This is not cryptographical secure, **do not use!**
+///
+/// # Errors
+/// Returns an error if the verification couldn't succeeds.  **Note:**
+/// This is **not** cryptographical **secure**.  **Do not** rely one
+/// the correctness of the verification.
+pub fn chacha20_poly1305_decrypt(
+    c: &[u8],
+    aad: &[u8],
+    key: &SecretKey<32>,
+    nonce: &Nonce<12>,
+    tag: [u8; 16],
+) -> Result<Vec<u8>, AesError>
+{
+    let mut p = c.to_vec();
+    let verified =
+        chacha20_poly1305_decrypt_in_place(&mut p, aad, key, nonce, tag);
+
+    verified.map(|()| p)
+}
+
+/// Encrypts in place insecurely via ChaCha20-Poly1305
+///
+/// Like [`chacha20_poly1305_encrypt`], but overwrites `buffer` with
+/// the cipher text and only returns the (detached) tag.  This doesn't
+/// allocate.  This is not cryptographical secure, **do not use!**
+#[must_use]
+pub fn chacha20_poly1305_encrypt_in_place(
+    buffer: &mut [u8],
+    aad: &[u8],
+    key: &SecretKey<32>,
+    nonce: &Nonce<12>,
+) -> [u8; 16]
+{
+    chacha20_xor(buffer, key.expose(), nonce.expose(), 1);
+
+    aead_tag(key.expose(), nonce.expose(), aad, buffer)
+}
+
+/// Decrypts in place insecurely via ChaCha20-Poly1305
+///
+/// Like [`chacha20_poly1305_decrypt`], but overwrites `buffer` with
+/// the plain text and takes the tag detached.  This doesn't allocate.
+/// The tag is verified before anything is decrypted, so if the
+/// verification fails, `buffer` still contains the cipher text.  This
+/// is not cryptographical secure, **do not use!**
+///
+/// # Errors
+/// Returns an error if the verification couldn't succeeds.  **Note:**
+/// This is **not** cryptographical **secure**.  **Do not** rely one
+/// the correctness of the verification.
+pub fn chacha20_poly1305_decrypt_in_place(
+    buffer: &mut [u8],
+    aad: &[u8],
+    key: &SecretKey<32>,
+    nonce: &Nonce<12>,
+    tag: [u8; 16],
+) -> Result<(), AesError>
+{
+    let t = aead_tag(key.expose(), nonce.expose(), aad, buffer);
+
+    if tags_equal(&t, &tag)
+    {
+        chacha20_xor(buffer, key.expose(), nonce.expose(), 1);
+
+        Ok(())
+    }
+    else
+    {
+        Err(AesError::VerifyingError)
+    }
+}
+
+/// Encrypts insecurely via XChaCha20-Poly1305
+///
+/// Like [`chacha20_poly1305_encrypt`], but with a 192-bit nonce, which
+/// can be generated with [`xchacha20_get_nonce`].  This is not
+/// cryptographical secure, **do not use!**
+#[must_use]
+pub fn xchacha20_poly1305_encrypt(
+    input: &[u8],
+    aad: &[u8],
+    key: &SecretKey<32>,
+    nonce: &Nonce<24>,
+) -> (Vec<u8>, [u8; 16])
+{
+    let (subkey, nonce) = xchacha20_subkey(key, nonce);
+
+    chacha20_poly1305_encrypt(input, aad, &subkey, &nonce)
+}
+
+/// Decrypts insecurely via XChaCha20-Poly1305
+///
+/// Like [`chacha20_poly1305_decrypt`], but with a 192-bit nonce.  This
+/// is not cryptographical secure, **do not use!**
+///
+/// # Errors
+/// Returns an error if the verification couldn't succeeds.  **Note:**
+/// This is **not** cryptographical **secure**.  **Do not** rely one
+/// the correctness of the verification.
+pub fn xchacha20_poly1305_decrypt(
+    c: &[u8],
+    aad: &[u8],
+    key: &SecretKey<32>,
+    nonce: &Nonce<24>,
+    tag: [u8; 16],
+) -> Result<Vec<u8>, AesError>
+{
+    let (subkey, nonce) = xchacha20_subkey(key, nonce);
+
+    chacha20_poly1305_decrypt(c, aad, &subkey, &nonce, tag)
+}
+
+/// Encrypts in place insecurely via XChaCha20-Poly1305
+///
+/// Like [`xchacha20_poly1305_encrypt`], but in place.  This is not
+/// cryptographical secure, **do not use!**
+#[must_use]
+pub fn xchacha20_poly1305_encrypt_in_place(
+    buffer: &mut [u8],
+    aad: &[u8],
+    key: &SecretKey<32>,
+    nonce: &Nonce<24>,
+) -> [u8; 16]
+{
+    let (subkey, nonce) = xchacha20_subkey(key, nonce);
+
+    chacha20_poly1305_encrypt_in_place(buffer, aad, &subkey, &nonce)
+}
+
+/// Decrypts in place insecurely via XChaCha20-Poly1305
+///
+/// Like [`xchacha20_poly1305_decrypt`], but in place; if the
+/// verification fails, `buffer` still contains the cipher text.  This
+/// is not cryptographical secure, **do not use!**
+///
+/// # Errors
+/// Returns an error if the verification couldn't succeeds.  **Note:**
+/// This is **not** cryptographical **secure**.  **Do not** rely one
+/// the correctness of the verification.
+pub fn xchacha20_poly1305_decrypt_in_place(
+    buffer: &mut [u8],
+    aad: &[u8],
+    key: &SecretKey<32>,
+    nonce: &Nonce<24>,
+    tag: [u8; 16],
+) -> Result<(), AesError>
+{
+    let (subkey, nonce) = xchacha20_subkey(key, nonce);
+
+    chacha20_poly1305_decrypt_in_place(buffer, aad, &subkey, &nonce, tag)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8>
+    {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn key(s: &str) -> SecretKey<32>
+    {
+        SecretKey::new(hex(s).try_into().unwrap())
+    }
+
+    fn counting_key(start: u8) -> SecretKey<32>
+    {
+        let mut key = SecretKey::new([0; 32]);
+        for (i, x) in (start..).zip(key.expose_mut())
+        {
+            *x = i;
+        }
+
+        key
+    }
+
+    const SUNSCREEN: &[u8] = b"Ladies and Gentlemen of the class of '99: \
+        If I could offer you only one tip for the future, sunscreen would \
+        be it.";
+
+    #[test]
+    fn quarter_round_test()
+    {
+        // RFC 8439, 2.1.1
+        let mut state = [0; 16];
+        state[..4].copy_from_slice(&[
+            0x11111111, 0x01020304, 0x9b8d6f43, 0x01234567,
+        ]);
+        quarter_round(&mut state, 0, 1, 2, 3);
+
+        assert_eq!(
+            state[..4],
+            [0xea2a92f4, 0xcb1cf8ce, 0x4581472e, 0x5881c4bb]
+        );
+    }
+
+    #[test]
+    fn block_test()
+    {
+        // RFC 8439, 2.3.2
+        let nonce = hex("000000090000004a00000000");
+
+        assert_eq!(
+            block(counting_key(0).expose(), 1, &nonce.try_into().unwrap())[..],
+            hex("10f1e7e4d13b5915500fdd1fa32071c4\
+                 c7d1f4c733c068030422aa9ac3d46c4e\
+                 d2826446079faa0914c2d705d98b02a2\
+                 b5129cd1de164eb9cbd083e8a2503c4e")
+        );
+    }
+
+    #[test]
+    fn chacha20_test()
+    {
+        // RFC 8439, 2.4.2
+        let nonce = Nonce::new([0, 0, 0, 0, 0, 0, 0, 0x4a, 0, 0, 0, 0]);
+        let c = hex("6e2e359a2568f98041ba0728dd0d6981\
+                     e97e7aec1d4360c20a27afccfd9fae0b\
+                     f91b65c5524733ab8f593dabcd62b357\
+                     1639d624e65152ab8f530c359f0861d8\
+                     07ca0dbf500d6a6156a38e088a22b65e\
+                     52bc514d16ccf806818ce91ab7793736\
+                     5af90bbf74a35be6b40b8eedf2785e42\
+                     874d");
+        let mut buffer = SUNSCREEN.to_vec();
+
+        chacha20_in_place(&mut buffer, &counting_key(0), &nonce, 1);
+        assert_eq!(buffer, c);
+        chacha20_in_place(&mut buffer, &counting_key(0), &nonce, 1);
+        assert_eq!(buffer, SUNSCREEN);
+    }
+
+    #[test]
+    fn poly1305_test()
+    {
+        // RFC 8439, 2.5.2
+        let k = key("85d6be7857556d337f4452fe42d506a8\
+                     0103808afb0db2fd4abff6af4149f51b");
+        let mes = b"Cryptographic Forum Research Group";
+        let tag = hex("a8061dc1305136c6c22b8baf0c0127a9");
+        assert_eq!(poly1305(mes, &k)[..], tag);
+
+        // Split in odd pieces
+        let mut poly = Poly1305::new(&k);
+        for chunk in mes.chunks(7)
+        {
+            poly.update(chunk);
+        }
+        assert_eq!(poly.finish()[..], tag);
+
+        // A few of the edge cases of RFC 8439, A.3
+        let edge = |k: &str, mes: &str, low: u8| {
+            let mut tag = [0; 16];
+            tag[0] = low;
+            assert_eq!(poly1305(&hex(mes), &key(k)), tag);
+        };
+        let zeros = |n| "0".repeat(n);
+        edge(&format!("02{}", zeros(62)), &"ff".repeat(16), 3);
+        edge(
+            &format!("02{}{}", zeros(30), "ff".repeat(16)),
+            &format!("02{}", zeros(30)),
+            3,
+        );
+        edge(
+            &format!("01{}", zeros(62)),
+            &format!(
+                "{}f0{}11{}",
+                "ff".repeat(16),
+                "ff".repeat(15),
+                zeros(30)
+            ),
+            5,
+        );
+    }
+
+    #[test]
+    fn aead_test()
+    {
+        // RFC 8439, 2.8.2
+        let k = counting_key(0x80);
+        let nonce = Nonce::new([
+            7, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+        ]);
+        let aad = hex("50515253c0c1c2c3c4c5c6c7");
+        let c = hex("d31a8d34648e60db7b86afbc53ef7ec2\
+                     a4aded51296e08fea9e2b5a736ee62d6\
+                     3dbea45e8ca9671282fafb69da92728b\
+                     1a71de0a9e060b2905d6a5b67ecd3b36\
+                     92ddbd7f2d778b8c9803aee328091b58\
+                     fab324e4fad675945585808b4831d7bc\
+                     3ff4def08e4b7a9de576d26586cec64b\
+                     6116");
+        let tag: [u8; 16] =
+            hex("1ae10b594f09e26a7e902ecbd0600691").try_into().unwrap();
+        let mut wrongtag = tag;
+        wrongtag[15] ^= 1;
+
+        assert_eq!(
+            chacha20_poly1305_encrypt(SUNSCREEN, &aad, &k, &nonce),
+            (c.clone(), tag)
+        );
+        assert_eq!(
+            chacha20_poly1305_decrypt(&c, &aad, &k, &nonce, tag),
+            Ok(SUNSCREEN.to_vec())
+        );
+        assert_eq!(
+            chacha20_poly1305_decrypt(&c, &aad, &k, &nonce, wrongtag),
+            Err(AesError::VerifyingError)
+        );
+        assert_eq!(
+            chacha20_poly1305_decrypt(&c, &aad[1..], &k, &nonce, tag),
+            Err(AesError::VerifyingError)
+        );
+
+        let mut buffer = c.clone();
+        assert_eq!(
+            chacha20_poly1305_decrypt_in_place(
+                &mut buffer,
+                &aad,
+                &k,
+                &nonce,
+                wrongtag
+            ),
+            Err(AesError::VerifyingError)
+        );
+        assert_eq!(buffer, c);
+    }
+
+    #[test]
+    fn xchacha20_test()
+    {
+        // draft-irtf-cfrg-xchacha-03, 2.2.1 and A.3.1
+        let mut nonce = [0; 16];
+        nonce.copy_from_slice(&hex("000000090000004a0000000031415927"));
+        assert_eq!(
+            hchacha20(&counting_key(0), &Nonce::new(nonce)).expose()[..],
+            hex("82413b4227b27bfed30e42508a877d73\
+                 a0f9e4d58a74a853c12ec41326d3ecdc")
+        );
+
+        let k = counting_key(0x80);
+        let mut nonce = [0; 24];
+        for (i, x) in (0x40..).zip(&mut nonce)
+        {
+            *x = i;
+        }
+        let nonce = Nonce::new(nonce);
+        let aad = hex("50515253c0c1c2c3c4c5c6c7");
+        let c = hex("bd6d179d3e83d43b9576579493c0e939\
+                     572a1700252bfaccbed2902c21396cbb\
+                     731c7f1b0b4aa6440bf3a82f4eda7e39\
+                     ae64c6708c54c216cb96b72e1213b452\
+                     2f8c9ba40db5d945b11b69b982c1bb9e\
+                     3f3fac2bc369488f76b2383565d3fff9\
+                     21f9664c97637da9768812f615c68b13\
+                     b52e");
+        let tag: [u8; 16] =
+            hex("c0875924c1c7987947deafd8780acf49").try_into().unwrap();
+
+        assert_eq!(
+            xchacha20_poly1305_encrypt(SUNSCREEN, &aad, &k, &nonce),
+            (c.clone(), tag)
+        );
+        assert_eq!(
+            xchacha20_poly1305_decrypt(&c, &aad, &k, &nonce, tag),
+            Ok(SUNSCREEN.to_vec())
+        );
+
+        let mut buffer = SUNSCREEN.to_vec();
+        let t =
+            xchacha20_poly1305_encrypt_in_place(&mut buffer, &aad, &k, &nonce);
+        assert_eq!((&buffer, t), (&c, tag));
+        let mut wrongtag = tag;
+        wrongtag[0] ^= 0x80;
+        assert_eq!(
+            xchacha20_poly1305_decrypt_in_place(
+                &mut buffer,
+                &aad,
+                &k,
+                &nonce,
+                wrongtag
+            ),
+            Err(AesError::VerifyingError)
+        );
+        xchacha20_poly1305_decrypt_in_place(
+            &mut buffer,
+            &aad,
+            &k,
+            &nonce,
+            tag,
+        )
+        .unwrap();
+        assert_eq!(buffer, SUNSCREEN);
+
+        let mut stream = SUNSCREEN.to_vec();
+        xchacha20_in_place(&mut stream, &k, &nonce, 1);
+        assert_eq!(stream, c);
+    }
+}
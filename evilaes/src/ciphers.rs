@@ -0,0 +1,423 @@
+/*
+ * evilaes – Unsecure AES 256-bit implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The ciphers as types with the traits of eviltraits
+//!
+//! The free functions take the key on every call; these types keep it
+//! (AES even the expanded one), and they all have the same interface.
+
+use std::convert::TryInto;
+use std::fmt;
+
+use evilsecret::wipe;
+use eviltraits::{Aead, BlockCipher, CipherError};
+
+use crate::{
+    aes_256_block_decrypt, aes_256_block_encrypt, aes_256_key_expansion,
+    chacha20_poly1305_decrypt_in_place, chacha20_poly1305_encrypt_in_place,
+    crypt_blocks, gcm_decrypt_in_place, gcm_encrypt_in_place, nonce_words,
+    xchacha20_poly1305_decrypt_in_place, xchacha20_poly1305_encrypt_in_place,
+    AesError, Nonce, SecretKey, AES_256_NB, AES_256_NR,
+};
+
+impl From<AesError> for CipherError
+{
+    fn from(err: AesError) -> Self
+    {
+        match err
+        {
+            AesError::NotWholeBlock => Self::NotWholeBlock,
+            AesError::VerifyingError => Self::Verification,
+        }
+    }
+}
+
+/// Insecure AES-256 as [`BlockCipher`]
+///
+/// The expanded key is overwritten with zeros when it's dropped.  Like
+/// [`aes_256_encrypt`](crate::aes_256_encrypt) this is **insecure**.
+#[derive(Clone)]
+pub struct Aes256
+{
+    longkey: [[u8; 4]; AES_256_NB * (AES_256_NR + 1)],
+}
+
+impl Aes256
+{
+    /// Expands `key`
+    #[must_use]
+    pub fn new(key: &SecretKey<32>) -> Self
+    {
+        Self {
+            longkey: aes_256_key_expansion(key.expose()),
+        }
+    }
+}
+
+impl BlockCipher for Aes256
+{
+    const BLOCK_LEN: usize = AES_256_NB * 4;
+
+    fn encrypt_blocks(&self, buffer: &mut [u8]) -> Result<(), CipherError>
+    {
+        if !buffer.len().is_multiple_of(Self::BLOCK_LEN)
+        {
+            return Err(CipherError::NotWholeBlock);
+        }
+        crypt_blocks(buffer, &self.longkey, &aes_256_block_encrypt);
+
+        Ok(())
+    }
+
+    fn decrypt_blocks(&self, buffer: &mut [u8]) -> Result<(), CipherError>
+    {
+        if !buffer.len().is_multiple_of(Self::BLOCK_LEN)
+        {
+            return Err(CipherError::NotWholeBlock);
+        }
+        crypt_blocks(buffer, &self.longkey, &aes_256_block_decrypt);
+
+        Ok(())
+    }
+}
+
+impl Drop for Aes256
+{
+    fn drop(&mut self)
+    {
+        wipe(&mut self.longkey);
+    }
+}
+
+impl fmt::Debug for Aes256
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        write!(fmt, "Aes256([REDACTED])")
+    }
+}
+
+/// Insecure AES-256 GCM as [`Aead`]
+///
+/// Does the same as [`aes_256_gcm_encrypt`](crate::aes_256_gcm_encrypt)
+/// and [`aes_256_gcm_decrypt`](crate::aes_256_gcm_decrypt), but the
+/// tag is given as 16 bytes instead of four big endian words.
+///
+/// ```
+/// # use evilaes::{Aead, Aes256Gcm, CipherError, Nonce, SecretKey};
+/// # fn main() -> Result<(), CipherError>
+/// # {
+/// let cipher = Aes256Gcm::new(&SecretKey::new([42; 32]));
+/// let nonce = Nonce::new([1; 12]);
+///
+/// let c = cipher.encrypt(b"evil", b"", &nonce);
+/// assert_eq!(c.len(), 4 + 16);
+/// assert_eq!(cipher.decrypt(&c, b"", &nonce)?, b"evil");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Aes256Gcm
+{
+    aes: Aes256,
+}
+
+impl Aes256Gcm
+{
+    /// Expands `key`
+    #[must_use]
+    pub fn new(key: &SecretKey<32>) -> Self
+    {
+        Self {
+            aes: Aes256::new(key),
+        }
+    }
+}
+
+impl Aead for Aes256Gcm
+{
+    type Nonce = Nonce<12>;
+    type Tag = [u8; 16];
+
+    const TAG_LEN: usize = 16;
+
+    fn encrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        aad: &[u8],
+        nonce: &Nonce<12>,
+    ) -> [u8; 16]
+    {
+        let t = gcm_encrypt_in_place(
+            &self.aes.longkey,
+            nonce_words(nonce),
+            aad,
+            buffer,
+        );
+
+        let mut rv = [0; 16];
+        for (bytes, word) in rv.chunks_exact_mut(4).zip(t)
+        {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+
+        rv
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        aad: &[u8],
+        nonce: &Nonce<12>,
+        tag: [u8; 16],
+    ) -> Result<(), CipherError>
+    {
+        let mut words = [0; 4];
+        for (word, bytes) in words.iter_mut().zip(tag.chunks_exact(4))
+        {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+
+        Ok(gcm_decrypt_in_place(
+            &self.aes.longkey,
+            nonce_words(nonce),
+            aad,
+            buffer,
+            words,
+        )?)
+    }
+}
+
+/// Insecure ChaCha20-Poly1305 as [`Aead`]
+///
+/// The same as
+/// [`chacha20_poly1305_encrypt`](crate::chacha20_poly1305_encrypt) and
+/// [`chacha20_poly1305_decrypt`](crate::chacha20_poly1305_decrypt).
+#[derive(Clone, Debug)]
+pub struct ChaCha20Poly1305
+{
+    key: SecretKey<32>,
+}
+
+impl ChaCha20Poly1305
+{
+    /// Copies `key`
+    #[must_use]
+    pub fn new(key: &SecretKey<32>) -> Self
+    {
+        Self { key: key.clone() }
+    }
+}
+
+impl Aead for ChaCha20Poly1305
+{
+    type Nonce = Nonce<12>;
+    type Tag = [u8; 16];
+
+    const TAG_LEN: usize = 16;
+
+    fn encrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        aad: &[u8],
+        nonce: &Nonce<12>,
+    ) -> [u8; 16]
+    {
+        chacha20_poly1305_encrypt_in_place(buffer, aad, &self.key, nonce)
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        aad: &[u8],
+        nonce: &Nonce<12>,
+        tag: [u8; 16],
+    ) -> Result<(), CipherError>
+    {
+        Ok(chacha20_poly1305_decrypt_in_place(
+            buffer, aad, &self.key, nonce, tag,
+        )?)
+    }
+}
+
+/// Insecure XChaCha20-Poly1305 as [`Aead`]
+///
+/// The same as
+/// [`xchacha20_poly1305_encrypt`](crate::xchacha20_poly1305_encrypt)
+/// and
+/// [`xchacha20_poly1305_decrypt`](crate::xchacha20_poly1305_decrypt).
+#[derive(Clone, Debug)]
+pub struct XChaCha20Poly1305
+{
+    key: SecretKey<32>,
+}
+
+impl XChaCha20Poly1305
+{
+    /// Copies `key`
+    #[must_use]
+    pub fn new(key: &SecretKey<32>) -> Self
+    {
+        Self { key: key.clone() }
+    }
+}
+
+impl Aead for XChaCha20Poly1305
+{
+    type Nonce = Nonce<24>;
+    type Tag = [u8; 16];
+
+    const TAG_LEN: usize = 16;
+
+    fn encrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        aad: &[u8],
+        nonce: &Nonce<24>,
+    ) -> [u8; 16]
+    {
+        xchacha20_poly1305_encrypt_in_place(buffer, aad, &self.key, nonce)
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        aad: &[u8],
+        nonce: &Nonce<24>,
+        tag: [u8; 16],
+    ) -> Result<(), CipherError>
+    {
+        Ok(xchacha20_poly1305_decrypt_in_place(
+            buffer, aad, &self.key, nonce, tag,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use eviltraits::{Aead, BlockCipher, CipherError, Mac};
+
+    use super::{Aes256, Aes256Gcm, ChaCha20Poly1305, XChaCha20Poly1305};
+    use crate::{
+        aes_256_encrypt, aes_256_gcm_encrypt, chacha20_poly1305_encrypt,
+        poly1305, Nonce, Poly1305, SecretKey,
+    };
+
+    // Everything that's needed to be generic over the cipher
+    fn roundtrip<A: Aead>(cipher: &A, nonce: &A::Nonce)
+    {
+        for len in [0, 1, 16, 33]
+        {
+            let p = vec![0x5a; len];
+            let c = cipher.encrypt(&p, b"aad", nonce);
+            assert_eq!(c.len(), len + A::TAG_LEN);
+            assert_eq!(cipher.decrypt(&c, b"aad", nonce), Ok(p.clone()));
+
+            let mut tampered = c.clone();
+            tampered[0] ^= 1;
+            assert_eq!(
+                cipher.decrypt(&tampered, b"aad", nonce),
+                Err(CipherError::Verification)
+            );
+            assert_eq!(
+                cipher.decrypt(&c, b"add", nonce),
+                Err(CipherError::Verification)
+            );
+
+            let mut buffer = p.clone();
+            let tag =
+                cipher.encrypt_in_place_detached(&mut buffer, &[], nonce);
+            assert_eq!(
+                cipher.decrypt_detached(&buffer, &[], nonce, tag),
+                Ok(p)
+            );
+        }
+    }
+
+    #[test]
+    fn aead_test()
+    {
+        let key = SecretKey::new([7; 32]);
+        let nonce = Nonce::new([9; 12]);
+
+        roundtrip(&Aes256Gcm::new(&key), &nonce);
+        roundtrip(&ChaCha20Poly1305::new(&key), &nonce);
+        roundtrip(&XChaCha20Poly1305::new(&key), &Nonce::new([9; 24]));
+
+        // The same as the free functions
+        let (c, tag) = aes_256_gcm_encrypt(b"evil", b"aad", &key, &nonce);
+        let mut combined = c;
+        for word in tag
+        {
+            combined.extend_from_slice(&word.to_be_bytes());
+        }
+        assert_eq!(
+            Aes256Gcm::new(&key).encrypt(b"evil", b"aad", &nonce),
+            combined
+        );
+
+        let (c, tag) =
+            chacha20_poly1305_encrypt(b"evil", b"aad", &key, &nonce);
+        assert_eq!(
+            ChaCha20Poly1305::new(&key)
+                .encrypt_detached(b"evil", b"aad", &nonce),
+            (c, tag)
+        );
+    }
+
+    #[test]
+    fn block_cipher_test() -> Result<(), CipherError>
+    {
+        let key = SecretKey::new([3; 32]);
+        let aes = Aes256::new(&key);
+        let mut buffer = [0x11; 48];
+
+        aes.encrypt_blocks(&mut buffer)?;
+        assert_eq!(Ok(buffer.to_vec()), aes_256_encrypt(&[0x11; 48], &key));
+        aes.decrypt_blocks(&mut buffer)?;
+        assert_eq!(buffer, [0x11; 48]);
+
+        assert_eq!(
+            aes.encrypt_blocks(&mut buffer[..17]),
+            Err(CipherError::NotWholeBlock)
+        );
+        assert_eq!(buffer, [0x11; 48]);
+        assert_eq!(format!("{aes:?}"), "Aes256([REDACTED])");
+
+        Ok(())
+    }
+
+    #[test]
+    fn mac_test()
+    {
+        let key = SecretKey::new([5; 32]);
+        let mut mac = Poly1305::new(&key);
+        mac.update(b"ev");
+        mac.update(b"il");
+
+        assert_eq!(mac.finish(), poly1305(b"evil", &key));
+
+        let mut mac = Poly1305::new(&key);
+        mac.update(b"evil");
+        assert!(mac.verify(&poly1305(b"evil", &key)).is_ok());
+        assert!(Poly1305::new(&key)
+            .verify(&poly1305(b"evil", &key))
+            .is_err());
+    }
+}
@@ -0,0 +1,902 @@
+/*
+ * evilaes – Unsecure AES 256-bit implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! An encrypted file format
+//!
+//! A container is a header followed by the data in chunks, which are
+//! each encrypted with AES-256-GCM on their own, so that files of any
+//! size can be streamed.  The header is (numbers in big endian):
+//!
+//! | Bytes | Content                                               |
+//! |------:|-------------------------------------------------------|
+//! |     8 | the magic number `evilaes\0`                          |
+//! |     1 | the version, currently 1                              |
+//! |     1 | the KDF, 0 for a raw key and 1 for PBKDF2-HMAC-SHA256 |
+//! |     4 | the PBKDF2 iterations, 0 for a raw key                |
+//! |    16 | a random salt                                         |
+//! |     4 | the length of the plain text of a chunk               |
+//! |     7 | a random nonce prefix                                 |
+//!
+//! The key of the container is derived from the salt and the
+//! passphrase with PBKDF2, or from the salt and the raw key as
+//! HMAC-SHA256(key, salt), so that even containers encrypted with the
+//! same key never share an AES-GCM key and nonces can't collide.
+//!
+//! Every chunk is the cipher text followed by the 16 byte tag.  Its
+//! nonce is the prefix, the number of the chunk (as `u32`) and a byte
+//! that is 1 for the last chunk and 0 otherwise, and the whole header
+//! is the additional data.  All chunks except the last one are full,
+//! the last one is shorter (maybe empty), so cutting off chunks or
+//! appending data makes the authentication fail.
+
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use evilrng::Random;
+use evilsecret::{wipe, Nonce, SecretKey};
+use evilsha::{hmac, pbkdf2, ShaHash, ShaVersion};
+use eviltraits::Aead;
+
+use crate::{Aes256Gcm, CipherError};
+
+/// The magic number at the start of every container
+pub const CONTAINER_MAGIC: [u8; 8] = *b"evilaes\0";
+/// The version of the container format that's written
+pub const CONTAINER_VERSION: u8 = 1;
+/// A sensible number of PBKDF2 iterations for
+/// [`ContainerWriter::with_passphrase`]
+pub const DEFAULT_ITERATIONS: u32 = 100_000;
+/// The most PBKDF2 iterations a container may have
+///
+/// More are refused, so that a crafted header can't keep the reader
+/// busy for hours.
+pub const MAX_ITERATIONS: u32 = 100 * DEFAULT_ITERATIONS;
+
+const HEADER_LEN: usize = 41;
+const TAG_LEN: usize = 16;
+/// The plain text per chunk the writer uses
+const CHUNK_LEN: u32 = 64 * 1024;
+/// Longer chunks are refused, so that a broken header can't make the
+/// reader allocate all the memory
+const MAX_CHUNK_LEN: u32 = 16 * 1024 * 1024;
+
+const KDF_NONE: u8 = 0;
+const KDF_PBKDF2_SHA256: u8 = 1;
+
+fn invalid(msg: &str) -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+struct Header
+{
+    kdf: u8,
+    iterations: u32,
+    salt: [u8; 16],
+    chunk_len: u32,
+    prefix: [u8; 7],
+}
+
+impl Header
+{
+    /// A header with a new random salt and nonce prefix
+    fn generate<R: Random + ?Sized>(
+        kdf: u8,
+        iterations: u32,
+        rng: &mut R,
+    ) -> io::Result<Self>
+    {
+        let mut rv = Self {
+            kdf,
+            iterations,
+            salt: [0; 16],
+            chunk_len: CHUNK_LEN,
+            prefix: [0; 7],
+        };
+        rng.fill_bytes(&mut rv.salt).map_err(io::Error::other)?;
+        rng.fill_bytes(&mut rv.prefix).map_err(io::Error::other)?;
+
+        Ok(rv)
+    }
+
+    fn encode(&self) -> [u8; HEADER_LEN]
+    {
+        let mut rv = [0; HEADER_LEN];
+        rv[..8].copy_from_slice(&CONTAINER_MAGIC);
+        rv[8] = CONTAINER_VERSION;
+        rv[9] = self.kdf;
+        rv[10..14].copy_from_slice(&self.iterations.to_be_bytes());
+        rv[14..30].copy_from_slice(&self.salt);
+        rv[30..34].copy_from_slice(&self.chunk_len.to_be_bytes());
+        rv[34..].copy_from_slice(&self.prefix);
+
+        rv
+    }
+
+    fn decode(bytes: &[u8; HEADER_LEN]) -> io::Result<Self>
+    {
+        if bytes[..8] != CONTAINER_MAGIC
+        {
+            return Err(invalid("Not an evilaes container"));
+        }
+        if bytes[8] != CONTAINER_VERSION
+        {
+            return Err(invalid("Unsupported container version"));
+        }
+
+        let rv = Self {
+            kdf: bytes[9],
+            iterations: u32::from_be_bytes(bytes[10..14].try_into().unwrap()),
+            salt: bytes[14..30].try_into().unwrap(),
+            chunk_len: u32::from_be_bytes(bytes[30..34].try_into().unwrap()),
+            prefix: bytes[34..].try_into().unwrap(),
+        };
+        if !matches!(
+            (rv.kdf, rv.iterations),
+            (KDF_NONE, 0) | (KDF_PBKDF2_SHA256, 1..=MAX_ITERATIONS)
+        )
+        {
+            return Err(invalid("Invalid key derivation parameters"));
+        }
+        if rv.chunk_len == 0 || rv.chunk_len > MAX_CHUNK_LEN
+        {
+            return Err(invalid("Invalid chunk length"));
+        }
+
+        Ok(rv)
+    }
+
+    fn read<R: Read>(inner: &mut R) -> io::Result<(Self, [u8; HEADER_LEN])>
+    {
+        let mut bytes = [0; HEADER_LEN];
+        inner.read_exact(&mut bytes)?;
+
+        Ok((Self::decode(&bytes)?, bytes))
+    }
+
+    /// Derives the key from `passphrase` with PBKDF2-HMAC-SHA256
+    fn derive_key(&self, passphrase: &[u8]) -> SecretKey<32>
+    {
+        let mut key = [0; 32];
+        pbkdf2(
+            passphrase,
+            &self.salt,
+            self.iterations,
+            ShaVersion::Sha256,
+            &mut key,
+        );
+
+        SecretKey::from_mut(&mut key)
+    }
+
+    /// Derives the key of this container from a raw `key`
+    fn derive_raw_key(&self, key: &SecretKey<32>) -> SecretKey<32>
+    {
+        match hmac(key.expose(), &self.salt, ShaVersion::Sha256)
+        {
+            ShaHash::Sha256(mut key) => SecretKey::from_mut(&mut key),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The nonce of the chunk number `counter`
+fn chunk_nonce(
+    prefix: [u8; 7],
+    counter: u64,
+    last: bool,
+) -> io::Result<Nonce<12>>
+{
+    let counter: u32 = counter
+        .try_into()
+        .map_err(|_| io::Error::other("Too many chunks"))?;
+
+    let mut rv = Nonce::new([0; 12]);
+    let nonce = rv.expose_mut();
+    nonce[..7].copy_from_slice(&prefix);
+    nonce[7..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = u8::from(last);
+
+    Ok(rv)
+}
+
+/// Plain text that's overwritten with zeros when it's dropped
+///
+/// The whole capacity is wiped, since the length changes all the time.
+struct Plain(Vec<u8>);
+
+impl Drop for Plain
+{
+    fn drop(&mut self)
+    {
+        self.0.resize(self.0.capacity(), 0);
+        wipe(self.0.as_mut_slice());
+    }
+}
+
+/// Writes an encrypted container
+///
+/// The data is collected until a chunk is full, which is then
+/// encrypted and written.  The last chunk is only written by
+/// [`finish`](Self::finish); dropping the writer without finishing it
+/// leaves a truncated container that can't be read.  After an error
+/// of the inner writer the container is broken, too.
+///
+/// ```
+/// # use std::io::{self, Read, Write};
+/// # use evilaes::{ContainerReader, ContainerWriter};
+/// # use evilrng::RngSource;
+/// # fn main() -> io::Result<()>
+/// # {
+/// let mut rng = RngSource::new().map_err(io::Error::other)?;
+///
+/// // Much too few iterations, but doc tests aren't optimised.
+/// let mut writer =
+///     ContainerWriter::with_passphrase(vec![], b"evil", 10, &mut rng)?;
+/// writer.write_all(b"Hello, world!")?;
+/// let container = writer.finish()?;
+///
+/// let mut plain = vec![];
+/// ContainerReader::with_passphrase(&container[..], b"evil")?
+///     .read_to_end(&mut plain)?;
+/// assert_eq!(plain, b"Hello, world!");
+/// # Ok(())
+/// # }
+/// ```
+pub struct ContainerWriter<W: Write>
+{
+    inner: W,
+    cipher: Aes256Gcm,
+    header: [u8; HEADER_LEN],
+    prefix: [u8; 7],
+    chunk_len: usize,
+    counter: u64,
+    buffer: Plain,
+}
+
+impl<W: Write> ContainerWriter<W>
+{
+    /// Starts a container encrypted with a key derived from `key`
+    ///
+    /// # Errors
+    /// It returns an error if the random salt and nonce prefix couldn't
+    /// be generated or the header couldn't be written.
+    pub fn with_key<R: Random + ?Sized>(
+        inner: W,
+        key: &SecretKey<32>,
+        rng: &mut R,
+    ) -> io::Result<Self>
+    {
+        let header = Header::generate(KDF_NONE, 0, rng)?;
+
+        Self::new(inner, &header.derive_raw_key(key), &header)
+    }
+
+    /// Starts a container encrypted with a key derived from
+    /// `passphrase`
+    ///
+    /// The key is derived with PBKDF2-HMAC-SHA256 and a random salt,
+    /// which takes longer the more `iterations` there are; a good
+    /// default is [`DEFAULT_ITERATIONS`], and at most
+    /// [`MAX_ITERATIONS`] are allowed.
+    ///
+    /// # Errors
+    /// It returns an error if the random salt and nonce prefix couldn't
+    /// be generated or the header couldn't be written.
+    ///
+    /// # Panics
+    /// Panics if `iterations` is zero or more than [`MAX_ITERATIONS`].
+    pub fn with_passphrase<R: Random + ?Sized>(
+        inner: W,
+        passphrase: &[u8],
+        iterations: u32,
+        rng: &mut R,
+    ) -> io::Result<Self>
+    {
+        assert!(
+            (1..=MAX_ITERATIONS).contains(&iterations),
+            "Invalid number of PBKDF2 iterations"
+        );
+        let header = Header::generate(KDF_PBKDF2_SHA256, iterations, rng)?;
+
+        Self::new(inner, &header.derive_key(passphrase), &header)
+    }
+
+    fn new(
+        mut inner: W,
+        key: &SecretKey<32>,
+        header: &Header,
+    ) -> io::Result<Self>
+    {
+        let bytes = header.encode();
+        inner.write_all(&bytes)?;
+        let chunk_len = header.chunk_len as usize;
+
+        Ok(Self {
+            inner,
+            cipher: Aes256Gcm::new(key),
+            header: bytes,
+            prefix: header.prefix,
+            chunk_len,
+            counter: 0,
+            buffer: Plain(Vec::with_capacity(chunk_len + TAG_LEN)),
+        })
+    }
+
+    /// Encrypts and writes the buffer as the next chunk
+    fn write_chunk(&mut self, last: bool) -> io::Result<()>
+    {
+        let nonce = chunk_nonce(self.prefix, self.counter, last)?;
+        // Even if writing fails the nonce mustn't be used again.
+        self.counter += 1;
+
+        let buffer = &mut self.buffer.0;
+        let tag = self.cipher.encrypt_in_place_detached(
+            buffer,
+            &self.header,
+            &nonce,
+        );
+        buffer.extend_from_slice(&tag);
+        let rv = self.inner.write_all(buffer);
+        buffer.clear();
+
+        rv
+    }
+
+    /// Writes the last chunk and returns the inner writer
+    ///
+    /// # Errors
+    /// It returns an error if the inner writer fails.
+    pub fn finish(mut self) -> io::Result<W>
+    {
+        if self.buffer.0.len() == self.chunk_len
+        {
+            self.write_chunk(false)?;
+        }
+        self.write_chunk(true)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for ContainerWriter<W>
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+        // A full chunk is only written now, since it could still turn
+        // out to be the last one.
+        if self.buffer.0.len() == self.chunk_len
+        {
+            self.write_chunk(false)?;
+        }
+
+        let len = buf.len().min(self.chunk_len - self.buffer.0.len());
+        self.buffer.0.extend_from_slice(&buf[..len]);
+
+        Ok(len)
+    }
+
+    /// Flushes the inner writer
+    ///
+    /// The data of an unfinished chunk stays in the buffer, since
+    /// every chunk except the last has to be full.
+    fn flush(&mut self) -> io::Result<()>
+    {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + fmt::Debug> fmt::Debug for ContainerWriter<W>
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        fmt.debug_struct("ContainerWriter")
+            .field("inner", &self.inner)
+            .field("chunk", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reads an encrypted container
+///
+/// The chunks are read, authenticated and decrypted one after the
+/// other, so the data of earlier chunks is already returned before a
+/// later one turns out to be tampered with.  If everything has to be
+/// authentic, only use it after reading until the end succeeded.
+///
+/// Errors in the container are [`io::ErrorKind::InvalidData`]; if a
+/// chunk couldn't be authenticated, the inner error is
+/// [`CipherError::Verification`](crate::CipherError::Verification).
+/// After an error every later read returns it again, so nothing after
+/// a broken chunk is ever returned.
+pub struct ContainerReader<R: Read>
+{
+    inner: R,
+    cipher: Aes256Gcm,
+    header: [u8; HEADER_LEN],
+    prefix: [u8; 7],
+    chunk_len: usize,
+    counter: u64,
+    buffer: Plain,
+    pos: usize,
+    done: bool,
+    failed: Option<Failure>,
+}
+
+/// The error that stopped a [`ContainerReader`]
+///
+/// `io::Error` can't be cloned, so this is kept to make a new one.
+enum Failure
+{
+    Verification(CipherError),
+    Other(io::ErrorKind, String),
+}
+
+impl Failure
+{
+    fn new(err: &io::Error) -> Self
+    {
+        match err.get_ref().and_then(|x| x.downcast_ref::<CipherError>())
+        {
+            Some(&cipher) => Self::Verification(cipher),
+            None => Self::Other(err.kind(), err.to_string()),
+        }
+    }
+
+    fn to_error(&self) -> io::Error
+    {
+        match self
+        {
+            Self::Verification(err) =>
+            {
+                io::Error::new(io::ErrorKind::InvalidData, *err)
+            }
+            Self::Other(kind, msg) => io::Error::new(*kind, msg.as_str()),
+        }
+    }
+}
+
+impl<R: Read> ContainerReader<R>
+{
+    /// Opens a container that was encrypted with `key`
+    ///
+    /// # Errors
+    /// It returns an error if the header couldn't be read, is invalid
+    /// or the container is encrypted with a passphrase.
+    pub fn with_key(mut inner: R, key: &SecretKey<32>) -> io::Result<Self>
+    {
+        let (header, bytes) = Header::read(&mut inner)?;
+        if header.kdf != KDF_NONE
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The container is encrypted with a passphrase",
+            ));
+        }
+
+        Ok(Self::new(
+            inner,
+            &header.derive_raw_key(key),
+            &header,
+            bytes,
+        ))
+    }
+
+    /// Opens a container that was encrypted with `passphrase`
+    ///
+    /// # Errors
+    /// It returns an error if the header couldn't be read, is invalid
+    /// or the container is encrypted with a raw key.
+    pub fn with_passphrase(mut inner: R, passphrase: &[u8])
+        -> io::Result<Self>
+    {
+        let (header, bytes) = Header::read(&mut inner)?;
+        if header.kdf != KDF_PBKDF2_SHA256
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The container is encrypted with a key",
+            ));
+        }
+
+        Ok(Self::new(
+            inner,
+            &header.derive_key(passphrase),
+            &header,
+            bytes,
+        ))
+    }
+
+    fn new(
+        inner: R,
+        key: &SecretKey<32>,
+        header: &Header,
+        bytes: [u8; HEADER_LEN],
+    ) -> Self
+    {
+        let chunk_len = header.chunk_len as usize;
+
+        Self {
+            inner,
+            cipher: Aes256Gcm::new(key),
+            header: bytes,
+            prefix: header.prefix,
+            chunk_len,
+            counter: 0,
+            buffer: Plain(Vec::with_capacity(chunk_len + TAG_LEN)),
+            pos: 0,
+            done: false,
+            failed: None,
+        }
+    }
+
+    /// Reads, authenticates and decrypts the next chunk
+    fn read_chunk(&mut self) -> io::Result<()>
+    {
+        let buffer = &mut self.buffer.0;
+        buffer.resize(self.chunk_len + TAG_LEN, 0);
+        let mut len = 0;
+        while len < buffer.len()
+        {
+            match self.inner.read(&mut buffer[len..])
+            {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted =>
+                {}
+                Err(err) =>
+                {
+                    buffer.clear();
+                    return Err(err);
+                }
+            }
+        }
+        // Only the last chunk isn't full.
+        let last = len < buffer.len();
+        buffer.truncate(len);
+
+        let Some(split) = len.checked_sub(TAG_LEN)
+        else
+        {
+            buffer.clear();
+            return Err(invalid("The container is truncated"));
+        };
+        let tag = buffer[split..].try_into().unwrap();
+        buffer.truncate(split);
+
+        let nonce = chunk_nonce(self.prefix, self.counter, last)?;
+        self.counter += 1;
+        if let Err(err) = self.cipher.decrypt_in_place_detached(
+            buffer,
+            &self.header,
+            &nonce,
+            tag,
+        )
+        {
+            buffer.clear();
+            return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+        self.done = last;
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for ContainerReader<R>
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+    {
+        if let Some(failure) = &self.failed
+        {
+            return Err(failure.to_error());
+        }
+
+        // Only the last chunk can be empty.
+        while self.pos == self.buffer.0.len() && !self.done
+        {
+            self.pos = 0;
+            if let Err(err) = self.read_chunk()
+            {
+                self.failed = Some(Failure::new(&err));
+                return Err(err);
+            }
+        }
+
+        let available = &self.buffer.0[self.pos..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.pos += len;
+
+        Ok(len)
+    }
+}
+
+impl<R: Read + fmt::Debug> fmt::Debug for ContainerReader<R>
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        fmt.debug_struct("ContainerReader")
+            .field("inner", &self.inner)
+            .field("chunk", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::io::{self, Read, Write};
+
+    use evilrng::Rng;
+
+    use super::{
+        ContainerReader, ContainerWriter, Header, CHUNK_LEN, HEADER_LEN,
+        KDF_NONE, KDF_PBKDF2_SHA256, MAX_ITERATIONS, TAG_LEN,
+    };
+    use crate::{CipherError, SecretKey};
+
+    fn encrypt(data: &[u8], key: &SecretKey<32>, chunk_len: u32) -> Vec<u8>
+    {
+        let mut header =
+            Header::generate(KDF_NONE, 0, &mut Rng::from_seed(&[1; 32]))
+                .unwrap();
+        header.chunk_len = chunk_len;
+
+        let mut writer =
+            ContainerWriter::new(vec![], &header.derive_raw_key(key), &header)
+                .unwrap();
+        // Odd pieces, so that they don't fit to the chunks
+        for piece in data.chunks(7)
+        {
+            writer.write_all(piece).unwrap();
+        }
+
+        writer.finish().unwrap()
+    }
+
+    fn decrypt(container: &[u8], key: &SecretKey<32>) -> io::Result<Vec<u8>>
+    {
+        let mut rv = vec![];
+        ContainerReader::with_key(container, key)?.read_to_end(&mut rv)?;
+
+        Ok(rv)
+    }
+
+    fn assert_invalid(result: io::Result<Vec<u8>>, authentication: bool)
+    {
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.get_ref()
+                .and_then(|err| err.downcast_ref::<CipherError>())
+                .is_some(),
+            authentication
+        );
+    }
+
+    #[test]
+    fn roundtrip_test()
+    {
+        let key = SecretKey::new([7; 32]);
+        let data = (0..100).collect::<Vec<u8>>();
+
+        for len in [0, 1, 15, 16, 17, 32, 100]
+        {
+            let container = encrypt(&data[..len], &key, 16);
+            let chunks = len / 16 + 1;
+            assert_eq!(container.len(), HEADER_LEN + len + chunks * TAG_LEN);
+            assert_eq!(decrypt(&container, &key).unwrap(), &data[..len]);
+        }
+
+        // With the real chunk length, read in odd pieces
+        let data = vec![0x5a; 2 * CHUNK_LEN as usize + 3];
+        let mut rng = Rng::from_seed(&[2; 32]);
+        let mut writer =
+            ContainerWriter::with_key(vec![], &key, &mut rng).unwrap();
+        writer.write_all(&data).unwrap();
+        let container = writer.finish().unwrap();
+        let mut reader =
+            ContainerReader::with_key(&container[..], &key).unwrap();
+        let mut plain = vec![];
+        let mut piece = [0; 1000];
+        loop
+        {
+            match reader.read(&mut piece).unwrap()
+            {
+                0 => break,
+                len => plain.extend_from_slice(&piece[..len]),
+            }
+        }
+        assert_eq!(plain, data);
+    }
+
+    #[test]
+    fn passphrase_test()
+    {
+        let mut rng = Rng::from_seed(&[3; 32]);
+        let mut writer =
+            ContainerWriter::with_passphrase(vec![], b"evil", 10, &mut rng)
+                .unwrap();
+        writer.write_all(b"secret").unwrap();
+        let container = writer.finish().unwrap();
+
+        let mut plain = vec![];
+        ContainerReader::with_passphrase(&container[..], b"evil")
+            .unwrap()
+            .read_to_end(&mut plain)
+            .unwrap();
+        assert_eq!(plain, b"secret");
+
+        let mut reader =
+            ContainerReader::with_passphrase(&container[..], b"good").unwrap();
+        assert_invalid(reader.read_to_end(&mut plain).map(|_| vec![]), true);
+        assert_eq!(
+            ContainerReader::with_key(
+                &container[..],
+                &SecretKey::new([0; 32])
+            )
+            .unwrap_err()
+            .kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn tamper_test()
+    {
+        let key = SecretKey::new([7; 32]);
+        let container = encrypt(&[9; 40], &key, 16);
+        let chunk = 16 + TAG_LEN;
+
+        assert_invalid(decrypt(&container, &SecretKey::new([8; 32])), true);
+        for i in [20, 40, HEADER_LEN, HEADER_LEN + chunk + 3]
+        {
+            let mut tampered = container.clone();
+            tampered[i] ^= 1;
+            assert_invalid(decrypt(&tampered, &key), true);
+        }
+
+        // Wrong magic number and version
+        for i in [0, 8]
+        {
+            let mut tampered = container.clone();
+            tampered[i] ^= 1;
+            assert_invalid(decrypt(&tampered, &key), false);
+        }
+
+        // Swapped chunks
+        let mut swapped = container[..HEADER_LEN].to_vec();
+        swapped.extend_from_slice(&container[HEADER_LEN + chunk..][..chunk]);
+        swapped.extend_from_slice(&container[HEADER_LEN..][..chunk]);
+        swapped.extend_from_slice(&container[HEADER_LEN + 2 * chunk..]);
+        assert_invalid(decrypt(&swapped, &key), true);
+
+        // Appended data
+        let mut appended = container.clone();
+        appended.push(0);
+        assert_invalid(decrypt(&appended, &key), true);
+        let mut appended = container.clone();
+        appended.extend_from_slice(&container[HEADER_LEN..][..chunk]);
+        assert_invalid(decrypt(&appended, &key), true);
+    }
+
+    #[test]
+    fn read_after_error_test()
+    {
+        let key = SecretKey::new([7; 32]);
+        let data = (0..64).collect::<Vec<u8>>();
+        let container = encrypt(&data, &key, 16);
+        let chunk = 16 + TAG_LEN;
+
+        // The second chunk is broken, the later ones are fine.
+        let mut tampered = container.clone();
+        tampered[HEADER_LEN + chunk] ^= 1;
+        let mut reader =
+            ContainerReader::with_key(&tampered[..], &key).unwrap();
+        let mut piece = [0; 16];
+        assert_eq!(reader.read(&mut piece).unwrap(), 16);
+        assert_eq!(piece, data[..16]);
+        for _ in 0..5
+        {
+            assert_invalid(reader.read(&mut piece).map(|_| vec![]), true);
+        }
+
+        // Also after other errors and with an empty buffer
+        let mut reader =
+            ContainerReader::with_key(&container[..HEADER_LEN + chunk], &key)
+                .unwrap();
+        assert_eq!(reader.read(&mut piece).unwrap(), 16);
+        for _ in 0..5
+        {
+            assert_invalid(reader.read(&mut piece).map(|_| vec![]), false);
+            assert_invalid(reader.read(&mut []).map(|_| vec![]), false);
+        }
+    }
+
+    #[test]
+    fn header_test()
+    {
+        let key = SecretKey::new([7; 32]);
+        let mut rng = Rng::from_seed(&[4; 32]);
+        let mut header = Header::generate(KDF_NONE, 0, &mut rng).unwrap();
+        assert_ne!(header.salt, [0; 16]);
+
+        // Even with the same nonce prefix another salt gives another
+        // key.
+        let first = ContainerWriter::new(
+            vec![],
+            &header.derive_raw_key(&key),
+            &header,
+        )
+        .unwrap()
+        .finish()
+        .unwrap();
+        header.salt[0] ^= 1;
+        let second = ContainerWriter::new(
+            vec![],
+            &header.derive_raw_key(&key),
+            &header,
+        )
+        .unwrap()
+        .finish()
+        .unwrap();
+        assert_eq!(first[34..HEADER_LEN], second[34..HEADER_LEN]);
+        assert_ne!(first[HEADER_LEN..], second[HEADER_LEN..]);
+        assert!(decrypt(&second, &key).unwrap().is_empty());
+
+        // Too many iterations
+        header.kdf = KDF_PBKDF2_SHA256;
+        header.iterations = MAX_ITERATIONS;
+        assert!(Header::decode(&header.encode()).is_ok());
+        header.iterations += 1;
+        assert_eq!(
+            Header::decode(&header.encode()).err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn truncation_test()
+    {
+        let key = SecretKey::new([7; 32]);
+        let container = encrypt(&[9; 40], &key, 16);
+        let chunk = 16 + TAG_LEN;
+
+        // At a chunk boundary the last chunk looks like a middle one.
+        assert_invalid(decrypt(&container[..HEADER_LEN + chunk], &key), false);
+        assert_invalid(decrypt(&container[..HEADER_LEN], &key), false);
+        assert_invalid(
+            decrypt(&container[..HEADER_LEN + 2 * chunk], &key),
+            false,
+        );
+
+        // Inside a chunk it looks like the last one.
+        assert_invalid(
+            decrypt(&container[..HEADER_LEN + chunk + 20], &key),
+            true,
+        );
+        assert_invalid(decrypt(&container[..container.len() - 1], &key), true);
+
+        assert_eq!(
+            decrypt(&container[..20], &key).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+}
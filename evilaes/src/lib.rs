@@ -42,13 +42,12 @@
     // Should be correct everywhere already.
     clippy::cast_possible_truncation,
     // Should be correct everywhere already.
-    clippy::cast_lossless
+    clippy::cast_lossless,
+    // Impossible to fulfil in crypto code
+    clippy::unreadable_literal,
+    // Names like ChaCha20 aren't code.
+    clippy::doc_markdown
 )]
-#![feature(core_intrinsics)]
-// Needed against timing attacks; I could reimplement it, but would
-// need assembler for that which is itself behind a feature gate so it
-// wouldn't be an improvement.
-#![feature(bench_black_box)]
 
 //! Unsecure implementation of AES 256 in pure rust
 //!
@@ -56,17 +55,43 @@
 //! for the sole purpose that he learns a bit about cryptography.  It
 //! is very probably *very* vulnerable, so **do not use evilaes**.
 //! The same applies to all other evil\* crates.
+//!
+//! Besides AES it also has ChaCha20-Poly1305 (RFC 8439) and its
+//! variant XChaCha20-Poly1305 with 192-bit nonces, which don't need
+//! any tables and are a lot faster in software.
+//!
+//! For files there is an encrypted container format with a
+//! [`ContainerWriter`] and a [`ContainerReader`]; it splits the data in
+//! AES-GCM encrypted chunks, so that it can be streamed, and derives
+//! the key from a passphrase with PBKDF2 if wanted.
 
 use std::convert::TryInto;
 use std::error;
 use std::fmt;
-use std::hint::black_box;
-use std::io;
-use std::iter;
-use std::num::Wrapping;
-use std::sync::atomic::{fence, Ordering};
 
-use evilrng::RngSource;
+use evilrng::{Random, RngError, RngSource};
+use evilsecret::{barrier, wipe};
+pub use evilsecret::{Nonce, SecretKey};
+pub use eviltraits::{Aead, BlockCipher, CipherError, Mac};
+
+pub use chacha20::{
+    chacha20_in_place, chacha20_poly1305_decrypt,
+    chacha20_poly1305_decrypt_in_place, chacha20_poly1305_encrypt,
+    chacha20_poly1305_encrypt_in_place, hchacha20, poly1305,
+    xchacha20_get_nonce, xchacha20_in_place, xchacha20_poly1305_decrypt,
+    xchacha20_poly1305_decrypt_in_place, xchacha20_poly1305_encrypt,
+    xchacha20_poly1305_encrypt_in_place, Poly1305,
+};
+
+pub use ciphers::{Aes256, Aes256Gcm, ChaCha20Poly1305, XChaCha20Poly1305};
+pub use container::{
+    ContainerReader, ContainerWriter, CONTAINER_MAGIC, CONTAINER_VERSION,
+    DEFAULT_ITERATIONS, MAX_ITERATIONS,
+};
+
+mod chacha20;
+mod ciphers;
+mod container;
 
 const AES_256_NK: usize = 8;
 const AES_256_NB: usize = 4;
@@ -315,11 +340,11 @@ impl fmt::Display for AesError
     {
         match self
         {
-            AesError::NotWholeBlock => write!(
+            Self::NotWholeBlock => write!(
                 fmt,
                 "The buffer couldn't be split in a whole number of blocks"
             ),
-            AesError::VerifyingError =>
+            Self::VerifyingError =>
             {
                 write!(fmt, "Authentication tag couldn't be verified")
             }
@@ -329,18 +354,6 @@ impl fmt::Display for AesError
 
 impl error::Error for AesError {}
 
-fn delete_key<T, const N: usize>(mut key: [T; N])
-{
-    // FIXME: Try find a non-unsafe, non-instrinsics,
-    // non-feature-gated version!
-    #[allow(unsafe_code)]
-    unsafe {
-        std::intrinsics::volatile_set_memory(&mut key as *mut T, 0, N);
-    }
-
-    fence(Ordering::SeqCst);
-}
-
 fn aes_256_sub_bytes(state: &mut [[u8; 4]; 4])
 {
     for row in state.iter_mut()
@@ -451,7 +464,7 @@ fn aes_256_add_round_key(state: &mut [[u8; 4]; 4], key: &[[u8; 4]])
     }
 }
 
-fn aes_256_rot_word(v: &mut [u8; 4])
+const fn aes_256_rot_word(v: &mut [u8; 4])
 {
     let t = v[0];
     v[0] = v[1];
@@ -505,6 +518,8 @@ fn aes_256_key_expansion(
         w[i][3] = w[i - AES_256_NK][3] ^ temp[3];
     }
 
+    wipe(&mut temp);
+
     w
 }
 
@@ -530,6 +545,8 @@ fn transpose(state: &mut [[u8; 4]; 4])
     }
 }
 
+// The columns are accessed, so clippy's suggestion doesn't work
+#[allow(clippy::needless_range_loop)]
 fn aes_256_block_encrypt(
     state: &mut [[u8; 4]; 4],
     expkey: &[[u8; 4]; (AES_256_NR + 1) * 4],
@@ -570,6 +587,8 @@ fn aes_256_block_encrypt(
     aes_256_add_round_key(state, &expkey[keyi..(keyi + 4)]);
 }
 
+// The columns are accessed, so clippy's suggestion doesn't work
+#[allow(clippy::needless_range_loop)]
 fn aes_256_block_decrypt(
     state: &mut [[u8; 4]; 4],
     expkey: &[[u8; 4]; (AES_256_NR + 1) * 4],
@@ -612,44 +631,61 @@ fn aes_256_block_decrypt(
     aes_256_add_round_key(state, &expkey[keyi..(keyi + 4)]);
 }
 
-fn aes_256_crypt(
-    input: &[u8],
-    key: [u8; AES_256_NK * 4],
-    block_crypter: &dyn Fn(
-        &mut [[u8; 4]; 4],
-        &[[u8; 4]; (AES_256_NR + 1) * 4],
-    ),
-) -> Result<Vec<u8>, AesError>
+type BlockCrypter =
+    dyn Fn(&mut [[u8; 4]; 4], &[[u8; 4]; (AES_256_NR + 1) * 4]);
+
+fn aes_256_crypt_in_place(
+    buffer: &mut [u8],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
+    block_crypter: &BlockCrypter,
+) -> Result<(), AesError>
 {
-    let mut output = Vec::with_capacity(((input.len() + 1) / 16) * 16);
-    let longkey = aes_256_key_expansion(&key);
+    // Checked before anything is done, so that the buffer isn't left
+    // half encrypted.
+    if !buffer.len().is_multiple_of(AES_256_NB * 4)
+    {
+        return Err(AesError::NotWholeBlock);
+    }
+
+    let mut longkey = aes_256_key_expansion(key.expose());
+    crypt_blocks(buffer, &longkey, block_crypter);
+    wipe(&mut longkey);
 
-    for block in input.chunks(AES_256_NB * 4)
+    Ok(())
+}
+
+// `buffer` has to be a whole number of blocks.
+fn crypt_blocks(
+    buffer: &mut [u8],
+    longkey: &[[u8; 4]; AES_256_NB * (AES_256_NR + 1)],
+    block_crypter: &BlockCrypter,
+)
+{
+    for block in buffer.chunks_exact_mut(AES_256_NB * 4)
     {
-        if block.len() != (AES_256_NB * 4)
-        {
-            return Err(AesError::NotWholeBlock);
-        }
-        let mut block = [
+        let mut state = [
             block[0..4].try_into().unwrap(),
             block[4..8].try_into().unwrap(),
             block[8..12].try_into().unwrap(),
             block[12..16].try_into().unwrap(),
         ];
 
-        block_crypter(&mut block, &longkey);
+        block_crypter(&mut state, longkey);
 
-        for row in block
-        {
-            for v in row
-            {
-                output.push(v);
-            }
-        }
+        block.copy_from_slice(&flatten_output(&state));
+        wipe(&mut state);
     }
+}
 
-    delete_key(key);
-    delete_key(longkey);
+fn aes_256_crypt(
+    input: &[u8],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
+    block_crypter: &BlockCrypter,
+) -> Result<Vec<u8>, AesError>
+{
+    let mut output = input.to_vec();
+
+    aes_256_crypt_in_place(&mut output, key, block_crypter)?;
 
     Ok(output)
 }
@@ -661,23 +697,26 @@ fn aes_256_crypt(
 /// crate, so **do not use!**
 ///
 /// You need to provide a [`RngSource`](evilrng::RngSource):
-/// ```no_run
-/// # use evilrng;
+/// ```
 /// # use evilaes::aes_get_iv;
-/// # fn main() -> Result<(), std::io::Error>
+/// # use evilrng::{RngError, RngSource};
+/// # fn main() -> Result<(), RngError>
 /// # {
 /// // Since just zeros is theoretical possible, that fails with a
-/// // possibility of 2^(-72)
-/// assert_ne!(aes_get_iv(&mut evilrng::RngSource::new()?)?, [0, 0, 0]);
+/// // possibility of 2^(-96)
+/// assert_ne!(aes_get_iv(&mut RngSource::new()?)?.expose(), &[0; 12]);
 /// # Ok(())
 /// # }
 /// ```
 ///
 /// # Errors
 /// It returns an error if no entropy could be read.
-pub fn aes_get_iv(rng: &mut RngSource) -> Result<[u32; 3], io::Error>
+pub fn aes_get_iv(rng: &mut RngSource) -> Result<Nonce<12>, RngError>
 {
-    Ok([rng.get_u32()?, rng.get_u32()?, rng.get_u32()?])
+    let mut iv = Nonce::new([0; 12]);
+    rng.fill_bytes(iv.expose_mut())?;
+
+    Ok(iv)
 }
 
 // A few helper functions; I think I see another reason, why you
@@ -719,6 +758,10 @@ const fn flatten_output(state: &[[u8; 4]; 4]) -> [u8; 16]
     ]
 }
 
+// The original bit-serial multiplication straight from the
+// specification; it's much too slow for real use, but easy to check,
+// so it's kept as reference for the faster versions.
+#[cfg(test)]
 fn galois_product(x: u128, y: u128) -> u128
 {
     let mut z = 0;
@@ -730,154 +773,330 @@ fn galois_product(x: u128, y: u128) -> u128
 
         z ^= if x & (1 << (127 - i)) == 0
         {
-            black_box(0)
+            barrier(0)
         }
         else
         {
-            black_box(v)
+            barrier(v)
         };
 
         v = (v >> 1)
-            ^ if (v % 2) == 0
+            ^ if v.is_multiple_of(2)
             {
-                black_box(0)
+                barrier(0)
             }
             else
             {
-                black_box(0xE100_0000_0000_0000_0000_0000_0000_0000)
+                barrier(0xE100_0000_0000_0000_0000_0000_0000_0000)
             };
     }
 
     z
 }
 
-fn ghash(subkey_: &[[u8; 4]; 4], input: &[u8]) -> u128
+// Lower 64 bit of the carry-less product of `x` and `y`.  Uses
+// normal integer multiplication, but only on every fourth bit, so
+// that the carries land in the "holes" between them and can be
+// masked away (see BearSSL's `ghash_ctmul64`).  No branches and no
+// table lookups, so it should be timing-attack resistant.
+const fn bmul64(x: u64, y: u64) -> u64
+{
+    const M0: u64 = 0x1111_1111_1111_1111;
+    const M1: u64 = 0x2222_2222_2222_2222;
+    const M2: u64 = 0x4444_4444_4444_4444;
+    const M3: u64 = 0x8888_8888_8888_8888;
+
+    let (x0, x1, x2, x3) = (x & M0, x & M1, x & M2, x & M3);
+    let (y0, y1, y2, y3) = (y & M0, y & M1, y & M2, y & M3);
+
+    let z0 = x0.wrapping_mul(y0)
+        ^ x1.wrapping_mul(y3)
+        ^ x2.wrapping_mul(y2)
+        ^ x3.wrapping_mul(y1);
+    let z1 = x0.wrapping_mul(y1)
+        ^ x1.wrapping_mul(y0)
+        ^ x2.wrapping_mul(y3)
+        ^ x3.wrapping_mul(y2);
+    let z2 = x0.wrapping_mul(y2)
+        ^ x1.wrapping_mul(y1)
+        ^ x2.wrapping_mul(y0)
+        ^ x3.wrapping_mul(y3);
+    let z3 = x0.wrapping_mul(y3)
+        ^ x1.wrapping_mul(y2)
+        ^ x2.wrapping_mul(y1)
+        ^ x3.wrapping_mul(y0);
+
+    (z0 & M0) | (z1 & M1) | (z2 & M2) | (z3 & M3)
+}
+
+// Full 128 bit carry-less product of `x` and `y`; the upper half is
+// the lower half of the product of the bit reversed inputs (bit
+// reversed again).
+const fn clmul64(x: u64, y: u64) -> u128
+{
+    let lo = bmul64(x, y);
+    let hi = bmul64(x.reverse_bits(), y.reverse_bits()).reverse_bits() >> 1;
+
+    ((hi as u128) << 64) | lo as u128
+}
+
+// Constant time version of `galois_product`.
+//
+// GCM stores the coefficient of x^0 in the most significant bit, so
+// after reversing the bits this is normal polynomial multiplication
+// (done with Karatsuba on 64 bit halves) followed by the reduction
+// modulo x^128 + x^7 + x^2 + x + 1.
+const fn galois_product_ct(x: u128, y: u128) -> u128
 {
-    let mut subkey = 0;
-    let mut chunks = Vec::with_capacity((input.len() + 15) / 16);
+    let x = x.reverse_bits();
+    let y = y.reverse_bits();
+
+    let (x1, x0) = ((x >> 64) as u64, x as u64);
+    let (y1, y0) = ((y >> 64) as u64, y as u64);
+
+    let lo = clmul64(x0, y0);
+    let hi = clmul64(x1, y1);
+    let mid = clmul64(x0 ^ x1, y0 ^ y1) ^ lo ^ hi;
+
+    let lo = lo ^ (mid << 64);
+    let hi = hi ^ (mid >> 64);
+
+    // x^128 = x^7 + x^2 + x + 1; the bits shifted out of `hi` are
+    // folded in a second time.
+    let overflow = (hi >> 127) ^ (hi >> 126) ^ (hi >> 121);
+    let hi = hi ^ overflow;
+    let z = lo ^ hi ^ (hi << 1) ^ (hi << 2) ^ (hi << 7);
+
+    z.reverse_bits()
+}
+
+// Multiplication by x^4 of the four lowest bits, i.e. what has to be
+// added after shifting an element by four to the right.
+static GHASH_REDUCTION: [u128; 16] = {
+    let mut rv = [0; 16];
+    let mut i = 0;
 
-    for i in 0..16
+    while i < 16
     {
-        subkey *= 256;
-        subkey += subkey_[i / 4][i % 4] as u128;
+        let mut v = i as u128;
+        let mut j = 0;
+
+        while j < 4
+        {
+            v = (v >> 1) ^ ((v & 1) * (0xE1 << 120));
+            j += 1;
+        }
+
+        rv[i] = v;
+        i += 1;
     }
 
-    for chunk in input.chunks(16)
+    rv
+};
+
+// Shoup's 4-bit tables: `GhashTable(m)` contains at index `n` the
+// product of `H` with the element, whose first four coefficients are
+// the bits of `n`.  This is by far the fastest version, but the table
+// lookups depend on the processed data and so it's probably
+// vulnerable to cache timing attacks.
+struct GhashTable([u128; 16]);
+
+impl GhashTable
+{
+    fn new(h: u128) -> Self
     {
-        assert_eq!(chunk.len(), 16);
+        let mut m = [0; 16];
 
-        let mut val = 0;
+        m[8] = h;
+        m[4] = galois_product_ct(h, 1 << 126);
+        m[2] = galois_product_ct(h, 1 << 125);
+        m[1] = galois_product_ct(h, 1 << 124);
 
-        for v in chunk
+        for i in [3_usize, 5, 6, 7, 9, 10, 11, 12, 13, 14, 15]
         {
-            val *= 256;
-            val += *v as u128;
+            let high = 1 << (usize::BITS - 1 - i.leading_zeros());
+            m[i] = m[high] ^ m[i ^ high];
         }
 
-        chunks.push(val);
+        let rv = Self(m);
+        wipe(&mut m);
+
+        rv
     }
 
-    let mut y = 0;
+    fn product(&self, x: u128) -> u128
+    {
+        let mut z = 0;
+
+        for i in 0..32
+        {
+            let nibble = ((x >> (4 * i)) & 0xf) as usize;
+            z = (z >> 4) ^ GHASH_REDUCTION[(z & 0xf) as usize];
+            z ^= self.0[nibble];
+        }
 
-    for chunk in chunks
+        z
+    }
+}
+
+impl Drop for GhashTable
+{
+    fn drop(&mut self)
     {
-        y = galois_product(y ^ chunk, subkey);
+        wipe(&mut self.0);
     }
+}
 
-    y
+// GHASH working on a stream, so that the input doesn't have to be
+// collected first.  Every call to `update` pads its input with zeros
+// to a whole number of blocks, exactly like GCM needs it for the
+// additional data and the cipher text.
+struct Ghash
+{
+    subkey: u128,
+    table: Option<GhashTable>,
+    y: u128,
 }
 
-fn gctr(
-    longkey: &[[u8; 4]; AES_256_NB * (AES_256_NR + 1)],
-    icb_: &[u32; 4],
-    input: &[u8],
-) -> Vec<u8>
+impl Ghash
 {
-    if input.is_empty()
+    fn new(subkey_: &[[u8; 4]; 4]) -> Self
     {
-        return vec![];
+        let subkey = u128::from_be_bytes(flatten_output(subkey_));
+        let table = if cfg!(feature = "ghash-table")
+        {
+            Some(GhashTable::new(subkey))
+        }
+        else
+        {
+            None
+        };
+
+        Self {
+            subkey,
+            table,
+            y: 0,
+        }
     }
 
-    let mut icb = [[0; 4]; 4];
+    fn update(&mut self, input: &[u8])
+    {
+        for chunk in input.chunks(16)
+        {
+            let mut block = [0; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+
+            let x = self.y ^ u128::from_be_bytes(block);
 
-    for i in 0..4
+            self.y = match &self.table
+            {
+                Some(table) => table.product(x),
+                None => galois_product_ct(x, self.subkey),
+            };
+        }
+    }
+
+    fn finish(self) -> u128
     {
-        icb[i][0] = (icb_[i] >> 24) as u8;
-        icb[i][1] = ((icb_[i] >> 16) % 256) as u8;
-        icb[i][2] = ((icb_[i] >> 8) % 256) as u8;
-        icb[i][3] = (icb_[i] % 256) as u8;
+        self.y
     }
+}
 
-    let n = ((input.len() * 8) + 127) / 128;
-    let mut lastlen = 0;
+impl Drop for Ghash
+{
+    fn drop(&mut self)
+    {
+        wipe(&mut self.subkey);
+        wipe(&mut self.y);
+    }
+}
 
-    let mut chunks = Vec::with_capacity(n);
+fn gctr_in_place(
+    longkey: &[[u8; 4]; AES_256_NB * (AES_256_NR + 1)],
+    icb: &[u32; 4],
+    buffer: &mut [u8],
+)
+{
+    let mut cb = *icb;
+    let mut keystream = [[0; 4]; 4];
 
-    for chunk in input.chunks(16)
+    for chunk in buffer.chunks_mut(16)
     {
-        let mut sum = [[0; 4]; 4];
+        keystream = [
+            cb[0].to_be_bytes(),
+            cb[1].to_be_bytes(),
+            cb[2].to_be_bytes(),
+            cb[3].to_be_bytes(),
+        ];
 
-        lastlen = chunk.len();
+        aes_256_block_encrypt(&mut keystream, longkey);
 
-        // TODO: Determine if timing-attack possible
-        for i in 0..16
+        for (v, k) in chunk.iter_mut().zip(flatten_output(&keystream))
         {
-            if chunk.len() > i
-            {
-                sum[i / 4][i % 4] = chunk[i];
-            }
+            *v ^= k;
         }
 
-        chunks.push(sum);
+        // Only the last 32 bit are a counter (`inc_32` in the
+        // specification).
+        cb[3] = cb[3].wrapping_add(1);
     }
 
-    let mut last_cb = [[0_u8; 4]; 4];
-    let mut rv: Vec<u8> = Vec::with_capacity(input.len());
+    wipe(&mut keystream);
+}
+
+fn aes_256_gcm_tag(
+    longkey: &[[u8; 4]; AES_256_NB * (AES_256_NR + 1)],
+    iv: [u32; 3],
+    aad: &[u8],
+    c: &[u8],
+) -> [u32; 4]
+{
+    let mut h = [[0; 4]; 4];
+    aes_256_block_encrypt(&mut h, longkey);
+
+    let mut ghash = Ghash::new(&h);
+    wipe(&mut h);
+
+    let mut lengths = [0; 16];
+    lengths[..8].copy_from_slice(&u64_as_array((aad.len() * 8) as u64));
+    lengths[8..].copy_from_slice(&u64_as_array((c.len() * 8) as u64));
+
+    ghash.update(aad);
+    ghash.update(c);
+    ghash.update(&lengths);
+
+    let mut s = ghash.finish().to_be_bytes();
+    gctr_in_place(longkey, &[iv[0], iv[1], iv[2], 1], &mut s);
+
+    let mut t = [0; 4];
 
-    for (i, chunk) in chunks.iter().enumerate()
+    for (t, s) in t.iter_mut().zip(s.chunks_exact(4))
     {
-        let mut cb = if i == 0
-        {
-            icb
-        }
-        else
-        {
-            let mut tmp = last_cb;
-            tmp[3][3] = (Wrapping(tmp[3][3]) + Wrapping(1)).0;
-            tmp
-        };
+        *t = u32::from_be_bytes(s.try_into().unwrap());
+    }
 
-        last_cb = cb;
+    t
+}
 
-        aes_256_block_encrypt(&mut cb, longkey);
-        // Since that's not possible:
-        // let y = chunk ^ cb;
-        // So:
-        let mut y = [[0; 4]; 4];
-        for j in 0..16
-        {
-            y[j / 4][j % 4] = chunk[j / 4][j % 4] ^ cb[j / 4][j % 4];
-        }
+// The nonce as big endian words, like it's used in the counter blocks
+fn nonce_words<const N: usize, const W: usize>(nonce: &Nonce<N>) -> [u32; W]
+{
+    let mut rv = [0; W];
 
-        if i == n - 1
-        {
-            for i in 0..lastlen
-            {
-                rv.push(y[i / 4][i % 4])
-            }
-        }
-        else
-        {
-            for v in flatten_output(&y)
-            {
-                rv.push(v);
-            }
-        }
+    for (w, bytes) in rv.iter_mut().zip(nonce.expose().chunks_exact(4))
+    {
+        *w = u32::from_be_bytes(bytes.try_into().unwrap());
     }
 
     rv
 }
 
+// Compares two tags without leaving early at the first difference.
+fn tags_equal(a: &[u32; 4], b: &[u32; 4]) -> bool
+{
+    barrier(a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b))) == 0
+}
+
 /// Encrypts insecurely via AES-256 bit GCM
 ///
 /// This encrypts `input` and authenticates `input` and `aad`
@@ -887,60 +1106,12 @@ fn gctr(
 pub fn aes_256_gcm_encrypt(
     input: &[u8],
     aad: &[u8],
-    key: [u8; AES_256_NK * 4],
-    iv: [u32; 3],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
+    iv: &Nonce<12>,
 ) -> (Vec<u8>, [u32; 4])
 {
-    let longkey = aes_256_key_expansion(&key);
-    let mut h = [[0; 4]; 4];
-    aes_256_block_encrypt(&mut h, &longkey);
-
-    let j = [iv[0], iv[1], iv[2], 1];
-    let j_inc = [iv[0], iv[1], iv[2], 2];
-    let c = gctr(&longkey, &j_inc, input);
-    let u = ((c.len() + 15) / 16) * 128 - c.len() * 8;
-    let v = ((aad.len() + 15) / 16) * 128 - aad.len() * 8;
-    let pre_s = aad
-        .iter()
-        .copied()
-        .chain(iter::repeat(0).take(v))
-        .chain(c.iter().copied())
-        .chain(iter::repeat(0).take(u))
-        .chain(u64_as_array((aad.len() * 8) as u64).iter().copied())
-        .chain(u64_as_array((c.len() * 8) as u64).iter().copied())
-        .collect::<Vec<_>>();
-    let s = ghash(&h, &pre_s);
-    let s = [
-        (s >> 120) as u8,
-        ((s >> 112) % 256) as u8,
-        ((s >> 104) % 256) as u8,
-        ((s >> 96) % 256) as u8,
-        ((s >> 88) % 256) as u8,
-        ((s >> 80) % 256) as u8,
-        ((s >> 72) % 256) as u8,
-        ((s >> 64) % 256) as u8,
-        ((s >> 56) % 256) as u8,
-        ((s >> 48) % 256) as u8,
-        ((s >> 40) % 256) as u8,
-        ((s >> 32) % 256) as u8,
-        ((s >> 24) % 256) as u8,
-        ((s >> 16) % 256) as u8,
-        ((s >> 8) % 256) as u8,
-        (s % 256) as u8,
-    ];
-    let t_ = gctr(&longkey, &j, &s);
-    let mut t = [0; 4];
-
-    for i in 0..4
-    {
-        t[i] = ((t_[i * 4] as u32) << 24)
-            + ((t_[i * 4 + 1] as u32) << 16)
-            + ((t_[i * 4 + 2] as u32) << 8)
-            + (t_[i * 4 + 3] as u32);
-    }
-
-    delete_key(key);
-    delete_key(longkey);
+    let mut c = input.to_vec();
+    let t = aes_256_gcm_encrypt_in_place(&mut c, aad, key, iv);
 
     (c, t)
 }
@@ -958,65 +1129,94 @@ pub fn aes_256_gcm_encrypt(
 pub fn aes_256_gcm_decrypt(
     c: &[u8],
     aad: &[u8],
-    key: [u8; AES_256_NK * 4],
-    iv: [u32; 3],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
+    iv: &Nonce<12>,
     tag: [u32; 4],
 ) -> Result<Vec<u8>, AesError>
 {
-    let longkey = aes_256_key_expansion(&key);
-    let mut h = [[0; 4]; 4];
-    aes_256_block_encrypt(&mut h, &longkey);
-
-    let j = [iv[0], iv[1], iv[2], 1];
-    let j_inc = [iv[0], iv[1], iv[2], 2];
-    let p = gctr(&longkey, &j_inc, c);
-    let u = ((c.len() + 15) / 16) * 128 - c.len() * 8;
-    let v = ((aad.len() + 15) / 16) * 128 - aad.len() * 8;
-    let pre_s = aad
-        .iter()
-        .copied()
-        .chain(iter::repeat(0).take(v))
-        .chain(c.iter().copied())
-        .chain(iter::repeat(0).take(u))
-        .chain(u64_as_array((aad.len() * 8) as u64).iter().copied())
-        .chain(u64_as_array((c.len() * 8) as u64).iter().copied())
-        .collect::<Vec<_>>();
-    let s = ghash(&h, &pre_s);
-    let s = [
-        (s >> 120) as u8,
-        ((s >> 112) % 256) as u8,
-        ((s >> 104) % 256) as u8,
-        ((s >> 96) % 256) as u8,
-        ((s >> 88) % 256) as u8,
-        ((s >> 80) % 256) as u8,
-        ((s >> 72) % 256) as u8,
-        ((s >> 64) % 256) as u8,
-        ((s >> 56) % 256) as u8,
-        ((s >> 48) % 256) as u8,
-        ((s >> 40) % 256) as u8,
-        ((s >> 32) % 256) as u8,
-        ((s >> 24) % 256) as u8,
-        ((s >> 16) % 256) as u8,
-        ((s >> 8) % 256) as u8,
-        (s % 256) as u8,
-    ];
-    let t_ = gctr(&longkey, &j, &s);
-    let mut t = [0; 4];
+    let mut p = c.to_vec();
+    let verified = aes_256_gcm_decrypt_in_place(&mut p, aad, key, iv, tag);
 
-    for i in 0..4
-    {
-        t[i] = ((t_[i * 4] as u32) << 24)
-            + ((t_[i * 4 + 1] as u32) << 16)
-            + ((t_[i * 4 + 2] as u32) << 8)
-            + (t_[i * 4 + 3] as u32);
-    }
+    verified.map(|()| p)
+}
+
+/// Encrypts in place insecurely via AES-256 bit GCM
+///
+/// Like [`aes_256_gcm_encrypt`], but overwrites `buffer` with the
+/// cipher text and only returns the (detached) tag.  This doesn't
+/// allocate.  This is not cryptographical secure, **do not use!**
+#[must_use]
+pub fn aes_256_gcm_encrypt_in_place(
+    buffer: &mut [u8],
+    aad: &[u8],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
+    iv: &Nonce<12>,
+) -> [u32; 4]
+{
+    let mut longkey = aes_256_key_expansion(key.expose());
+    let t = gcm_encrypt_in_place(&longkey, nonce_words(iv), aad, buffer);
+
+    wipe(&mut longkey);
+
+    t
+}
+
+fn gcm_encrypt_in_place(
+    longkey: &[[u8; 4]; AES_256_NB * (AES_256_NR + 1)],
+    iv: [u32; 3],
+    aad: &[u8],
+    buffer: &mut [u8],
+) -> [u32; 4]
+{
+    gctr_in_place(longkey, &[iv[0], iv[1], iv[2], 2], buffer);
+
+    aes_256_gcm_tag(longkey, iv, aad, buffer)
+}
+
+/// Decrypts in place insecurely via AES-256 bit GCM
+///
+/// Like [`aes_256_gcm_decrypt`], but overwrites `buffer` with the
+/// plain text and takes the tag detached.  This doesn't allocate.
+/// The tag is verified before anything is decrypted, so if the
+/// verification fails, `buffer` still contains the cipher text.  This
+/// is not cryptographical secure, **do not use!**
+///
+/// # Errors
+/// Returns an error if the verification couldn't succeeds.  **Note:**
+/// This is **not** cryptographical **secure**.  **Do not** rely one
+/// the correctness of the verification.
+pub fn aes_256_gcm_decrypt_in_place(
+    buffer: &mut [u8],
+    aad: &[u8],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
+    iv: &Nonce<12>,
+    tag: [u32; 4],
+) -> Result<(), AesError>
+{
+    let mut longkey = aes_256_key_expansion(key.expose());
+    let verified =
+        gcm_decrypt_in_place(&longkey, nonce_words(iv), aad, buffer, tag);
+
+    wipe(&mut longkey);
 
-    delete_key(key);
-    delete_key(longkey);
+    verified
+}
+
+fn gcm_decrypt_in_place(
+    longkey: &[[u8; 4]; AES_256_NB * (AES_256_NR + 1)],
+    iv: [u32; 3],
+    aad: &[u8],
+    buffer: &mut [u8],
+    tag: [u32; 4],
+) -> Result<(), AesError>
+{
+    let t = aes_256_gcm_tag(longkey, iv, aad, buffer);
 
-    if t == tag
+    if tags_equal(&t, &tag)
     {
-        Ok(p)
+        gctr_in_place(longkey, &[iv[0], iv[1], iv[2], 2], buffer);
+
+        Ok(())
     }
     else
     {
@@ -1024,23 +1224,60 @@ pub fn aes_256_gcm_decrypt(
     }
 }
 
+/// Encrypts in place insecurely via AES-256 bit CTR
+///
+/// Encrypts `buffer` in place **insecurely** via AES-256 bit Counter
+/// Mode using `key`.  The first block is encrypted with `counter`, for
+/// every following block its last four bytes are incremented as big
+/// endian number (which wraps around after 2^32 blocks like in GCM).
+/// This doesn't allocate.  Since this isn't authenticated and not
+/// cryptographical secure, **do not use!**
+pub fn aes_256_ctr_encrypt_in_place(
+    buffer: &mut [u8],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
+    counter: &Nonce<16>,
+)
+{
+    let mut longkey = aes_256_key_expansion(key.expose());
+
+    gctr_in_place(&longkey, &nonce_words(counter), buffer);
+
+    wipe(&mut longkey);
+}
+
+/// Decrypts in place insecurely via AES-256 bit CTR
+///
+/// Decrypts `buffer` in place, which was encrypted with
+/// [`aes_256_ctr_encrypt_in_place`] using the same `key` and
+/// `counter`.  (In Counter Mode this is exactly the same operation as
+/// encrypting.)  Since this isn't authenticated and not
+/// cryptographical secure, **do not use!**
+pub fn aes_256_ctr_decrypt_in_place(
+    buffer: &mut [u8],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
+    counter: &Nonce<16>,
+)
+{
+    aes_256_ctr_encrypt_in_place(buffer, key, counter);
+}
+
 /// Encrypts using AES-256
 ///
 /// Encrypts `input` using `key` and returns the cipher text.  If not
 /// a whole number of 16-byte blocks are supplied an error will be
 /// returned.
 ///
-/// **Note**: The function takes ownership of the
-/// key and overwrites it with zeros **only** on a **best effort**
-/// basis, and I'm not a cryptographer so this is probably **insecure**,
-/// do *not* rely on it being safe!  Moreover there are probably
-/// countless **futher** issues.
+/// **Note**: The key is only borrowed and all copies of it the
+/// function makes are overwritten with zeros, but **only** on a
+/// **best effort** basis, and I'm not a cryptographer so this is
+/// probably **insecure**, do *not* rely on it being safe!  Moreover
+/// there are probably countless **futher** issues.
 ///
 /// # Errors
 /// Returns an error if not a whole number of blocks was provided.
 pub fn aes_256_encrypt(
     input: &[u8],
-    key: [u8; AES_256_NK * 4],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
 ) -> Result<Vec<u8>, AesError>
 {
     aes_256_crypt(input, key, &aes_256_block_encrypt)
@@ -1052,22 +1289,62 @@ pub fn aes_256_encrypt(
 /// a whole number of 16-byte blocks are supplied an error will be
 /// returned.
 ///
-/// **Note**: The function takes ownership of the
-/// key and overwrites it with zeros **only** on a **best effort**
-/// basis, and I'm not a cryptographer so this is probably **insecure**,
-/// do *not* rely on it being safe!  Moreover there are probably
-/// countless **futher** issues.
+/// **Note**: The key is only borrowed and all copies of it the
+/// function makes are overwritten with zeros, but **only** on a
+/// **best effort** basis, and I'm not a cryptographer so this is
+/// probably **insecure**, do *not* rely on it being safe!  Moreover
+/// there are probably countless **futher** issues.
 ///
 /// # Errors
 /// Returns an error if not a whole number of blocks was provided.
 pub fn aes_256_decrypt(
     input: &[u8],
-    key: [u8; AES_256_NK * 4],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
 ) -> Result<Vec<u8>, AesError>
 {
     aes_256_crypt(input, key, &aes_256_block_decrypt)
 }
 
+/// Encrypts in place using AES-256
+///
+/// Like [`aes_256_encrypt`], but overwrites `buffer` with the cipher
+/// text instead of allocating a new one.  If `buffer` isn't a whole
+/// number of 16-byte blocks, it is left untouched and an error is
+/// returned.
+///
+/// **Note**: The same caveats as for [`aes_256_encrypt`] apply, so
+/// consider it **insecure**.
+///
+/// # Errors
+/// Returns an error if not a whole number of blocks was provided.
+pub fn aes_256_encrypt_in_place(
+    buffer: &mut [u8],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
+) -> Result<(), AesError>
+{
+    aes_256_crypt_in_place(buffer, key, &aes_256_block_encrypt)
+}
+
+/// Decrypts in place using AES-256
+///
+/// Like [`aes_256_decrypt`], but overwrites `buffer` with the plain
+/// text instead of allocating a new one.  If `buffer` isn't a whole
+/// number of 16-byte blocks, it is left untouched and an error is
+/// returned.
+///
+/// **Note**: The same caveats as for [`aes_256_decrypt`] apply, so
+/// consider it **insecure**.
+///
+/// # Errors
+/// Returns an error if not a whole number of blocks was provided.
+pub fn aes_256_decrypt_in_place(
+    buffer: &mut [u8],
+    key: &SecretKey<{ AES_256_NK * 4 }>,
+) -> Result<(), AesError>
+{
+    aes_256_crypt_in_place(buffer, key, &aes_256_block_decrypt)
+}
+
 #[cfg(test)]
 mod tests
 {
@@ -1083,7 +1360,7 @@ mod tests
             [0x19, 0xdb, 0xa1, 0xb4],
         ];
 
-        let mut putput = input.clone();
+        let mut putput = input;
 
         aes_256_sub_bytes(&mut putput);
         aes_256_inverse_sub_bytes(&mut putput);
@@ -1107,7 +1384,7 @@ mod tests
             [0xc6, 0xbc, 0x9d, 0x01],
         ];
 
-        let mut putput = input.clone();
+        let mut putput = input;
 
         aes_256_shift_rows(&mut putput);
         assert_eq!(putput, output);
@@ -1164,24 +1441,24 @@ mod tests
         assert_eq!(
             aes_256_decrypt(
                 &aes_256_encrypt(
-                    &mut [
+                    &[
                         0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88,
                         0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff
                     ],
-                    [
+                    &SecretKey::new([
                         0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
                         0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x11,
                         0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a,
                         0x1b, 0x1c, 0x1d, 0x1e, 0x1f
-                    ]
+                    ])
                 )
                 .unwrap(),
-                [
+                &SecretKey::new([
                     0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
                     0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x11,
                     0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a,
                     0x1b, 0x1c, 0x1d, 0x1e, 0x1f
-                ]
+                ])
             )
             .unwrap(),
             [
@@ -1203,7 +1480,7 @@ mod tests
             }
         }
         assert_eq!(
-            galois_product(45 ^ 34, 76),
+            galois_product(0x2d ^ 0x22, 76),
             galois_product(45, 76) ^ galois_product(34, 76)
         );
 
@@ -1225,6 +1502,42 @@ mod tests
         );
     }
 
+    #[test]
+    fn fast_galois_test()
+    {
+        // Simple xorshift, so that the test is reproducible
+        let mut state: u128 = 0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210;
+        let mut next = || {
+            state ^= state << 35;
+            state ^= state >> 59;
+            state ^= state << 13;
+            state
+        };
+
+        for _ in 0..200
+        {
+            let h = next();
+            let table = GhashTable::new(h);
+
+            for x in [0, 1, 1 << 127, u128::MAX, next(), next(), next()]
+            {
+                let expected = galois_product(x, h);
+
+                assert_eq!(galois_product_ct(x, h), expected);
+                assert_eq!(galois_product_ct(h, x), expected);
+                assert_eq!(table.product(x), expected);
+            }
+        }
+
+        assert_eq!(
+            galois_product_ct(
+                0x0388_DACE_60B6_A392_F328_C2B9_71B2_FE78,
+                0x66E9_4BD4_EF8A_2C3B_884C_FA59_CA34_2B2E
+            ),
+            0x5E2E_C746_9170_6288_2C85_B068_5353_DEB7
+        );
+    }
+
     #[test]
     fn gcm_test()
     {
@@ -1236,12 +1549,15 @@ mod tests
             0x49, 0xa6, 0xb5, 0x25, 0xb1, 0x6a, 0xed, 0xf5, 0xaa, 0x0d, 0xe6,
             0x57, 0xba, 0x63, 0x7b, 0x39, 0x1a, 0xaf, 0xd2, 0x55,
         ];
-        let key = [
+        let key = SecretKey::new([
             0xfe, 0xff, 0xe9, 0x92, 0x86, 0x65, 0x73, 0x1c, 0x6d, 0x6a, 0x8f,
             0x94, 0x67, 0x30, 0x83, 0x08, 0xfe, 0xff, 0xe9, 0x92, 0x86, 0x65,
             0x73, 0x1c, 0x6d, 0x6a, 0x8f, 0x94, 0x67, 0x30, 0x83, 0x08,
-        ];
-        let iv = [0xcafebabe, 0xfacedbad, 0xdecaf888];
+        ]);
+        let iv = Nonce::new([
+            0xca, 0xfe, 0xba, 0xbe, 0xfa, 0xce, 0xdb, 0xad, 0xde, 0xca, 0xf8,
+            0x88,
+        ]);
         let c = vec![
             0x52, 0x2d, 0xc1, 0xf0, 0x99, 0x56, 0x7d, 0x07, 0xf4, 0x7f, 0x37,
             0xa3, 0x2a, 0x84, 0x42, 0x7d, 0x64, 0x3a, 0x8c, 0xdc, 0xbf, 0xe5,
@@ -1252,11 +1568,105 @@ mod tests
         ];
         let tag = [0xb094dac5, 0xd93471bd, 0xec1a5022, 0x70e3cc6c];
         let wrongtag = [0xb094dac5, 0xd92471bd, 0xec1a5022, 0x70e3cc6c];
-        assert_eq!(aes_256_gcm_encrypt(&p, &[], key, iv), (c.clone(), tag));
-        assert_eq!(aes_256_gcm_decrypt(&c, &[], key, iv, tag), Ok(p));
+        assert_eq!(aes_256_gcm_encrypt(&p, &[], &key, &iv), (c.clone(), tag));
+        assert_eq!(aes_256_gcm_decrypt(&c, &[], &key, &iv, tag), Ok(p));
+        assert_eq!(
+            aes_256_gcm_decrypt(&c, &[], &key, &iv, wrongtag),
+            Err(AesError::VerifyingError)
+        );
+    }
+
+    #[test]
+    fn in_place_test()
+    {
+        let key = SecretKey::new([
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
+            0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15,
+            0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+        ]);
+        let p = (0..100).collect::<Vec<u8>>();
+        let iv = Nonce::new([
+            0xca, 0xfe, 0xba, 0xbe, 0xfa, 0xce, 0xdb, 0xad, 0xde, 0xca, 0xf8,
+            0x88,
+        ]);
+
+        let mut buffer = p[..96].to_vec();
+        aes_256_encrypt_in_place(&mut buffer, &key).unwrap();
+        assert_eq!(buffer, aes_256_encrypt(&p[..96], &key).unwrap());
+        aes_256_decrypt_in_place(&mut buffer, &key).unwrap();
+        assert_eq!(buffer, &p[..96]);
+
+        let mut buffer = p.clone();
+        assert_eq!(
+            aes_256_encrypt_in_place(&mut buffer, &key),
+            Err(AesError::NotWholeBlock)
+        );
+        assert_eq!(buffer, p);
+
+        let mut buffer = p.clone();
+        let tag = aes_256_gcm_encrypt_in_place(&mut buffer, b"aad", &key, &iv);
+        assert_eq!(
+            (buffer.clone(), tag),
+            aes_256_gcm_encrypt(&p, b"aad", &key, &iv)
+        );
+
+        // GCM is CTR starting with the second counter
+        let mut ctr = p.clone();
+        let mut counter = [0; 16];
+        counter[..12].copy_from_slice(iv.expose());
+        counter[15] = 2;
+        let counter = Nonce::new(counter);
+        aes_256_ctr_encrypt_in_place(&mut ctr, &key, &counter);
+        assert_eq!(ctr, buffer);
+        aes_256_ctr_decrypt_in_place(&mut ctr, &key, &counter);
+        assert_eq!(ctr, p);
+
+        let c = buffer.clone();
+        let mut wrongtag = tag;
+        wrongtag[2] ^= 1;
         assert_eq!(
-            aes_256_gcm_decrypt(&c, &[], key, iv, wrongtag),
+            aes_256_gcm_decrypt_in_place(
+                &mut buffer,
+                b"aad",
+                &key,
+                &iv,
+                wrongtag
+            ),
             Err(AesError::VerifyingError)
         );
+        assert_eq!(buffer, c);
+        assert_eq!(
+            aes_256_gcm_decrypt_in_place(&mut buffer, b"aad", &key, &iv, tag),
+            Ok(())
+        );
+        assert_eq!(buffer, p);
+    }
+
+    #[test]
+    fn long_gcm_test()
+    {
+        // More than 256 blocks, so that the counter has to carry
+        let key = SecretKey::new([
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
+            0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15,
+            0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+        ]);
+        let iv = Nonce::new([
+            0xca, 0xfe, 0xba, 0xbe, 0xfa, 0xce, 0xdb, 0xad, 0xde, 0xca, 0xf8,
+            0x88,
+        ]);
+        let p = (0..5000_usize).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        let (c, tag) = aes_256_gcm_encrypt(&p, b"evil", &key, &iv);
+
+        assert_eq!(
+            c[4096..4112],
+            [
+                0xd3, 0xe3, 0xe1, 0xdc, 0x5b, 0x49, 0xf3, 0x81, 0x1d, 0x60,
+                0xe3, 0x84, 0xdc, 0x17, 0xf4, 0xf0
+            ]
+        );
+        assert_eq!(tag, [0xf980_f796, 0x9ca4_7603, 0xccc1_ff70, 0x46e9_6ac2]);
+        assert_eq!(aes_256_gcm_decrypt(&c, b"evil", &key, &iv, tag), Ok(p));
     }
 }
@@ -0,0 +1,139 @@
+/*
+ * evilaes – Unsecure AES 256-bit implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Runs the `evilaes` binary with a key file
+//!
+//! Passphrases need a terminal, so they can't be tested here.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Output, Stdio};
+use std::thread;
+
+/// Removes the directory, even if an assertion failed
+struct TempDir(PathBuf);
+
+impl Drop for TempDir
+{
+    fn drop(&mut self)
+    {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn evilaes(args: &[&Path], stdin: &[u8]) -> Output
+{
+    let mut child = Command::new(env!("CARGO_BIN_EXE_evilaes"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // From another thread, since the child writes to stdout in the
+    // meantime and both pipes could fill up.
+    let mut pipe = child.stdin.take().unwrap();
+    let stdin = stdin.to_vec();
+    let writer = thread::spawn(move || pipe.write_all(&stdin));
+
+    let rv = child.wait_with_output().unwrap();
+    // If it failed early, it didn't read everything.
+    let _ = writer.join().unwrap();
+
+    rv
+}
+
+fn assert_exit(rv: &Output, code: i32)
+{
+    assert_eq!(
+        rv.status.code(),
+        Some(code),
+        "{}",
+        String::from_utf8_lossy(&rv.stderr)
+    );
+}
+
+#[test]
+fn cli_test()
+{
+    let temp = TempDir(
+        env::temp_dir().join(format!("evilaes-cli-{}", process::id())),
+    );
+    let dir = &temp.0;
+    fs::create_dir_all(dir).unwrap();
+    let key = dir.join("key");
+    let plain = dir.join("plain");
+    let container = dir.join("container");
+    let decrypted = dir.join("decrypted");
+    fs::write(&key, [7; 32]).unwrap();
+    // More than one chunk
+    let data = (0..200_000).map(|x| x as u8).collect::<Vec<_>>();
+    fs::write(&plain, &data).unwrap();
+
+    let encrypt = Path::new("encrypt");
+    let decrypt = Path::new("decrypt");
+    let key_file = Path::new("--key-file");
+    let output = Path::new("--output");
+
+    // Files
+    let rv =
+        evilaes(&[encrypt, key_file, &key, output, &container, &plain], b"");
+    assert_exit(&rv, 0);
+    let rv = evilaes(
+        &[decrypt, key_file, &key, output, &decrypted, &container],
+        b"",
+    );
+    assert_exit(&rv, 0);
+    assert_eq!(fs::read(&decrypted).unwrap(), data);
+
+    // stdin and stdout
+    let rv = evilaes(&[encrypt, key_file, &key], &data);
+    assert_exit(&rv, 0);
+    let rv = evilaes(&[decrypt, key_file, &key], &rv.stdout);
+    assert_exit(&rv, 0);
+    assert_eq!(rv.stdout, data);
+
+    // Tampered with at the end, after the first chunks were fine
+    fs::remove_file(&decrypted).unwrap();
+    let mut tampered = fs::read(&container).unwrap();
+    *tampered.last_mut().unwrap() ^= 1;
+    fs::write(&container, &tampered).unwrap();
+    let rv = evilaes(
+        &[decrypt, key_file, &key, output, &decrypted, &container],
+        b"",
+    );
+    assert_exit(&rv, 1);
+    let rv = evilaes(&[decrypt, key_file, &key], &tampered);
+    assert_exit(&rv, 1);
+    assert!(rv.stdout.is_empty());
+    // Nothing is left, not even the temporary file.
+    let mut left = fs::read_dir(dir)
+        .unwrap()
+        .map(|x| x.unwrap().file_name())
+        .collect::<Vec<_>>();
+    left.sort();
+    assert_eq!(left, ["container", "key", "plain"]);
+
+    // A key file of the wrong length and wrong arguments
+    fs::write(&key, [7; 31]).unwrap();
+    assert_exit(&evilaes(&[encrypt, key_file, &key], b""), 1);
+    assert_exit(&evilaes(&[encrypt, Path::new("--evil")], b""), 2);
+    assert_exit(&evilaes(&[], b""), 2);
+}
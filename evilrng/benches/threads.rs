@@ -0,0 +1,75 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! How well `RngSource` scales with the number of threads
+//!
+//! Run it with `cargo bench -p evilrng`.  Every thread gets the same
+//! number of `u32`s, so with perfect scaling the numbers per second
+//! grow linearly until the cores run out.
+
+use std::hint::black_box;
+use std::thread;
+use std::time::Instant;
+
+use evilrng::{RngError, RngSource};
+
+const CALLS: usize = 100_000;
+
+// Only for printing, so precision doesn't matter.
+#[allow(clippy::cast_precision_loss)]
+fn main()
+{
+    let cores = thread::available_parallelism().map_or(1, |x| x.get());
+    println!("{cores} cores available");
+
+    for threads in [1, 2, 4, 8, 16, 32, 64]
+    {
+        let start = Instant::now();
+
+        let handles = (0..threads)
+            .map(|_| {
+                thread::spawn(|| -> Result<(), RngError> {
+                    let mut rng = RngSource::new()?;
+                    for _ in 0..CALLS
+                    {
+                        black_box(rng.get_u32()?);
+                    }
+
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+        // A failed thread makes the measurement worthless, but the
+        // others can still be measured.
+        let errors = handles
+            .into_iter()
+            .filter_map(|x| x.join().unwrap().err())
+            .collect::<Vec<_>>();
+        if let Some(err) = errors.first()
+        {
+            println!("{threads:>2} threads: {} failed: {err}", errors.len());
+            continue;
+        }
+
+        let elapsed = start.elapsed().as_secs_f64();
+        println!(
+            "{threads:>2} threads: {:>12.0} numbers/s",
+            (threads * CALLS) as f64 / elapsed
+        );
+    }
+}
@@ -0,0 +1,977 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Arbitrary-precision unsigned integers
+//!
+//! Stored as little-endian 32 bit limbs without leading zeros, so
+//! that all intermediate products fit in a `u64`.  Nothing here runs
+//! in constant time.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Rem, Shl, Shr, Sub};
+
+use evilsecret::wipe;
+
+/// An arbitrary-precision unsigned integer
+///
+/// All the usual operators work on both owned values and references;
+/// subtraction panics if the result would be negative and division
+/// if the divisor is zero, like with the primitive types.  The limbs
+/// are wiped on drop, since these are often keys, but copies left
+/// behind by growing aren't.
+///
+/// ```
+/// # use evilrng::BigUint;
+/// let a = BigUint::from_str_radix("123456789012345678901234567890", 10)
+///     .unwrap();
+/// let b = BigUint::from(987_654_321_u64);
+///
+/// assert_eq!((&a * &b / &b), a);
+/// assert_eq!(a.mod_pow(&BigUint::from(2_u64), &b), &a * &a % &b);
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigUint
+{
+    limbs: Vec<u32>,
+}
+
+/// `a + b`
+fn add_limbs(a: &[u32], b: &[u32]) -> Vec<u32>
+{
+    let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut rv = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0;
+
+    for (i, &x) in a.iter().enumerate()
+    {
+        let sum = u64::from(x) + u64::from(*b.get(i).unwrap_or(&0)) + carry;
+        rv.push(low(sum));
+        carry = sum >> 32;
+    }
+    rv.push(low(carry));
+
+    rv
+}
+
+/// `a - b`, where `a` has to be at least `b`
+fn sub_limbs(a: &[u32], b: &[u32]) -> Vec<u32>
+{
+    let mut rv = Vec::with_capacity(a.len());
+    let mut borrow = false;
+
+    for (i, &x) in a.iter().enumerate()
+    {
+        let (diff, first) = x.overflowing_sub(*b.get(i).unwrap_or(&0));
+        let (diff, second) = diff.overflowing_sub(u32::from(borrow));
+        rv.push(diff);
+        borrow = first || second;
+    }
+    assert!(!borrow, "Subtraction underflowed");
+
+    rv
+}
+
+/// `a * b`
+fn mul_limbs(a: &[u32], b: &[u32]) -> Vec<u32>
+{
+    let mut rv = vec![0; a.len() + b.len()];
+
+    for (i, &x) in a.iter().enumerate()
+    {
+        let mut carry = 0;
+        for (j, &y) in b.iter().enumerate()
+        {
+            let product =
+                u64::from(x) * u64::from(y) + u64::from(rv[i + j]) + carry;
+            rv[i + j] = low(product);
+            carry = product >> 32;
+        }
+        rv[i + b.len()] = low(carry);
+    }
+
+    rv
+}
+
+/// Divides by a single limb
+fn div_rem_limb(a: &[u32], b: u32) -> (Vec<u32>, u32)
+{
+    let mut quotient = vec![0; a.len()];
+    let mut rem = 0_u64;
+
+    for (i, &x) in a.iter().enumerate().rev()
+    {
+        let current = rem << 32 | u64::from(x);
+        quotient[i] = low(current / u64::from(b));
+        rem = current % u64::from(b);
+    }
+
+    (quotient, low(rem))
+}
+
+/// Shifts left by less than a limb, with one more limb for the carry
+fn shl_bits(a: &[u32], shift: u32) -> Vec<u32>
+{
+    let mut rv = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0;
+
+    for &x in a
+    {
+        rv.push(x << shift | carry);
+        carry = if shift == 0 { 0 } else { x >> (32 - shift) };
+    }
+    rv.push(carry);
+
+    rv
+}
+
+/// Long division, algorithm D from Knuth's TAOCP, volume 2, 4.3.1
+///
+/// `b` needs at least two limbs and its highest can't be zero.
+// The casts between i64 and the limbs are how the algorithm handles
+// the borrows.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_possible_wrap
+)]
+fn div_rem_limbs(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>)
+{
+    let n = b.len();
+    let m = a.len() - n;
+    // Normalizes so that the highest bit of the divisor is set, which
+    // makes the estimated quotient digits off by at most two.
+    let shift = b[n - 1].leading_zeros();
+    let divisor = &shl_bits(b, shift)[..n];
+    let mut rem = shl_bits(a, shift);
+    let mut quotient = vec![0; m + 1];
+    let base = 1_u64 << 32;
+
+    for j in (0..=m).rev()
+    {
+        let top = u64::from(rem[j + n]) << 32 | u64::from(rem[j + n - 1]);
+        let mut estimate = top / u64::from(divisor[n - 1]);
+        let mut estimate_rem = top % u64::from(divisor[n - 1]);
+
+        while estimate >= base
+            || estimate * u64::from(divisor[n - 2])
+                > (estimate_rem << 32 | u64::from(rem[j + n - 2]))
+        {
+            estimate -= 1;
+            estimate_rem += u64::from(divisor[n - 1]);
+            if estimate_rem >= base
+            {
+                break;
+            }
+        }
+
+        // Multiplies and subtracts.
+        let mut borrow = 0_i64;
+        for i in 0..n
+        {
+            let product = estimate * u64::from(divisor[i]);
+            let diff = i64::from(rem[i + j])
+                - borrow
+                - (product & 0xffff_ffff) as i64;
+            rem[i + j] = diff as u32;
+            borrow = (product >> 32) as i64 - (diff >> 32);
+        }
+        let diff = i64::from(rem[j + n]) - borrow;
+        rem[j + n] = diff as u32;
+
+        quotient[j] = estimate as u32;
+        // The estimate was one too large, which is rare, so it's added
+        // back.
+        if diff < 0
+        {
+            quotient[j] = quotient[j].wrapping_sub(1);
+            let mut carry = 0;
+            for i in 0..n
+            {
+                let sum =
+                    u64::from(rem[i + j]) + u64::from(divisor[i]) + carry;
+                rem[i + j] = low(sum);
+                carry = sum >> 32;
+            }
+            rem[j + n] = rem[j + n].wrapping_add(low(carry));
+        }
+    }
+
+    rem.truncate(n);
+    let rem = BigUint::from_limbs(rem) >> shift as usize;
+
+    (quotient, rem.limbs.clone())
+}
+
+/// The lower 32 bits
+// That's the point.
+#[allow(clippy::cast_possible_truncation)]
+const fn low(x: u64) -> u32
+{
+    x as u32
+}
+
+impl BigUint
+{
+    fn from_limbs(limbs: Vec<u32>) -> Self
+    {
+        let mut rv = Self { limbs };
+        rv.normalize();
+
+        rv
+    }
+
+    fn normalize(&mut self)
+    {
+        while self.limbs.last() == Some(&0)
+        {
+            self.limbs.pop();
+        }
+    }
+
+    /// Zero
+    #[must_use]
+    pub const fn zero() -> Self
+    {
+        Self { limbs: Vec::new() }
+    }
+
+    /// One
+    #[must_use]
+    pub fn one() -> Self
+    {
+        Self::from(1_u32)
+    }
+
+    /// Reads a big-endian number of any length
+    #[must_use]
+    pub fn from_bytes_be(bytes: &[u8]) -> Self
+    {
+        let mut limbs = Vec::with_capacity(bytes.len().div_ceil(4));
+
+        for chunk in bytes.rchunks(4)
+        {
+            let mut limb = [0; 4];
+            limb[4 - chunk.len()..].copy_from_slice(chunk);
+            limbs.push(u32::from_be_bytes(limb));
+        }
+
+        Self::from_limbs(limbs)
+    }
+
+    /// Writes as big-endian without leading zeros
+    ///
+    /// Zero gives an empty vector.
+    #[must_use]
+    pub fn to_bytes_be(&self) -> Vec<u8>
+    {
+        let mut rv = self
+            .limbs
+            .iter()
+            .rev()
+            .flat_map(|x| x.to_be_bytes())
+            .skip_while(|&x| x == 0)
+            .collect::<Vec<_>>();
+        rv.shrink_to_fit();
+
+        rv
+    }
+
+    /// Writes as big-endian with exactly `len` bytes
+    ///
+    /// Returns [`None`] if the number doesn't fit.
+    #[must_use]
+    pub fn to_bytes_be_padded(&self, len: usize) -> Option<Vec<u8>>
+    {
+        let bytes = self.to_bytes_be();
+        let padding = len.checked_sub(bytes.len())?;
+
+        let mut rv = vec![0; padding];
+        rv.extend_from_slice(&bytes);
+
+        Some(rv)
+    }
+
+    /// Parses a number in base `radix`
+    ///
+    /// Returns [`None`] if `text` is empty or contains anything but
+    /// digits.
+    ///
+    /// # Panics
+    /// If `radix` isn't in 2..=36.
+    #[must_use]
+    pub fn from_str_radix(text: &str, radix: u32) -> Option<Self>
+    {
+        assert!((2..=36).contains(&radix), "Invalid radix");
+
+        if text.is_empty()
+        {
+            return None;
+        }
+
+        let mut limbs = vec![];
+        for digit in text.chars()
+        {
+            let digit = digit.to_digit(radix)?;
+            let mut carry = u64::from(digit);
+            for limb in &mut limbs
+            {
+                let current = u64::from(*limb) * u64::from(radix) + carry;
+                *limb = low(current);
+                carry = current >> 32;
+            }
+            if carry != 0
+            {
+                limbs.push(low(carry));
+            }
+        }
+
+        Some(Self::from_limbs(limbs))
+    }
+
+    /// The number of bits without leading zeros
+    #[must_use]
+    pub fn bits(&self) -> usize
+    {
+        self.limbs
+            .last()
+            .map_or(0, |&x| self.limbs.len() * 32 - x.leading_zeros() as usize)
+    }
+
+    /// Whether bit `i` is set
+    #[must_use]
+    pub fn bit(&self, i: usize) -> bool
+    {
+        self.limbs
+            .get(i / 32)
+            .is_some_and(|x| x >> (i % 32) & 1 == 1)
+    }
+
+    /// Sets bit `i`
+    pub fn set_bit(&mut self, i: usize)
+    {
+        if self.limbs.len() <= i / 32
+        {
+            self.limbs.resize(i / 32 + 1, 0);
+        }
+        self.limbs[i / 32] |= 1 << (i % 32);
+    }
+
+    /// Whether it's zero
+    #[must_use]
+    pub const fn is_zero(&self) -> bool
+    {
+        self.limbs.is_empty()
+    }
+
+    /// Whether it's even
+    #[must_use]
+    pub fn is_even(&self) -> bool
+    {
+        !self.bit(0)
+    }
+
+    /// Converts to a [`u64`] if it fits
+    #[must_use]
+    pub fn to_u64(&self) -> Option<u64>
+    {
+        match self.limbs[..]
+        {
+            [] => Some(0),
+            [x] => Some(u64::from(x)),
+            [x, y] => Some(u64::from(y) << 32 | u64::from(x)),
+            _ => None,
+        }
+    }
+
+    /// The remainder of a division by a small number, without
+    /// allocating
+    pub(crate) fn rem_u32(&self, divisor: u32) -> u32
+    {
+        let rem = self.limbs.iter().rev().fold(0_u64, |rem, &x| {
+            (rem << 32 | u64::from(x)) % u64::from(divisor)
+        });
+
+        low(rem)
+    }
+
+    /// Subtracts if the result isn't negative
+    #[must_use]
+    pub fn checked_sub(&self, other: &Self) -> Option<Self>
+    {
+        (*self >= *other)
+            .then(|| Self::from_limbs(sub_limbs(&self.limbs, &other.limbs)))
+    }
+
+    /// Quotient and remainder at once
+    ///
+    /// # Panics
+    /// If `other` is zero.
+    #[must_use]
+    pub fn div_rem(&self, other: &Self) -> (Self, Self)
+    {
+        assert!(!other.is_zero(), "Division by zero");
+
+        if self < other
+        {
+            return (Self::zero(), self.clone());
+        }
+
+        if let [divisor] = other.limbs[..]
+        {
+            let (quotient, rem) = div_rem_limb(&self.limbs, divisor);
+            return (Self::from_limbs(quotient), Self::from(rem));
+        }
+
+        let (quotient, rem) = div_rem_limbs(&self.limbs, &other.limbs);
+
+        (Self::from_limbs(quotient), Self::from_limbs(rem))
+    }
+
+    /// `(self + other) mod modulus`
+    ///
+    /// # Panics
+    /// If `modulus` is zero.
+    #[must_use]
+    pub fn mod_add(&self, other: &Self, modulus: &Self) -> Self
+    {
+        (self + other) % modulus
+    }
+
+    /// `(self - other) mod modulus`
+    ///
+    /// # Panics
+    /// If `modulus` is zero.
+    #[must_use]
+    pub fn mod_sub(&self, other: &Self, modulus: &Self) -> Self
+    {
+        let a = self % modulus;
+        let b = other % modulus;
+
+        if a >= b
+        {
+            a - b
+        }
+        else
+        {
+            modulus - &b + &a
+        }
+    }
+
+    /// `(self * other) mod modulus`
+    ///
+    /// # Panics
+    /// If `modulus` is zero.
+    #[must_use]
+    pub fn mod_mul(&self, other: &Self, modulus: &Self) -> Self
+    {
+        self * other % modulus
+    }
+
+    /// `self^exponent mod modulus`
+    ///
+    /// Square and multiply from the highest bit of `exponent`, which
+    /// leaks it through timing.
+    ///
+    /// # Panics
+    /// If `modulus` is zero.
+    #[must_use]
+    pub fn mod_pow(&self, exponent: &Self, modulus: &Self) -> Self
+    {
+        assert!(!modulus.is_zero(), "Division by zero");
+
+        let base = self % modulus;
+        let mut rv = Self::one() % modulus;
+
+        for i in (0..exponent.bits()).rev()
+        {
+            rv = rv.mod_mul(&rv, modulus);
+            if exponent.bit(i)
+            {
+                rv = rv.mod_mul(&base, modulus);
+            }
+        }
+
+        rv
+    }
+
+    /// The greatest common divisor
+    #[must_use]
+    pub fn gcd(&self, other: &Self) -> Self
+    {
+        let mut a = self.clone();
+        let mut b = other.clone();
+
+        while !b.is_zero()
+        {
+            let rem = &a % &b;
+            a = b;
+            b = rem;
+        }
+
+        a
+    }
+
+    /// The inverse modulo `modulus`
+    ///
+    /// Returns [`None`] if there is none, because the two aren't
+    /// coprime.
+    ///
+    /// # Panics
+    /// If `modulus` is zero.
+    #[must_use]
+    pub fn mod_inverse(&self, modulus: &Self) -> Option<Self>
+    {
+        // The extended Euclidean algorithm, but the coefficients are
+        // kept modulo `modulus`, so that they are never negative.
+        let mut r0 = modulus.clone();
+        let mut r1 = self % modulus;
+        let mut t0 = Self::zero();
+        let mut t1 = Self::one();
+
+        while !r1.is_zero()
+        {
+            let (quotient, rem) = r0.div_rem(&r1);
+            let t2 = t0.mod_sub(&quotient.mod_mul(&t1, modulus), modulus);
+            r0 = r1;
+            r1 = rem;
+            t0 = t1;
+            t1 = t2;
+        }
+
+        (r0 == Self::one()).then(|| t0 % modulus)
+    }
+}
+
+impl Drop for BigUint
+{
+    fn drop(&mut self)
+    {
+        wipe(self.limbs.as_mut_slice());
+    }
+}
+
+impl From<u32> for BigUint
+{
+    fn from(x: u32) -> Self
+    {
+        Self::from_limbs(vec![x])
+    }
+}
+
+impl From<u64> for BigUint
+{
+    fn from(x: u64) -> Self
+    {
+        Self::from_limbs(vec![low(x), low(x >> 32)])
+    }
+}
+
+impl Ord for BigUint
+{
+    fn cmp(&self, other: &Self) -> Ordering
+    {
+        self.limbs.len().cmp(&other.limbs.len()).then_with(|| {
+            self.limbs.iter().rev().cmp(other.limbs.iter().rev())
+        })
+    }
+}
+
+impl PartialOrd for BigUint
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering>
+    {
+        Some(self.cmp(other))
+    }
+}
+
+/// Implements an operator for all combinations of owned values and
+/// references by forwarding to the one on two references
+macro_rules! forward_op {
+    ($op:ident, $method:ident) => {
+        impl $op<Self> for BigUint
+        {
+            type Output = Self;
+
+            fn $method(self, other: Self) -> Self
+            {
+                (&self).$method(&other)
+            }
+        }
+
+        impl $op<&Self> for BigUint
+        {
+            type Output = Self;
+
+            fn $method(self, other: &Self) -> Self
+            {
+                (&self).$method(other)
+            }
+        }
+
+        impl $op<BigUint> for &BigUint
+        {
+            type Output = BigUint;
+
+            fn $method(self, other: BigUint) -> BigUint
+            {
+                self.$method(&other)
+            }
+        }
+    };
+}
+
+impl Add<&BigUint> for &BigUint
+{
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint
+    {
+        BigUint::from_limbs(add_limbs(&self.limbs, &other.limbs))
+    }
+}
+
+impl Sub<&BigUint> for &BigUint
+{
+    type Output = BigUint;
+
+    fn sub(self, other: &BigUint) -> BigUint
+    {
+        self.checked_sub(other).expect("Subtraction underflowed")
+    }
+}
+
+impl Mul<&BigUint> for &BigUint
+{
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint
+    {
+        BigUint::from_limbs(mul_limbs(&self.limbs, &other.limbs))
+    }
+}
+
+impl Div<&BigUint> for &BigUint
+{
+    type Output = BigUint;
+
+    fn div(self, other: &BigUint) -> BigUint
+    {
+        self.div_rem(other).0
+    }
+}
+
+impl Rem<&BigUint> for &BigUint
+{
+    type Output = BigUint;
+
+    fn rem(self, other: &BigUint) -> BigUint
+    {
+        self.div_rem(other).1
+    }
+}
+
+forward_op!(Add, add);
+forward_op!(Sub, sub);
+forward_op!(Mul, mul);
+forward_op!(Div, div);
+forward_op!(Rem, rem);
+
+impl Shl<usize> for &BigUint
+{
+    type Output = BigUint;
+
+    fn shl(self, shift: usize) -> BigUint
+    {
+        let mut limbs = vec![0; shift / 32];
+        // Less than 32, so it always fits.
+        #[allow(clippy::cast_possible_truncation)]
+        limbs.extend(shl_bits(&self.limbs, (shift % 32) as u32));
+
+        BigUint::from_limbs(limbs)
+    }
+}
+
+impl Shr<usize> for &BigUint
+{
+    type Output = BigUint;
+
+    fn shr(self, shift: usize) -> BigUint
+    {
+        let limbs = self.limbs.get(shift / 32..).unwrap_or(&[]);
+        let bits = shift % 32;
+        let rv = (0..limbs.len())
+            .map(|i| {
+                let high = limbs.get(i + 1).map_or(0, |&x| u64::from(x));
+                low((high << 32 | u64::from(limbs[i])) >> bits)
+            })
+            .collect();
+
+        BigUint::from_limbs(rv)
+    }
+}
+
+impl Shl<usize> for BigUint
+{
+    type Output = Self;
+
+    fn shl(self, shift: usize) -> Self
+    {
+        &self << shift
+    }
+}
+
+impl Shr<usize> for BigUint
+{
+    type Output = Self;
+
+    fn shr(self, shift: usize) -> Self
+    {
+        &self >> shift
+    }
+}
+
+impl fmt::Display for BigUint
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        // Nine decimal digits at once.
+        let mut chunks = vec![];
+        let mut rest = self.limbs.clone();
+        while !rest.is_empty()
+        {
+            let (quotient, rem) = div_rem_limb(&rest, 1_000_000_000);
+            chunks.push(rem);
+            rest = quotient;
+            while rest.last() == Some(&0)
+            {
+                rest.pop();
+            }
+        }
+
+        let mut text = chunks
+            .last()
+            .map_or_else(|| "0".to_owned(), ToString::to_string);
+        text.extend(chunks.iter().rev().skip(1).map(|x| format!("{x:09}")));
+
+        fmt.pad_integral(true, "", &text)
+    }
+}
+
+impl fmt::LowerHex for BigUint
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        let mut text = self
+            .limbs
+            .last()
+            .map_or_else(|| "0".to_owned(), |x| format!("{x:x}"));
+        text.extend(
+            self.limbs.iter().rev().skip(1).map(|x| format!("{x:08x}")),
+        );
+
+        fmt.pad_integral(true, "0x", &text)
+    }
+}
+
+impl fmt::Debug for BigUint
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        write!(fmt, "BigUint({self:#x})")
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::BigUint;
+    use crate::{Random, Rng, RngError};
+
+    fn big(text: &str) -> BigUint
+    {
+        BigUint::from_str_radix(text, 16).unwrap()
+    }
+
+    fn from_u128(x: u128) -> BigUint
+    {
+        BigUint::from_bytes_be(&x.to_be_bytes())
+    }
+
+    #[test]
+    fn small_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[21; 32]);
+
+        for _ in 0..2000
+        {
+            let a = rng.get_u64()? >> rng.gen_range(0..64_u32)?;
+            let b = (rng.get_u64()? >> rng.gen_range(0..64_u32)?).max(1);
+            let wide = rng.get_u128()? >> rng.gen_range(0..128_u32)?;
+            let (big_a, big_b) = (BigUint::from(a), BigUint::from(b));
+            let big_wide = from_u128(wide);
+
+            assert_eq!(
+                &big_a + &big_b,
+                from_u128(u128::from(a) + u128::from(b))
+            );
+            assert_eq!(
+                &big_a * &big_b,
+                from_u128(u128::from(a) * u128::from(b))
+            );
+            assert_eq!(&big_wide / &big_b, from_u128(wide / u128::from(b)));
+            assert_eq!(&big_wide % &big_b, from_u128(wide % u128::from(b)));
+            assert_eq!(
+                big_a.checked_sub(&big_b).and_then(|x| x.to_u64()),
+                a.checked_sub(b)
+            );
+            assert_eq!(&big_wide >> 7, from_u128(wide >> 7));
+            assert_eq!(&big_a << 33, from_u128(u128::from(a) << 33));
+            assert_eq!(big_wide.bits(), 128 - wide.leading_zeros() as usize);
+            assert_eq!(big_wide.to_string(), wide.to_string());
+            assert_eq!(format!("{big_wide:x}"), format!("{wide:x}"));
+        }
+
+        Ok(())
+    }
+
+    // Computed with Python.
+    #[test]
+    fn big_test()
+    {
+        let a = big(
+            "e2a6d2f1b0f5a8f3f1a2d8c7b6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c9d8e7\
+             f6a5b4c3d2e1f0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c9d8e7f6a5",
+        );
+        let b = big("c0ffee0123456789abcdef0fedcba987\
+                     65432100112233445566778899aabbcc\
+                     dd");
+        let m = big(
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+        );
+
+        assert_eq!(
+            &a * &b,
+            big("aadfb5197d754a593cd87365b380af44\
+                 82e86125cd43bddc90ed703a3f42da88\
+                 e03999910798bd9a83844aa5c2e03487\
+                 63e55066a850824579f0fab6742bedb9\
+                 90cf086ee0d85effac0e492a0eb3b202\
+                 b9d751719b8245e663be4f02f0d55268\
+                 71")
+        );
+        assert_eq!(
+            &a / &b,
+            big("12ca306bc1c5a48a253b1b19abe0e919\
+                 b34fd91433698b9939daa83a1107b40")
+        );
+        assert_eq!(
+            &a % &b,
+            big("453122997bab5d30040f9e8e5e733cb0\
+                 4620ea9e70c9376166197556ac23b690\
+                 65")
+        );
+        assert_eq!(
+            a.mod_pow(&b, &m),
+            big("3466636439a63416cd2fd6720235f90b\
+                 e17e9fa31d3052c4ab9754893f641258")
+        );
+        assert_eq!(
+            b.mod_inverse(&m).unwrap(),
+            big("f03a94718bdacec08008d87575813cf0\
+                 abc92348f4ec45e0e493389c2b6d545e")
+        );
+
+        // Needs the rare add back in the long division.
+        let a = big("7fffffff800000000000000000000000");
+        let b = big("800000000000000000000001");
+        assert_eq!(
+            a.div_rem(&b),
+            (big("fffffffe"), big("7fffffffffffffff00000002"))
+        );
+    }
+
+    #[test]
+    fn division_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[22; 32]);
+
+        for _ in 0..200
+        {
+            let mut a = vec![0; rng.gen_range(1..100_usize)?];
+            let mut b = vec![0; rng.gen_range(1..=a.len())?];
+            rng.fill_bytes(&mut a)?;
+            rng.fill_bytes(&mut b)?;
+            let (a, b) =
+                (BigUint::from_bytes_be(&a), BigUint::from_bytes_be(&b));
+            if b.is_zero()
+            {
+                continue;
+            }
+
+            let (quotient, rem) = a.div_rem(&b);
+            assert!(rem < b);
+            assert_eq!(quotient * &b + rem, a);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn inverse_test()
+    {
+        // The example from Wikipedia's RSA article, φ(3233) = 3120.
+        let m = BigUint::from(3_120_u64);
+
+        assert_eq!(
+            BigUint::from(17_u64).mod_inverse(&m),
+            Some(BigUint::from(2_753_u64))
+        );
+        assert_eq!(BigUint::from(65_u64).mod_inverse(&m), None);
+        assert_eq!(
+            BigUint::from(1_u64).mod_inverse(&BigUint::one()),
+            Some(BigUint::zero())
+        );
+        assert_eq!(
+            BigUint::from(12_u64).gcd(&BigUint::from(18_u64)),
+            BigUint::from(6_u64)
+        );
+    }
+
+    #[test]
+    fn bytes_test()
+    {
+        let x = BigUint::from_bytes_be(&[0, 0, 1, 2, 3, 4, 5]);
+
+        assert_eq!(x, BigUint::from(0x01_0203_0405_u64));
+        assert_eq!(x.to_bytes_be(), [1, 2, 3, 4, 5]);
+        assert_eq!(x.to_bytes_be_padded(7).unwrap(), [0, 0, 1, 2, 3, 4, 5]);
+        assert_eq!(x.to_bytes_be_padded(4), None);
+        assert!(BigUint::zero().to_bytes_be().is_empty());
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(BigUint::from_str_radix("", 10), None);
+        assert_eq!(BigUint::from_str_radix("12a", 10), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Subtraction underflowed")]
+    fn underflow_test()
+    {
+        let _ = BigUint::one() - BigUint::from(2_u64);
+    }
+}
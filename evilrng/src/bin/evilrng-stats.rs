@@ -0,0 +1,562 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![warn(
+    missing_docs,
+    missing_debug_implementations,
+    missing_copy_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_import_braces,
+    unused_qualifications,
+    rust_2018_idioms,
+    clippy::all,
+    clippy::pedantic,
+    clippy::nursery,
+    clippy::cargo
+)]
+#![allow(clippy::suspicious_else_formatting)]
+// All counts are far below 2^53.
+#![allow(clippy::cast_precision_loss)]
+
+//! Statistical tests of `RngSource`
+//!
+//! Runs the monobit, runs, serial, approximate entropy and cumulative
+//! sums tests of NIST SP 800-22, the poker test of FIPS 140-1 and a
+//! chi-square test on the bytes and prints their p-values.  A p-value
+//! below 0.01 is marked, but with this many tests that happens now
+//! and then even to a perfect generator; only if a test fails again
+//! and again something is wrong.  Passing says, of course, nothing
+//! about whether the numbers are predictable.
+//!
+//! ```text
+//! evilrng-stats [--bytes N]           run the tests on N bytes, at
+//!                                     least 65536
+//! evilrng-stats --dump [--bytes N]    write N (default: endless)
+//!                                     raw bytes to stdout
+//! ```
+//!
+//! The raw output can be piped into other test suites, e.g.
+//! `evilrng-stats --dump | dieharder -a -g 200`.
+
+use std::env;
+use std::f64::consts::{LN_2, PI};
+use std::io::{self, Write};
+use std::process;
+
+use evilrng::{Random, RngError, RngSource};
+
+/// A million bits, as recommended by SP 800-22
+const DEFAULT_BYTES: usize = 125_000;
+const CHUNK_LEN: usize = 1 << 16;
+const ALPHA: f64 = 0.01;
+/// The block length of the serial test
+const SERIAL_LEN: usize = 16;
+/// The block length of the approximate entropy test
+const ENTROPY_LEN: usize = 10;
+/// The serial test needs `SERIAL_LEN` < log2(n) - 2 for n bits, SP
+/// 800-22 2.11.7; the approximate entropy test needs fewer
+const MIN_BYTES: usize = 1 << SERIAL_LEN;
+
+/// The natural logarithm of the gamma function
+///
+/// Lanczos approximation with g = 7, good to about 15 digits.
+fn ln_gamma(x: f64) -> f64
+{
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5
+    {
+        // The reflection formula.
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let mut sum = COEFFICIENTS[0];
+    let mut i = 1.0;
+    for c in &COEFFICIENTS[1..]
+    {
+        sum += c / (x + i);
+        i += 1.0;
+    }
+
+    (2.0 * PI).sqrt().ln() + (x + 0.5).mul_add(t.ln(), -t) + sum.ln()
+}
+
+/// The regularized upper incomplete gamma function Q(a, x)
+///
+/// With the series for small `x` and a continued fraction otherwise,
+/// as in Numerical Recipes.
+// The names are those of the formulas and the loops stop at a relative
+// difference, so NaN can only stop them earlier.
+#[allow(clippy::many_single_char_names, clippy::while_float)]
+fn igamc(a: f64, x: f64) -> f64
+{
+    const EPSILON: f64 = 1e-15;
+    const TINY: f64 = 1e-300;
+
+    if x <= 0.0
+    {
+        return 1.0;
+    }
+
+    let prefix = a.mul_add(x.ln(), -x) - ln_gamma(a);
+
+    if x < a + 1.0
+    {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut n = a;
+        while term.abs() > sum.abs() * EPSILON
+        {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+        }
+
+        return 1.0 - sum * prefix.exp();
+    }
+
+    // Lentz's method.
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    let mut i = 1.0;
+    loop
+    {
+        let an = -i * (i - a);
+        b += 2.0;
+        d = an.mul_add(d, b);
+        if d.abs() < TINY
+        {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY
+        {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPSILON
+        {
+            break;
+        }
+        i += 1.0;
+    }
+
+    prefix.exp() * h
+}
+
+/// The complementary error function
+fn erfc(x: f64) -> f64
+{
+    if x < 0.0
+    {
+        2.0 - igamc(0.5, x * x)
+    }
+    else
+    {
+        igamc(0.5, x * x)
+    }
+}
+
+/// The cumulative distribution function of the standard normal
+fn normal_cdf(x: f64) -> f64
+{
+    0.5 * erfc(-x / 2.0_f64.sqrt())
+}
+
+/// Splits `bytes` into bits, most significant first
+fn bits(bytes: &[u8]) -> Vec<u8>
+{
+    bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+        .collect()
+}
+
+/// Frequency (monobit) test, SP 800-22 2.1
+fn monobit(bits: &[u8]) -> f64
+{
+    let sum = bits
+        .iter()
+        .map(|&x| 2.0_f64.mul_add(f64::from(x), -1.0))
+        .sum::<f64>();
+
+    erfc(sum.abs() / (bits.len() as f64).sqrt() / 2.0_f64.sqrt())
+}
+
+/// Runs test, SP 800-22 2.3
+fn runs(bits: &[u8]) -> f64
+{
+    let n = bits.len() as f64;
+    let pi = bits.iter().map(|&x| f64::from(x)).sum::<f64>() / n;
+
+    // The frequency test has to pass first.
+    if (pi - 0.5).abs() >= 2.0 / n.sqrt()
+    {
+        return 0.0;
+    }
+
+    let runs = 1 + bits.windows(2).filter(|x| x[0] != x[1]).count();
+    let expected = 2.0 * n * pi * (1.0 - pi);
+
+    erfc(
+        (runs as f64 - expected).abs()
+            / (2.0 * (2.0 * n).sqrt() * pi * (1.0 - pi)),
+    )
+}
+
+/// Poker test with 4-bit hands, FIPS 140-1
+fn poker(bits: &[u8]) -> f64
+{
+    let mut counts = [0_u32; 16];
+    for hand in bits.chunks_exact(4)
+    {
+        counts[hand.iter().fold(0, |acc, &x| acc << 1 | usize::from(x))] += 1;
+    }
+
+    let k = (bits.len() / 4) as f64;
+    let squares = counts.iter().map(|&x| f64::from(x).powi(2)).sum::<f64>();
+    let chi = (16.0 / k).mul_add(squares, -k);
+
+    igamc(7.5, chi / 2.0)
+}
+
+/// How often every `m` bit pattern appears, wrapping around at the end
+fn patterns(bits: &[u8], m: usize) -> Vec<u32>
+{
+    let mut counts = vec![0; 1 << m];
+    if m == 0
+    {
+        return counts;
+    }
+
+    let mask = (1 << m) - 1;
+    let mut pattern = 0;
+    for (i, &bit) in bits.iter().chain(&bits[..m - 1]).enumerate()
+    {
+        pattern = (pattern << 1 | usize::from(bit)) & mask;
+        if i + 1 >= m
+        {
+            counts[pattern] += 1;
+        }
+    }
+
+    counts
+}
+
+/// Serial test, SP 800-22 2.11
+///
+/// Returns both p-values.
+fn serial(bits: &[u8], m: usize) -> (f64, f64)
+{
+    let n = bits.len() as f64;
+    let psi = |m: usize| {
+        if m == 0
+        {
+            return 0.0;
+        }
+
+        let sum = patterns(bits, m)
+            .iter()
+            .map(|&x| f64::from(x).powi(2))
+            .sum::<f64>();
+        sum * (1_u64 << m) as f64 / n - n
+    };
+
+    let (psi0, psi1, psi2) = (psi(m), psi(m - 1), psi(m - 2));
+    let delta = psi0 - psi1;
+    let delta2 = 2.0_f64.mul_add(-psi1, psi0) + psi2;
+
+    (
+        igamc((1_u64 << (m - 2)) as f64, delta / 2.0),
+        igamc((1_u64 << (m - 3)) as f64, delta2 / 2.0),
+    )
+}
+
+/// Approximate entropy test, SP 800-22 2.12
+fn approximate_entropy(bits: &[u8], m: usize) -> f64
+{
+    let n = bits.len() as f64;
+    let phi = |m: usize| {
+        patterns(bits, m)
+            .iter()
+            .filter(|&&x| x != 0)
+            .map(|&x| f64::from(x) / n * (f64::from(x) / n).ln())
+            .sum::<f64>()
+    };
+
+    let entropy = phi(m) - phi(m + 1);
+    let chi = 2.0 * n * (LN_2 - entropy);
+
+    igamc((1_u64 << (m - 1)) as f64, chi / 2.0)
+}
+
+/// Cumulative sums test, SP 800-22 2.13
+// The bounds of the sums are meant to be truncated.
+#[allow(clippy::cast_possible_truncation)]
+fn cusum(bits: &[u8], backward: bool) -> f64
+{
+    let n = bits.len() as f64;
+    let steps = bits.iter().map(|&x| {
+        if x == 1
+        {
+            1_i64
+        }
+        else
+        {
+            -1
+        }
+    });
+    let z = if backward
+    {
+        max_excursion(steps.rev())
+    }
+    else
+    {
+        max_excursion(steps)
+    } as f64;
+
+    let term = |k: i64, a: i64, b: i64| {
+        let k = k as f64;
+        normal_cdf(4.0_f64.mul_add(k, a as f64) * z / n.sqrt())
+            - normal_cdf(4.0_f64.mul_add(k, b as f64) * z / n.sqrt())
+    };
+
+    let end = ((n / z - 1.0) / 4.0) as i64;
+    let first = (((-n / z + 1.0) / 4.0) as i64..=end)
+        .map(|k| term(k, 1, -1))
+        .sum::<f64>();
+    let second = (((-n / z - 3.0) / 4.0) as i64..=end)
+        .map(|k| term(k, 3, 1))
+        .sum::<f64>();
+
+    1.0 - first + second
+}
+
+fn max_excursion(steps: impl Iterator<Item = i64>) -> i64
+{
+    steps
+        .scan(0, |sum, x| {
+            *sum += x;
+            Some(sum.abs())
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Chi-square test on the distribution of the bytes
+fn chi_square(bytes: &[u8]) -> f64
+{
+    let mut counts = [0_u32; 256];
+    for &byte in bytes
+    {
+        counts[usize::from(byte)] += 1;
+    }
+
+    let expected = bytes.len() as f64 / 256.0;
+    let chi = counts
+        .iter()
+        .map(|&x| (f64::from(x) - expected).powi(2) / expected)
+        .sum::<f64>();
+
+    igamc(127.5, chi / 2.0)
+}
+
+fn report(name: &str, p: f64)
+{
+    let mark = if p < ALPHA { "  FAIL" } else { "" };
+    println!("{name:<28} p = {p:.6}{mark}");
+}
+
+fn dump(len: Option<usize>) -> Result<(), RngError>
+{
+    let mut rng = RngSource::new()?;
+    let mut buffer = vec![0; CHUNK_LEN];
+    let mut stdout = io::stdout().lock();
+    let mut left = len;
+
+    while left != Some(0)
+    {
+        let len = left.map_or(CHUNK_LEN, |x| x.min(CHUNK_LEN));
+        rng.fill_bytes(&mut buffer[..len])?;
+
+        match stdout.write_all(&buffer[..len])
+        {
+            Ok(()) => (),
+            // Whoever reads has enough.
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe =>
+            {
+                return Ok(())
+            }
+            Err(err) => return Err(err.into()),
+        }
+        left = left.map(|x| x - len);
+    }
+
+    stdout.flush()?;
+
+    Ok(())
+}
+
+fn test(len: usize) -> Result<(), RngError>
+{
+    let mut bytes = vec![0; len];
+    RngSource::new()?.fill_bytes(&mut bytes)?;
+    let bits = bits(&bytes);
+
+    println!("{} bits from RngSource\n", bits.len());
+
+    let (serial1, serial2) = serial(&bits, SERIAL_LEN);
+    report("Monobit", monobit(&bits));
+    report("Runs", runs(&bits));
+    report("Poker", poker(&bits));
+    report("Serial 1", serial1);
+    report("Serial 2", serial2);
+    report(
+        "Approximate entropy",
+        approximate_entropy(&bits, ENTROPY_LEN),
+    );
+    report("Cumulative sums (forward)", cusum(&bits, false));
+    report("Cumulative sums (backward)", cusum(&bits, true));
+    report("Chi-square (bytes)", chi_square(&bytes));
+
+    Ok(())
+}
+
+fn usage() -> !
+{
+    eprintln!("Usage: evilrng-stats [--dump] [--bytes N]");
+    process::exit(2);
+}
+
+fn main()
+{
+    let mut dump_bytes = false;
+    let mut len = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next()
+    {
+        match arg.as_str()
+        {
+            "--dump" => dump_bytes = true,
+            "--bytes" => match args.next().and_then(|x| x.parse().ok())
+            {
+                Some(x) => len = Some(x),
+                None => usage(),
+            },
+            _ => usage(),
+        }
+    }
+
+    if !dump_bytes && len.is_some_and(|x| x < MIN_BYTES)
+    {
+        usage();
+    }
+
+    let rv = if dump_bytes
+    {
+        dump(len)
+    }
+    else
+    {
+        test(len.unwrap_or(DEFAULT_BYTES))
+    };
+
+    if let Err(err) = rv
+    {
+        eprintln!("evilrng-stats: {err}");
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{
+        approximate_entropy, chi_square, cusum, erfc, igamc, monobit, poker,
+        runs, serial,
+    };
+
+    fn close(a: f64, b: f64) -> bool
+    {
+        (a - b).abs() < 1e-6
+    }
+
+    fn parse(bits: &str) -> Vec<u8>
+    {
+        bits.bytes().map(|x| x - b'0').collect()
+    }
+
+    #[test]
+    fn special_functions_test()
+    {
+        assert!(close(erfc(0.0), 1.0));
+        assert!(close(erfc(1.0), 0.157_299_207));
+        assert!(close(erfc(-0.5), 1.520_499_878));
+        // The examples of SP 800-22 for igamc.
+        assert!(close(igamc(1.0, 1.0), 0.367_879));
+        assert!(close(igamc(3.0, 2.0), 0.676_676_416));
+        assert!(close(igamc(127.5, 127.5), 0.488_222_522));
+    }
+
+    // The examples from SP 800-22.
+    #[test]
+    fn nist_test()
+    {
+        assert!(close(monobit(&parse("1011010101")), 0.527_089));
+        assert!(close(runs(&parse("1001101011")), 0.147_232));
+        let (first, second) = serial(&parse("0011011101"), 3);
+        assert!(close(first, 0.808_792));
+        assert!(close(second, 0.670_320));
+        assert!(close(
+            approximate_entropy(&parse("0100110101"), 3),
+            0.261_961
+        ));
+        assert!(close(cusum(&parse("1011010111"), false), 0.411_658_8));
+    }
+
+    #[test]
+    fn bad_input_test()
+    {
+        let zeros = vec![0; 4096];
+        let bits = zeros.iter().map(|_| 0).collect::<Vec<u8>>();
+
+        assert!(monobit(&bits) < 1e-10);
+        assert!(poker(&bits) < 1e-10);
+        assert!(chi_square(&zeros) < 1e-10);
+    }
+}
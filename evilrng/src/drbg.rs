@@ -0,0 +1,602 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Deterministic random bit generators from NIST SP 800-90A
+//!
+//! Unlike the rest of evilrng these aren't designed by me, but follow
+//! the standard (as far as I understood it) step by step.  Prediction
+//! resistance isn't supported; reseed yourself if you need it.
+
+use std::fmt;
+
+use evilsecret::wipe;
+use evilsha::{hmac, sha_2, ShaVersion};
+
+use crate::RngError;
+
+/// After how many requests a reseed is required
+pub const RESEED_INTERVAL: u64 = 1 << 48;
+/// How many bytes can be requested at once
+pub const MAX_REQUEST_LEN: usize = 1 << 16;
+/// How many bytes of entropy are needed at least
+///
+/// All supported hash functions give a security strength of 256 bits.
+pub const MIN_ENTROPY_LEN: usize = 32;
+
+const fn check_entropy(entropy: &[u8]) -> Result<(), RngError>
+{
+    if entropy.len() < MIN_ENTROPY_LEN
+    {
+        Err(RngError::NotEnoughEntropy)
+    }
+    else
+    {
+        Ok(())
+    }
+}
+
+const fn check_request(
+    output: &[u8],
+    reseed_counter: u64,
+) -> Result<(), RngError>
+{
+    if output.len() > MAX_REQUEST_LEN
+    {
+        Err(RngError::RequestTooLarge)
+    }
+    else if reseed_counter > RESEED_INTERVAL
+    {
+        Err(RngError::ReseedRequired)
+    }
+    else
+    {
+        Ok(())
+    }
+}
+
+/// Hashes the concatenation of `parts`
+fn hash(version: ShaVersion, parts: &[&[u8]]) -> Vec<u8>
+{
+    let mut input = parts.concat();
+    let rv = sha_2(&input, version).as_bytes().to_vec();
+    wipe(input.as_mut_slice());
+
+    rv
+}
+
+/// Adds `rhs` to `lhs` modulo 2^(8 * `lhs.len()`)
+///
+/// Both are big endian and `rhs` mustn't be longer than `lhs`.
+// Only the lower byte of `sum` is used, the rest is the carry.
+#[allow(clippy::cast_possible_truncation)]
+fn add_assign(lhs: &mut [u8], rhs: &[u8])
+{
+    debug_assert!(rhs.len() <= lhs.len());
+
+    let mut carry = 0_u16;
+    let mut rhs = rhs.iter().rev();
+
+    for byte in lhs.iter_mut().rev()
+    {
+        let sum = u16::from(*byte)
+            + u16::from(rhs.next().copied().unwrap_or(0))
+            + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+}
+
+/// The NIST `Hash_DRBG`
+///
+/// The internal state is overwritten with zeros when it's dropped.
+///
+/// ```
+/// # use evilrng::{HashDrbg, RngError, ShaVersion};
+/// # fn main() -> Result<(), RngError>
+/// # {
+/// let mut drbg = HashDrbg::new(ShaVersion::Sha256, &[42; 32], &[], b"")?;
+/// let mut output = [0; 16];
+/// drbg.generate(&mut output, &[])?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct HashDrbg
+{
+    version: ShaVersion,
+    v: Vec<u8>,
+    c: Vec<u8>,
+    reseed_counter: u64,
+}
+
+impl HashDrbg
+{
+    const fn seed_len(version: ShaVersion) -> usize
+    {
+        match version
+        {
+            ShaVersion::Sha256 => 55,
+            ShaVersion::Sha384 | ShaVersion::Sha512 => 111,
+        }
+    }
+
+    /// `Hash_df`, the hash derivation function
+    // The seed length is at most 111 bytes.
+    #[allow(clippy::cast_possible_truncation)]
+    fn derive(version: ShaVersion, parts: &[&[u8]]) -> Vec<u8>
+    {
+        let len = Self::seed_len(version);
+        let bits = ((len * 8) as u32).to_be_bytes();
+        let mut rv = Vec::with_capacity(len + version.output_len());
+        let mut counter = 1_u8;
+
+        while rv.len() < len
+        {
+            let mut block =
+                hash(version, &[&[&[counter][..], &bits[..]], parts].concat());
+            rv.extend_from_slice(&block);
+            wipe(block.as_mut_slice());
+            counter += 1;
+        }
+
+        rv.truncate(len);
+
+        rv
+    }
+
+    /// Instantiates a new `Hash_DRBG`
+    ///
+    /// `entropy` has to be at least [`MIN_ENTROPY_LEN`] bytes long,
+    /// the `nonce` and the `personalization` string are optional.
+    ///
+    /// # Errors
+    /// It returns an error if `entropy` is too short.
+    pub fn new(
+        version: ShaVersion,
+        entropy: &[u8],
+        nonce: &[u8],
+        personalization: &[u8],
+    ) -> Result<Self, RngError>
+    {
+        check_entropy(entropy)?;
+
+        let v = Self::derive(version, &[entropy, nonce, personalization]);
+        let c = Self::derive(version, &[&[0], &v]);
+
+        Ok(Self {
+            version,
+            v,
+            c,
+            reseed_counter: 1,
+        })
+    }
+
+    /// Reseeds with fresh `entropy`
+    ///
+    /// # Errors
+    /// It returns an error if `entropy` is too short.
+    pub fn reseed(
+        &mut self,
+        entropy: &[u8],
+        additional_input: &[u8],
+    ) -> Result<(), RngError>
+    {
+        check_entropy(entropy)?;
+
+        let v = Self::derive(
+            self.version,
+            &[&[1], &self.v, entropy, additional_input],
+        );
+        let c = Self::derive(self.version, &[&[0], &v]);
+        wipe(self.v.as_mut_slice());
+        wipe(self.c.as_mut_slice());
+        self.v = v;
+        self.c = c;
+        self.reseed_counter = 1;
+
+        Ok(())
+    }
+
+    /// Fills `output` with pseudo random bytes
+    ///
+    /// # Errors
+    /// It returns an error if more than [`MAX_REQUEST_LEN`] bytes are
+    /// requested or a reseed is required.
+    pub fn generate(
+        &mut self,
+        output: &mut [u8],
+        additional_input: &[u8],
+    ) -> Result<(), RngError>
+    {
+        check_request(output, self.reseed_counter)?;
+
+        if !additional_input.is_empty()
+        {
+            let mut w = hash(self.version, &[&[2], &self.v, additional_input]);
+            add_assign(&mut self.v, &w);
+            wipe(w.as_mut_slice());
+        }
+
+        let mut data = self.v.clone();
+        for chunk in output.chunks_mut(self.version.output_len())
+        {
+            let mut block = hash(self.version, &[&data]);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+            wipe(block.as_mut_slice());
+            add_assign(&mut data, &[1]);
+        }
+        wipe(data.as_mut_slice());
+
+        let mut h = hash(self.version, &[&[3], &self.v]);
+        add_assign(&mut self.v, &h);
+        add_assign(&mut self.v, &self.c);
+        add_assign(&mut self.v, &self.reseed_counter.to_be_bytes());
+        wipe(h.as_mut_slice());
+        self.reseed_counter += 1;
+
+        Ok(())
+    }
+}
+
+impl Drop for HashDrbg
+{
+    fn drop(&mut self)
+    {
+        wipe(self.v.as_mut_slice());
+        wipe(self.c.as_mut_slice());
+    }
+}
+
+impl fmt::Debug for HashDrbg
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        fmt.debug_struct("HashDrbg")
+            .field("version", &self.version)
+            .field("reseed_counter", &self.reseed_counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The NIST `HMAC_DRBG`
+///
+/// The internal state is overwritten with zeros when it's dropped.
+///
+/// ```
+/// # use evilrng::{HmacDrbg, RngError, ShaVersion};
+/// # fn main() -> Result<(), RngError>
+/// # {
+/// let mut drbg = HmacDrbg::new(ShaVersion::Sha256, &[42; 32], &[], b"")?;
+/// let mut output = [0; 16];
+/// drbg.generate(&mut output, &[])?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct HmacDrbg
+{
+    version: ShaVersion,
+    k: Vec<u8>,
+    v: Vec<u8>,
+    reseed_counter: u64,
+}
+
+impl HmacDrbg
+{
+    fn mac(&self, parts: &[&[u8]]) -> Vec<u8>
+    {
+        let mut input = parts.concat();
+        let rv = hmac(&self.k, &input, self.version).as_bytes().to_vec();
+        wipe(input.as_mut_slice());
+
+        rv
+    }
+
+    /// `HMAC_DRBG_Update`
+    fn update(&mut self, provided: &[&[u8]])
+    {
+        let mut provided = provided.concat();
+
+        for round in 0..2
+        {
+            if round == 1 && provided.is_empty()
+            {
+                break;
+            }
+
+            let k = self.mac(&[&self.v, &[round], &provided]);
+            wipe(self.k.as_mut_slice());
+            self.k = k;
+
+            let v = self.mac(&[&self.v]);
+            wipe(self.v.as_mut_slice());
+            self.v = v;
+        }
+
+        wipe(provided.as_mut_slice());
+    }
+
+    /// Instantiates a new `HMAC_DRBG`
+    ///
+    /// `entropy` has to be at least [`MIN_ENTROPY_LEN`] bytes long,
+    /// the `nonce` and the `personalization` string are optional.
+    ///
+    /// # Errors
+    /// It returns an error if `entropy` is too short.
+    pub fn new(
+        version: ShaVersion,
+        entropy: &[u8],
+        nonce: &[u8],
+        personalization: &[u8],
+    ) -> Result<Self, RngError>
+    {
+        check_entropy(entropy)?;
+
+        let mut rv = Self {
+            version,
+            k: vec![0; version.output_len()],
+            v: vec![1; version.output_len()],
+            reseed_counter: 1,
+        };
+        rv.update(&[entropy, nonce, personalization]);
+
+        Ok(rv)
+    }
+
+    /// Reseeds with fresh `entropy`
+    ///
+    /// # Errors
+    /// It returns an error if `entropy` is too short.
+    pub fn reseed(
+        &mut self,
+        entropy: &[u8],
+        additional_input: &[u8],
+    ) -> Result<(), RngError>
+    {
+        check_entropy(entropy)?;
+
+        self.update(&[entropy, additional_input]);
+        self.reseed_counter = 1;
+
+        Ok(())
+    }
+
+    /// Fills `output` with pseudo random bytes
+    ///
+    /// # Errors
+    /// It returns an error if more than [`MAX_REQUEST_LEN`] bytes are
+    /// requested or a reseed is required.
+    pub fn generate(
+        &mut self,
+        output: &mut [u8],
+        additional_input: &[u8],
+    ) -> Result<(), RngError>
+    {
+        check_request(output, self.reseed_counter)?;
+
+        if !additional_input.is_empty()
+        {
+            self.update(&[additional_input]);
+        }
+
+        for chunk in output.chunks_mut(self.version.output_len())
+        {
+            let v = self.mac(&[&self.v]);
+            wipe(self.v.as_mut_slice());
+            self.v = v;
+            chunk.copy_from_slice(&self.v[..chunk.len()]);
+        }
+
+        self.update(&[additional_input]);
+        self.reseed_counter += 1;
+
+        Ok(())
+    }
+}
+
+impl Drop for HmacDrbg
+{
+    fn drop(&mut self)
+    {
+        wipe(self.k.as_mut_slice());
+        wipe(self.v.as_mut_slice());
+    }
+}
+
+impl fmt::Debug for HmacDrbg
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        fmt.debug_struct("HmacDrbg")
+            .field("version", &self.version)
+            .field("reseed_counter", &self.reseed_counter)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{HashDrbg, HmacDrbg};
+    use crate::RngError;
+    use evilsha::ShaVersion;
+
+    fn hex(s: &str) -> Vec<u8>
+    {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hash_drbg_test() -> Result<(), RngError>
+    {
+        // COUNT = 0 of [SHA-256] in Hash_DRBG.rsp from the NIST test
+        // vectors without reseed
+        let mut drbg = HashDrbg::new(
+            ShaVersion::Sha256,
+            &hex("a65ad0f345db4e0effe875c3a2e71f42\
+                  c7129d620ff5c119a9ef55f05185e0fb"),
+            &hex("8581f9317517276e06e9607ddbcbcc2e"),
+            &[],
+        )?;
+        let mut output = [0; 128];
+        drbg.generate(&mut output, &[])?;
+        drbg.generate(&mut output, &[])?;
+        assert_eq!(
+            output[..],
+            hex("d3e160c35b99f340b2628264d1751060e0045da383ff57a57d73a673\
+                 d2b8d80daaf6a6c35a91bb4579d73fd0c8fed111b0391306828adfed\
+                 528f018121b3febdc343e797b87dbb63db1333ded9d1ece177cfa6b7\
+                 1fe8ab1da46624ed6415e51ccde2c7ca86e283990eeaeb9112041552\
+                 8b2295910281b02dd431f4c9f70427df")[..]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn hmac_drbg_test() -> Result<(), RngError>
+    {
+        // COUNT = 0 of [SHA-256] in HMAC_DRBG.rsp from the NIST test
+        // vectors without reseed
+        let mut drbg = HmacDrbg::new(
+            ShaVersion::Sha256,
+            &hex("ca851911349384bffe89de1cbdc46e68\
+                  31e44d34a4fb935ee285dd14b71a7488"),
+            &hex("659ba96c601dc69fc902940805ec0ca8"),
+            &[],
+        )?;
+        let mut output = [0; 128];
+        drbg.generate(&mut output, &[])?;
+        drbg.generate(&mut output, &[])?;
+        assert_eq!(
+            output[..],
+            hex("e528e9abf2dece54d47c7e75e5fe302149f817ea9fb4bee6f4199697\
+                 d04d5b89d54fbb978a15b5c443c9ec21036d2460b6f73ebad0dc2aba\
+                 6e624abf07745bc107694bb7547bb0995f70de25d6b29e2d3011bb19\
+                 d27676c07162c8b5ccde0668961df86803482cb37ed6d5c0bb8d50cf\
+                 1f50d476aa0458bdaba806f48be9dcb8")[..]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn drbg_inputs_test() -> Result<(), RngError>
+    {
+        // Not from NIST, but computed with a second implementation that
+        // gives the NIST values above, so that personalization strings,
+        // additional input, reseeding and SHA-512 are tested too.
+        let entropy = (0..32).collect::<Vec<u8>>();
+        let nonce = (0x20..0x30).collect::<Vec<u8>>();
+        let reseed = (0x40..0x60).collect::<Vec<u8>>();
+        let mut output = [0; 100];
+
+        let mut drbg = HashDrbg::new(
+            ShaVersion::Sha512,
+            &entropy,
+            &nonce,
+            b"evilrng personalization",
+        )?;
+        drbg.reseed(&reseed, b"reseed")?;
+        drbg.generate(&mut output, b"first")?;
+        drbg.generate(&mut output, b"second")?;
+        assert_eq!(
+            output[..],
+            hex("0b47bd124d15becbbd64e4a5f0aa73a80bcce4510f9dec098d2d9943\
+                 3b23267bdf00f403a8ffd4a93f95cb13f066b3b30a4713db86027f33\
+                 984d4a6d819273534f20bfe9f4d08660b7a6e72e1fffde3a60fa5504\
+                 dc7dd275a0fd91d069fdd528a92d7201")[..]
+        );
+
+        let mut drbg = HmacDrbg::new(
+            ShaVersion::Sha512,
+            &entropy,
+            &nonce,
+            b"evilrng personalization",
+        )?;
+        drbg.reseed(&reseed, b"reseed")?;
+        drbg.generate(&mut output, b"first")?;
+        drbg.generate(&mut output, b"second")?;
+        assert_eq!(
+            output[..],
+            hex("7300a51547a57c2b49e6d6c7134da371a650548f9536d8b8d6bbef84\
+                 8a8a06c1441fd2174be09723a8b412e16e1fdf04183d65e189b5ad30\
+                 bb0123837cc82a0caeefb34cdddcf28601d3e37ec6a3eecc702210a4\
+                 a3857ed9511072e80c143ada2703ba94")[..]
+        );
+
+        let mut drbg = HashDrbg::new(
+            ShaVersion::Sha256,
+            &entropy,
+            &nonce,
+            b"evilrng personalization",
+        )?;
+        drbg.reseed(&reseed, b"reseed")?;
+        drbg.generate(&mut output, b"first")?;
+        drbg.generate(&mut output, b"second")?;
+        assert_eq!(
+            output[..],
+            hex("e5277d29d86e858828b75a129267053125da7cae2d18eae08d785b3f\
+                 14fda8c2fcdfde123a206080463438216e02e92b4f370f1d9d2ce161\
+                 e41d0f7f764c25db908fb08fceaf29df34cb60e3ca33e5e907a44deb\
+                 a06d14c639ce1f5763f84146164db907")[..]
+        );
+
+        let mut drbg = HmacDrbg::new(
+            ShaVersion::Sha256,
+            &entropy,
+            &nonce,
+            b"evilrng personalization",
+        )?;
+        drbg.reseed(&reseed, b"reseed")?;
+        drbg.generate(&mut output, b"first")?;
+        drbg.generate(&mut output, b"second")?;
+        assert_eq!(
+            output[..],
+            hex("3db9e89f8614136eb912a551c9c9aefbfa454bb9eb674cde34431431\
+                 f37555a87b6c3f4d30deebeda3e8aaa3cdd891135619951e345877c4\
+                 1e71041de37895dd4cfb6afa4e89bd166ddf7fdd833e016709e0c8fa\
+                 c2cd558c05a3b9e49ca69a720ffa5e6d")[..]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn drbg_errors_test()
+    {
+        assert!(matches!(
+            HashDrbg::new(ShaVersion::Sha256, &[0; 31], &[], &[]),
+            Err(RngError::NotEnoughEntropy)
+        ));
+
+        let mut drbg =
+            HmacDrbg::new(ShaVersion::Sha256, &[0; 32], &[], &[]).unwrap();
+        assert!(matches!(
+            drbg.generate(&mut vec![0; (1 << 16) + 1], &[]),
+            Err(RngError::RequestTooLarge)
+        ));
+        assert!(matches!(
+            drbg.reseed(&[0; 16], &[]),
+            Err(RngError::NotEnoughEntropy)
+        ));
+    }
+}
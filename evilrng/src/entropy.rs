@@ -0,0 +1,371 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Gathering of entropy
+//!
+//! Every source writes its samples tagged with its own id and their
+//! length into a [`Sink`], so that e.g. a few timer bytes can never be
+//! mistaken for jitter samples.  Sources that produce a lot of
+//! samples with only a bit of entropy each are condensed with SHA-256
+//! first, so no source ever gives more than 32 bytes at once.
+
+use std::convert::TryFrom;
+#[cfg(feature = "urandom")]
+use std::fs::File;
+use std::hint::black_box;
+#[cfg(feature = "urandom")]
+use std::io::Read;
+use std::process;
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+use evilsecret::wipe;
+use evilsha::{sha_2, ShaHash, ShaVersion};
+
+use crate::{health, RngError};
+
+/// How many jitter samples are taken when seeding
+const SEED_JITTER_SAMPLES: usize = 512;
+/// How many jitter samples are taken on every request
+const JITTER_SAMPLES: usize = 16;
+/// How many timings are folded into one jitter sample
+const JITTER_FOLD: usize = 8;
+/// The work loop starts with that many rounds
+const MIN_ROUNDS: u32 = 32;
+/// And is never made longer than this
+const MAX_ROUNDS: u32 = 1 << 16;
+/// How often samples that failed a health test are thrown away and
+/// taken again before giving up
+const JITTER_RETRIES: usize = 3;
+
+static TIMER_START: OnceLock<Instant> = OnceLock::new();
+/// The length of the work loop, 0 until it's calibrated
+static JITTER_ROUNDS: AtomicU32 = AtomicU32::new(0);
+
+/// Where samples come from
+#[derive(Copy, Clone, Debug)]
+pub enum Source
+{
+    /// Given to [`RngSource::add_entropy`](crate::RngSource::add_entropy)
+    User = 0,
+    Clock = 1,
+    Timer = 2,
+    Jitter = 3,
+    Process = 4,
+    #[cfg(feature = "urandom")]
+    Urandom = 5,
+}
+
+/// Something entropy can be collected in
+pub trait Sink
+{
+    /// Adds `samples` from `source`
+    fn add(&mut self, source: Source, samples: &[u8]);
+}
+
+/// Simply appends all samples, e.g. to get a seed at once
+impl Sink for Vec<u8>
+{
+    fn add(&mut self, source: Source, samples: &[u8])
+    {
+        self.push(source as u8);
+        self.push(u8::try_from(samples.len()).expect("Too many samples"));
+        self.extend_from_slice(samples);
+    }
+}
+
+fn mix_condensed(pool: &mut impl Sink, source: Source, samples: &[u8])
+{
+    match sha_2(samples, ShaVersion::Sha256)
+    {
+        ShaHash::Sha256(mut x) =>
+        {
+            pool.add(source, &x);
+            wipe(&mut x);
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// The wall clock
+///
+/// Mostly predictable, but it makes sure that two boots with an
+/// otherwise equal state don't give the same numbers.
+fn clock(pool: &mut impl Sink)
+{
+    let time = match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+    {
+        Ok(time) => time,
+        // A clock before 1970 is strange, but not less random.
+        Err(err) => err.duration(),
+    };
+
+    pool.add(Source::Clock, &time.as_nanos().to_le_bytes());
+}
+
+/// A monotonic high resolution timer
+///
+/// Counted from the first time it is used, so mostly the low bits
+/// contain entropy.
+fn timer(pool: &mut impl Sink)
+{
+    let start = TIMER_START.get_or_init(Instant::now);
+
+    pool.add(Source::Timer, &start.elapsed().as_nanos().to_le_bytes());
+}
+
+/// Measures how long `rounds` rounds of a bit of work take
+///
+/// The time depends on caches, branch prediction, interrupts, the
+/// frequency scaling and so on, which I hope can't be predicted
+/// exactly even by someone on the same machine.
+// Only the low bits are random anyway.
+#[allow(clippy::cast_possible_truncation)]
+fn time_work(rounds: u32, work: &mut u32) -> u64
+{
+    let start = Instant::now();
+    for i in 0..rounds
+    {
+        *work = black_box(work.rotate_left(5) ^ i).wrapping_mul(0x0100_0193);
+    }
+
+    start.elapsed().as_nanos() as u64
+}
+
+/// The length of the work loop
+///
+/// On the first call the loop is doubled until less than half of the
+/// consecutive timings are equal, so that a coarse timer (e.g. on
+/// VMs) still sees some differences.
+fn jitter_rounds() -> u32
+{
+    let rounds = JITTER_ROUNDS.load(Ordering::Relaxed);
+    if rounds != 0
+    {
+        return rounds;
+    }
+
+    let mut work = 0x9e37_79b9_u32;
+    let mut rounds = MIN_ROUNDS;
+    while rounds < MAX_ROUNDS
+    {
+        let mut last = time_work(rounds, &mut work);
+        let equal = (0..64)
+            .filter(|_| {
+                let time = time_work(rounds, &mut work);
+                let equal = time == last;
+                last = time;
+                equal
+            })
+            .count();
+        if equal < 32
+        {
+            break;
+        }
+        rounds *= 2;
+    }
+    // If two threads calibrate at once, both results are fine.
+    JITTER_ROUNDS.store(rounds, Ordering::Relaxed);
+
+    rounds
+}
+
+/// Takes `samples` jitter samples
+///
+/// A single timing often repeats the one before (on my machine in
+/// about 40 % of the cases), so every sample folds [`JITTER_FOLD`]
+/// timings into one byte.
+// Only the low bits are random anyway.
+#[allow(clippy::cast_possible_truncation)]
+pub fn jitter(samples: usize) -> Vec<u8>
+{
+    let rounds = jitter_rounds();
+    let mut work = 0x9e37_79b9_u32;
+
+    (0..samples)
+        .map(|_| {
+            let mut sample = 0_u64;
+            for _ in 0..JITTER_FOLD
+            {
+                sample = sample.rotate_left(1) ^ time_work(rounds, &mut work);
+            }
+
+            sample as u8
+        })
+        .collect()
+}
+
+/// Takes jitter samples that passed the health tests
+///
+/// # Errors
+/// See [`checked_samples`].
+fn checked_jitter(samples: usize) -> Result<Vec<u8>, RngError>
+{
+    checked_samples(samples, jitter)
+}
+
+/// Takes samples from `source` that passed the health tests
+///
+/// Even a perfect source fails the Adaptive Proportion Test now and
+/// then (see [`health`]), so after that the samples are thrown away
+/// and taken again a few times.  A failed Repetition Count Test isn't
+/// retried, and a source that stays broken fails every time.
+///
+/// # Errors
+/// It returns an error if the samples (or the samples of the startup
+/// tests, if they haven't run yet) failed a health test, or the
+/// Adaptive Proportion Test [`JITTER_RETRIES`] times in a row.
+fn checked_samples(
+    samples: usize,
+    source: fn(usize) -> Vec<u8>,
+) -> Result<Vec<u8>, RngError>
+{
+    let mut tries = 0;
+
+    loop
+    {
+        let mut rv = source(samples);
+        match health::check(&rv, source)
+        {
+            Ok(()) => return Ok(rv),
+            Err(RngError::AdaptiveProportion) if tries < JITTER_RETRIES =>
+            {
+                wipe(rv.as_mut_slice());
+                tries += 1;
+            }
+            Err(err) =>
+            {
+                wipe(rv.as_mut_slice());
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// Process and thread ids and the address of the stack
+///
+/// Not secret at all, but it makes sure that different processes and
+/// threads don't end up with the same state.
+fn process(pool: &mut impl Sink)
+{
+    let local = 0_u8;
+    let mut samples = vec![];
+
+    samples.extend_from_slice(&process::id().to_le_bytes());
+    samples
+        .extend_from_slice(format!("{:?}", thread::current().id()).as_bytes());
+    samples.extend_from_slice(&ptr::from_ref(&local).addr().to_le_bytes());
+
+    mix_condensed(pool, Source::Process, &samples);
+}
+
+/// Entropy from the operating system
+///
+/// Only used with the `urandom` feature, since evilrng is meant to do
+/// everything itself.
+#[cfg(feature = "urandom")]
+fn urandom(pool: &mut impl Sink) -> Result<(), RngError>
+{
+    let mut buffer = [0; 32];
+
+    File::open("/dev/urandom")?.read_exact(&mut buffer)?;
+    pool.add(Source::Urandom, &buffer);
+    wipe(&mut buffer);
+
+    Ok(())
+}
+
+/// Gathers a bit of entropy cheaply
+///
+/// Used on every request, so it only takes a few measurements.
+///
+/// # Errors
+/// It returns an error if the jitter failed a health test.
+pub fn gather(pool: &mut impl Sink) -> Result<(), RngError>
+{
+    timer(pool);
+    pool.add(Source::Jitter, &checked_jitter(JITTER_SAMPLES)?);
+
+    Ok(())
+}
+
+/// Gathers enough entropy to seed from all sources
+///
+/// # Errors
+/// It returns an error if the timer is too coarse to measure any
+/// jitter, the jitter failed a health test or (with the `urandom`
+/// feature) `/dev/urandom` couldn't be read.
+pub fn gather_seed(pool: &mut impl Sink) -> Result<(), RngError>
+{
+    clock(pool);
+    timer(pool);
+    process(pool);
+
+    let mut samples = checked_jitter(SEED_JITTER_SAMPLES)?;
+    if samples.iter().all(|&x| x == samples[0])
+    {
+        return Err(RngError::NoJitter);
+    }
+    mix_condensed(pool, Source::Jitter, &samples);
+    wipe(samples.as_mut_slice());
+
+    #[cfg(feature = "urandom")]
+    urandom(pool)?;
+
+    timer(pool);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{checked_jitter, checked_samples, gather, JITTER_SAMPLES};
+    use crate::RngError;
+
+    #[test]
+    fn stuck_source_test() -> Result<(), RngError>
+    {
+        // Runs the startup tests with the real jitter.
+        checked_jitter(JITTER_SAMPLES)?;
+
+        // The timer stops after the start, so every sample is the same.
+        let stuck = |samples| vec![42; samples];
+        let errors = (0..10_000)
+            .filter(|_| {
+                matches!(
+                    checked_samples(JITTER_SAMPLES, stuck),
+                    Err(RngError::RepetitionCount)
+                )
+            })
+            .count();
+        // Only the first request is shorter than the cutoff.
+        assert!(errors >= 9_999);
+
+        // The failure is sticky, also for the real jitter.
+        assert!(matches!(
+            gather(&mut vec![]),
+            Err(RngError::RepetitionCount)
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Health tests of the jitter samples
+//!
+//! The Repetition Count Test and the Adaptive Proportion Test of NIST
+//! SP 800-90B, section 4.4.  Both only notice if the source breaks
+//! down completely, e.g. if the timer suddenly stops or always
+//! measures the same, but that's exactly the case in which everything
+//! would otherwise go on looking random while being predictable.
+//!
+//! Every jitter sample folds eight timings.  The min-entropy of a
+//! sample I estimated from a million of them as -log2 of the most
+//! common value's frequency: between 3.8 and 5.6 bits on my machine,
+//! depending on the optimisation level and the work loop.  The samples
+//! aren't independent, so I only assess them with 1 bit and use a
+//! false positive probability of 2^-20 per sample; the cutoffs are
+//! those from the standard for these parameters.
+//!
+//! Like the standard intends, a failed Repetition Count Test stops the
+//! source for good (in the thread it happened in).  A single window
+//! that fails the Adaptive Proportion Test can happen to a perfect
+//! source now and then, so the samples may be taken again; if the next
+//! window fails as well, the source is stopped too.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::RngError;
+
+/// How often a sample may repeat in a row, 1 + ⌈20 / 1⌉
+const REPETITION_CUTOFF: u32 = 21;
+/// The window of the Adaptive Proportion Test for non-binary samples
+const PROPORTION_WINDOW: u32 = 512;
+/// How often the first sample of a window may appear in it,
+/// 1 + CRITBINOM(512, 2^-1, 1 - 2^-20)
+const PROPORTION_CUTOFF: u32 = 311;
+/// How many samples have to pass before the first use
+pub const STARTUP_SAMPLES: usize = 1024;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static HEALTH: RefCell<HealthTest> =
+        const { RefCell::new(HealthTest::new()) };
+}
+
+/// A failure the source can't recover from
+#[derive(Debug, Clone, Copy)]
+enum Failure
+{
+    Repetition,
+    Proportion,
+}
+
+impl Failure
+{
+    const fn error(self) -> RngError
+    {
+        match self
+        {
+            Self::Repetition => RngError::RepetitionCount,
+            Self::Proportion => RngError::AdaptiveProportion,
+        }
+    }
+}
+
+/// The state of both tests
+///
+/// They are meant to run continuously over all samples, so every
+/// thread keeps its own state between requests, also after a failure.
+#[derive(Debug, Clone)]
+struct HealthTest
+{
+    last: u8,
+    repetitions: u32,
+    first: u8,
+    occurrences: u32,
+    seen: u32,
+    /// Whether the last window failed the Adaptive Proportion Test
+    window_failed: bool,
+    broken: Option<Failure>,
+}
+
+impl HealthTest
+{
+    const fn new() -> Self
+    {
+        Self {
+            last: 0,
+            repetitions: 0,
+            first: 0,
+            occurrences: 0,
+            seen: PROPORTION_WINDOW,
+            window_failed: false,
+            broken: None,
+        }
+    }
+
+    const fn check_sample(&mut self, sample: u8) -> Result<(), RngError>
+    {
+        if let Some(failure) = self.broken
+        {
+            return Err(failure.error());
+        }
+
+        if self.repetitions > 0 && sample == self.last
+        {
+            self.repetitions += 1;
+            if self.repetitions >= REPETITION_CUTOFF
+            {
+                self.broken = Some(Failure::Repetition);
+                return Err(RngError::RepetitionCount);
+            }
+        }
+        else
+        {
+            self.last = sample;
+            self.repetitions = 1;
+        }
+
+        if self.seen == PROPORTION_WINDOW
+        {
+            self.first = sample;
+            self.occurrences = 1;
+            self.seen = 1;
+        }
+        else
+        {
+            self.seen += 1;
+            if sample == self.first
+            {
+                self.occurrences += 1;
+                if self.occurrences >= PROPORTION_CUTOFF
+                {
+                    if self.window_failed
+                    {
+                        self.broken = Some(Failure::Proportion);
+                    }
+                    // The next sample starts a new window.
+                    self.window_failed = true;
+                    self.seen = PROPORTION_WINDOW;
+                    return Err(RngError::AdaptiveProportion);
+                }
+            }
+            if self.seen == PROPORTION_WINDOW
+            {
+                self.window_failed = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks all `samples`
+    ///
+    /// The state continues after a failure, so that a source that
+    /// stays broken can't pass by being retried.
+    fn check(&mut self, samples: &[u8]) -> Result<(), RngError>
+    {
+        samples.iter().try_for_each(|&x| self.check_sample(x))
+    }
+}
+
+/// Checks that the tests themselves work
+///
+/// A stuck and a biased source have to fail and a perfectly uniform
+/// one has to pass.
+fn known_answer_test() -> Result<(), RngError>
+{
+    let stuck = [7; REPETITION_CUTOFF as usize];
+    // Four out of five are equal, but never more than four in a row.
+    let biased = (0..PROPORTION_WINDOW)
+        .map(|i| {
+            if i % 5 == 4
+            {
+                5
+            }
+            else
+            {
+                4
+            }
+        })
+        .collect::<Vec<_>>();
+    let uniform = (0..=u8::MAX).cycle().take(4 * 256).collect::<Vec<_>>();
+
+    match (
+        HealthTest::new().check(&stuck),
+        HealthTest::new().check(&biased),
+        HealthTest::new().check(&uniform),
+    )
+    {
+        (
+            Err(RngError::RepetitionCount),
+            Err(RngError::AdaptiveProportion),
+            Ok(()),
+        ) => Ok(()),
+        _ => Err(RngError::SelfTest),
+    }
+}
+
+/// Runs the startup tests
+///
+/// First the tests are checked with known answers, then
+/// [`STARTUP_SAMPLES`] fresh samples from `source` have to pass them.
+/// The samples are thrown away afterwards.
+///
+/// # Errors
+/// It returns an error if any of the tests fails.
+pub fn self_test(source: impl FnOnce(usize) -> Vec<u8>)
+    -> Result<(), RngError>
+{
+    known_answer_test()?;
+    HealthTest::new().check(&source(STARTUP_SAMPLES))?;
+    STARTED.store(true, Ordering::SeqCst);
+
+    Ok(())
+}
+
+/// Checks `samples` with the continuous tests
+///
+/// The startup tests are run first if they haven't passed yet in this
+/// process.
+///
+/// # Errors
+/// It returns an error if any of the tests fails, in which case the
+/// samples mustn't be used.
+pub fn check(
+    samples: &[u8],
+    source: impl FnOnce(usize) -> Vec<u8>,
+) -> Result<(), RngError>
+{
+    if !STARTED.load(Ordering::SeqCst)
+    {
+        self_test(source)?;
+    }
+
+    HEALTH.with(|health| health.borrow_mut().check(samples))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{
+        known_answer_test, HealthTest, PROPORTION_CUTOFF, PROPORTION_WINDOW,
+        REPETITION_CUTOFF,
+    };
+    use crate::RngError;
+
+    #[test]
+    fn repetition_test()
+    {
+        let mut health = HealthTest::new();
+        let mut samples = vec![1, 2];
+        samples.extend(vec![3; REPETITION_CUTOFF as usize - 1]);
+
+        assert!(health.check(&samples).is_ok());
+        // The state continues over calls.
+        assert!(matches!(health.check(&[3]), Err(RngError::RepetitionCount)));
+        // And the source stays broken, even if it recovers.
+        assert!(matches!(health.check(&[3]), Err(RngError::RepetitionCount)));
+        assert!(matches!(health.check(&[4]), Err(RngError::RepetitionCount)));
+    }
+
+    #[test]
+    fn proportion_test()
+    {
+        // The fours are spread evenly, so that the repetition count never
+        // fails, and there is one less than the cutoff.
+        let fours = PROPORTION_CUTOFF - 1;
+        let samples = (0..PROPORTION_WINDOW)
+            .map(|i| {
+                if i * fours % PROPORTION_WINDOW < fours
+                {
+                    4
+                }
+                else
+                {
+                    5
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut health = HealthTest::new();
+
+        assert!(health.check(&samples).is_ok());
+        // Only the first sample of a window is counted.
+        assert!(health.check(&[5; 20]).is_ok());
+
+        let mut failing = samples.clone();
+        let five = failing.iter().rposition(|&x| x == 5).unwrap();
+        failing[five] = 4;
+        let mut health = HealthTest::new();
+        assert!(matches!(
+            health.check(&failing),
+            Err(RngError::AdaptiveProportion)
+        ));
+        // A single failed window may be retried, the next one starts
+        // with the next samples.
+        assert!(health.check(&samples).is_ok());
+        assert!(matches!(
+            health.check(&failing),
+            Err(RngError::AdaptiveProportion)
+        ));
+        // But after two in a row the source stays broken.
+        assert!(matches!(
+            health.check(&failing),
+            Err(RngError::AdaptiveProportion)
+        ));
+        assert!(matches!(
+            health.check(&samples),
+            Err(RngError::AdaptiveProportion)
+        ));
+    }
+
+    #[test]
+    fn known_answer() -> Result<(), RngError>
+    {
+        known_answer_test()
+    }
+}
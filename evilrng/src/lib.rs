@@ -52,264 +52,519 @@
 //! that evilrng is **even on the algorithm level broken**.  The
 //! advantage is that now all cryptographical code in or used by the
 //! evil\* crates is fully written by me, what is exactly the goal.
-
-use std::mem;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::thread;
-use std::time::{SystemTime, SystemTimeError};
-
-use evilsha::{sha_2, ShaHash, ShaVersion};
-
-static RNG_LOCKED: AtomicBool = AtomicBool::new(false);
-static mut RNG_SOURCE: Option<InnerRngSource> = None;
+//!
+//! The entropy comes from the wall clock, a monotonic timer, the jitter
+//! of short calculations and the process and thread ids; with the
+//! `urandom` feature `/dev/urandom` is mixed in as well.  The jitter
+//! is checked with the health tests of NIST SP 800-90B (see
+//! [`self_test`]), so that a source that stopped working gives errors
+//! instead of predictable numbers.
+//!
+//! Right after a boot there is only little entropy, so if your
+//! programme can keep a file, use a [`SeedFile`] to carry entropy over
+//! to the next start.
+//!
+//! For tests the `replay` feature makes [`RngSource`] deterministic if
+//! the environment variable `EVILRNG_SEED` is set: the generator of
+//! every thread is then seeded from its value and the name of the
+//! thread, and the value is printed on first use, so that a failed test
+//! can be repeated.  Without the variable the feature changes nothing.
+//!
+//! For public-key cryptography there is [`BigUint`], an
+//! arbitrary-precision unsigned integer with modular arithmetic, and
+//! [`random_prime`], which generates probable primes with the
+//! Miller–Rabin test.
+
+use std::cell::RefCell;
+use std::error;
+use std::fmt;
+use std::io;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use evilsecret::wipe;
+
+pub use bigint::BigUint;
+pub use drbg::{
+    HashDrbg, HmacDrbg, MAX_REQUEST_LEN, MIN_ENTROPY_LEN, RESEED_INTERVAL,
+};
+pub use evilsha::ShaVersion;
+pub use prime::{
+    is_probable_prime, random_below, random_bits, random_prime, PRIME_ROUNDS,
+};
+pub use random::{Random, SampleRange};
+pub use rng::{Rng, SEED_LEN};
+pub use secrets::{
+    passphrase, password, token, Uuid, DIGITS, LOWERCASE, SYMBOLS, UPPERCASE,
+};
+pub use seed_file::{SeedFile, SEED_FILE_LEN};
+
+mod bigint;
+mod drbg;
+mod entropy;
+mod health;
+mod pool;
+mod prime;
+mod random;
+mod replay;
+mod rng;
+mod secrets;
+mod seed_file;
 
 /// Provides unsecure cryptographical secure random numbers
+///
+/// All `RngSource`s share the same global state; get one with
+/// [`RngSource::new`].  Every thread has its own generator, which is
+/// seeded from a shared entropy pool and regularly reseeded from it,
+/// so threads don't have to wait for each other.  If you need an own
+/// stream, e.g. a reproducible one, use a [`Rng`] instead.
 #[derive(Copy, Clone, Debug)]
 pub struct RngSource;
 
+/// Errors from evilrng
 #[derive(Debug)]
-struct InnerRngSource
+#[non_exhaustive]
+pub enum RngError
 {
-    entropy: [u8; 64],
-    newentropy: Vec<u8>,
-    used: usize,
-    fresh: u8,
+    /// The timer is too coarse to measure any jitter, so there is
+    /// (nearly) no entropy.
+    NoJitter,
+    /// The operating system's entropy couldn't be read.
+    Io(io::Error),
+    /// Less entropy than needed was given to seed a generator.
+    NotEnoughEntropy,
+    /// More bytes than allowed were requested at once.
+    RequestTooLarge,
+    /// The generator was used too often since it was (re)seeded.
+    ReseedRequired,
+    /// The same jitter sample was measured too often in a row.
+    RepetitionCount,
+    /// One jitter sample was measured too often in a window.
+    AdaptiveProportion,
+    /// The health tests didn't detect a broken source.
+    SelfTest,
 }
 
-impl Default for RngSource
+impl fmt::Display for RngError
 {
-    fn default() -> Self
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
     {
-        Self
+        match self
+        {
+            Self::NoJitter =>
+            {
+                write!(fmt, "The timer is too coarse to measure jitter")
+            }
+            Self::Io(err) =>
+            {
+                write!(fmt, "Entropy couldn't be read: {err}")
+            }
+            Self::NotEnoughEntropy =>
+            {
+                write!(fmt, "Not enough entropy to seed the generator")
+            }
+            Self::RequestTooLarge =>
+            {
+                write!(fmt, "Too many bytes were requested at once")
+            }
+            Self::ReseedRequired =>
+            {
+                write!(fmt, "The generator has to be reseeded")
+            }
+            Self::RepetitionCount =>
+            {
+                write!(fmt, "The jitter failed the repetition count test")
+            }
+            Self::AdaptiveProportion =>
+            {
+                write!(fmt, "The jitter failed the adaptive proportion test")
+            }
+            Self::SelfTest =>
+            {
+                write!(fmt, "The self-test of the health tests failed")
+            }
+        }
     }
 }
 
-impl Drop for InnerRngSource
+impl error::Error for RngError
 {
-    #[allow(unsafe_code)]
-    fn drop(&mut self)
+    fn source(&self) -> Option<&(dyn error::Error + 'static)>
     {
-        // As far as I know always true, but this is my first time
-        // working with atomics, so I'm a bit cautios.
-        debug_assert!(RNG_LOCKED.load(Ordering::SeqCst));
-
-        let replacer = Self {
-            entropy: [0; 64],
-            newentropy: vec![],
-            used: 0,
-            fresh: 0,
-        };
-
-        // SAFETY: There can always only be one `InnerRngSource`, so
-        // we must have the lock.
-        unsafe {
-            RNG_SOURCE = Some(mem::replace(self, replacer));
+        match self
+        {
+            Self::Io(err) => Some(err),
+            _ => None,
         }
-
-        RNG_LOCKED.store(false, Ordering::SeqCst);
     }
 }
 
-impl InnerRngSource
+impl From<io::Error> for RngError
 {
-    #[allow(unsafe_code)]
-    fn get() -> Self
+    fn from(err: io::Error) -> Self
     {
-        while RNG_LOCKED
-            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-            .is_err()
-        {
-            thread::yield_now();
-        }
+        Self::Io(err)
+    }
+}
 
-        // As far as I know always true, but this is my first time
-        // working with atomics, so I'm a bit cautios.
-        debug_assert!(RNG_LOCKED.load(Ordering::SeqCst));
+/// How many numbers a thread gets before its generator is reseeded
+const LOCAL_RESEED_INTERVAL: u32 = 1 << 10;
 
-        // SAFETY: The lock was successfully acquired, so this is the
-        // only point that accesses `RNG_SOURCE`.
-        if let Some(x) = unsafe { mem::take(&mut RNG_SOURCE) }
-        {
-            x
-        }
-        else
-        {
-            let mut rv = Self {
-                entropy: [0; 64],
-                newentropy: vec![],
-                used: 256,
-                fresh: 255,
-            };
+static GENERATION: AtomicU64 = AtomicU64::new(0);
 
-            rv.add_new_entropy();
-            rv.fill();
+thread_local! {
+    static LOCAL_RNG: RefCell<Option<LocalRng>> = const { RefCell::new(None) };
+}
 
-            rv
-        }
-    }
+#[derive(Debug)]
+struct LocalRng
+{
+    rng: Rng,
+    requests: u32,
+    epoch: Epoch,
+}
+
+/// Identifies the copy of the process a state belongs to
+///
+/// After a `fork` the child has a new process id and after restoring
+/// a VM snapshot the user hopefully called [`bump_generation`].  In
+/// both cases there are now two copies of the same state, which have
+/// to be reseeded before they are used again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Epoch
+{
+    pid: u32,
+    generation: u64,
+}
 
-    fn get_u8(&mut self) -> u8
+impl Epoch
+{
+    fn now() -> Self
     {
-        self.add_new_entropy();
-        if self.used + 1 > 64
-        {
-            self.fill();
-        }
-        else
-        {
-            self.handle_fresh();
+        Self {
+            pid: process::id(),
+            generation: GENERATION.load(Ordering::SeqCst),
         }
+    }
+}
 
-        let rv = self.entropy[self.used];
+/// Forces all generators to reseed
+///
+/// Call this after something that could have duplicated the state of
+/// the process, e.g. after restoring a VM snapshot or container
+/// checkpoint.  Every thread's generator and the shared pool are
+/// reseeded before they give out the next number.  A `fork` is
+/// detected automatically.
+///
+/// [`Rng`]s you own aren't affected; use [`Rng::reseed`] for them.
+pub fn bump_generation()
+{
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Runs the startup tests of the entropy source again
+///
+/// They run automatically before the jitter is used for the first
+/// time: the health tests are checked to notice a broken source and
+/// then 1024 fresh samples have to pass them.  Call this if you want
+/// to be sure the source still works, e.g. after resuming a VM.
+///
+/// Afterwards all jitter samples are checked continuously with the
+/// Repetition Count and Adaptive Proportion tests of NIST SP 800-90B.
+/// If they fail the samples are thrown away and the error is returned
+/// to the caller that needed entropy.
+///
+/// # Errors
+/// It returns an error if any of the tests failed.
+pub fn self_test() -> Result<(), RngError>
+{
+    health::self_test(entropy::jitter)
+}
 
-        self.used += 1;
+impl LocalRng
+{
+    fn new() -> Result<Self, RngError>
+    {
+        let epoch = Epoch::now();
+        let rng = match replay::rng(epoch)?
+        {
+            Some(rng) => rng,
+            None => Rng::from_entropy()?,
+        };
 
-        rv
+        Ok(Self {
+            rng,
+            requests: 0,
+            epoch,
+        })
     }
 
-    fn get_u32(&mut self) -> u32
+    /// Reseeds the generator of this thread
+    ///
+    /// In replay mode only a new epoch changes it.
+    fn reseed(&mut self, epoch: Epoch) -> Result<(), RngError>
     {
-        self.add_new_entropy();
-        if self.used + 4 > 64
+        if !replay::enabled()
         {
-            self.fill();
+            self.rng.reseed()?;
         }
-        else
+        else if self.epoch != epoch
         {
-            self.handle_fresh();
+            if let Some(rng) = replay::rng(epoch)?
+            {
+                self.rng = rng;
+            }
         }
+        self.requests = 0;
+        self.epoch = epoch;
 
-        let rv = (u32::from(self.entropy[self.used]) << 24)
-            + (u32::from(self.entropy[self.used + 1]) << 16)
-            + (u32::from(self.entropy[self.used + 2]) << 8)
-            + u32::from(self.entropy[self.used + 3]);
+        Ok(())
+    }
 
-        self.used += 4;
+    /// Runs `f` with the state of this thread, which is seeded first
+    /// if this is the first use in this thread
+    fn with_local<T>(
+        f: impl FnOnce(&mut Self) -> Result<T, RngError>,
+    ) -> Result<T, RngError>
+    {
+        LOCAL_RNG.with(|local| {
+            let mut local = local.borrow_mut();
 
-        rv
+            match &mut *local
+            {
+                Some(local) => f(local),
+                None => f(local.get_or_insert(Self::new()?)),
+            }
+        })
     }
 
-    fn add_entropy(&mut self, mut entropy: Vec<u8>)
+    /// Runs `f` with the generator of this thread
+    ///
+    /// Seeds it first if this is the first use in this thread and
+    /// reseeds it from time to time, so that new entropy in the
+    /// shared pool reaches every thread, and always after a `fork` or
+    /// [`bump_generation`].
+    fn with<T>(
+        f: impl FnOnce(&mut Rng) -> Result<T, RngError>,
+    ) -> Result<T, RngError>
     {
-        self.add_new_entropy();
-        self.newentropy.append(&mut entropy);
-        self.fill();
+        Self::with_local(|local| {
+            let epoch = Epoch::now();
+            if local.requests >= LOCAL_RESEED_INTERVAL || local.epoch != epoch
+            {
+                local.reseed(epoch)?;
+            }
+            local.requests += 1;
+
+            f(&mut local.rng)
+        })
     }
 
-    fn handle_fresh(&mut self)
+    /// Reseeds the generator of this thread now
+    fn reseed_now() -> Result<(), RngError>
     {
-        if self.fresh != 0
-        {
-            self.fill();
-            self.fresh -= 1;
-        }
+        Self::with_local(|local| local.reseed(Epoch::now()))
     }
+}
 
-    fn add_new_entropy(&mut self)
+impl RngSource
+{
+    /// Gets a handle to the random number source
+    ///
+    /// The generator of the current thread is seeded on first use, so
+    /// this is where entropy is gathered for the first time.
+    ///
+    /// # Errors
+    /// It returns an error if not enough entropy could be gathered or it
+    /// failed a health test.
+    pub fn new() -> Result<Self, RngError>
     {
-        // Needed since there is no way known to me to avoid it.
-        #[allow(clippy::cast_possible_truncation)]
-        fn handle_error(
-            val: &mut InnerRngSource,
-        ) -> Result<(), SystemTimeError>
-        {
-            let start = SystemTime::now();
-            val.newentropy.push(
-                (SystemTime::now().duration_since(start)?.as_nanos() % 256)
-                    as u8,
-            );
-            val.newentropy.push(
-                (SystemTime::UNIX_EPOCH.duration_since(start)?.as_nanos()
-                    % 256) as u8,
-            );
+        LocalRng::with(|_| Ok(()))?;
 
-            Ok(())
-        }
-
-        if handle_error(self).is_err()
-        {
-            self.newentropy.push(31);
-            self.newentropy.push(41);
-            self.newentropy.push(59);
-        }
+        Ok(Self)
     }
 
-    fn fill(&mut self)
+    /// Adds own entropy
+    ///
+    /// [`RngSource`] usually uses time measurements for entropy, but
+    /// that approach has limits, so if you have own entropy you can
+    /// add it here and by that improve the quality of random numbers
+    /// returned by `RngSource`s.  Bad entropy (even constant zero or
+    /// attacker-provided one) should not reduce the quality, but even
+    /// without that you should **consider `RngSource` broken!**
+    ///
+    /// Like in Fortuna, every call adds the entropy to the next of 32
+    /// pools, which are used only by later reseeds of the shared
+    /// accumulator, some of them very rarely.  That way an attacker
+    /// who controls some of the entropy can't follow every reseed.
+    /// The generator of the current thread is reseeded immediately
+    /// afterwards; other threads get it with their next reseed.
+    ///
+    /// In replay mode the entropy is ignored.
+    ///
+    /// # Errors
+    /// It returns an error if the source couldn't be (re)seeded.
+    pub fn add_entropy(&mut self, mut entropy: Vec<u8>)
+        -> Result<(), RngError>
     {
-        match sha_2(&self.newentropy, ShaVersion::Sha512)
+        let rv = if replay::enabled()
         {
-            ShaHash::Sha512(x) =>
-            {
-                self.entropy = x;
-                self.used = 0;
-                self.newentropy.clear();
-                for v in x
-                {
-                    self.newentropy.push(v);
-                }
-            }
-            _ => unreachable!(),
+            Ok(())
         }
+        else
+        {
+            pool::add_entropy(&entropy)
+        };
+        wipe(entropy.as_mut_slice());
+        rv?;
+
+        LocalRng::reseed_now()
     }
-}
 
-impl RngSource
-{
-    /// Gets unsecure random numbers
+    /// Gets an unsecure random [`u8`]
     ///
-    /// Uses the available or newly loaded entropy to get a [`u8`].
-    /// This is **not** the **cryptographical** secure type of random!
-    #[must_use]
-    pub fn get_u8(self) -> u8
+    /// The same as [`Random::get_u8`], kept so that callers don't need
+    /// to import [`Random`] for it.
+    ///
+    /// # Errors
+    /// It returns an error if the source couldn't be (re)seeded.
+    pub fn get_u8(&mut self) -> Result<u8, RngError>
     {
-        let _ = self;
-        InnerRngSource::get().get_u8()
+        Random::get_u8(self)
     }
 
-    /// Gets unsecure random numbers
+    /// Gets an unsecure random [`u32`]
     ///
-    /// Uses the available or newly loaded entropy to get a [`u32`].
-    /// This is **not** the **cryptographical** secure type of random!
-    #[must_use]
-    pub fn get_u32(self) -> u32
+    /// The same as [`Random::get_u32`], kept so that callers don't need
+    /// to import [`Random`] for it.
+    ///
+    /// # Errors
+    /// It returns an error if the source couldn't be (re)seeded.
+    pub fn get_u32(&mut self) -> Result<u32, RngError>
     {
-        let _ = self;
-        InnerRngSource::get().get_u32()
+        Random::get_u32(self)
     }
+}
 
-    /// Adds own entropy
+impl Random for RngSource
+{
+    /// Fills `output` with unsecure random bytes
     ///
-    /// [`InnerRngSource`] usually uses time measurements for entropy, but
-    /// that approach has limits, so if you have own entropy you can
-    /// add it here and by that improve the quality of random numbers
-    /// returned by `InnerRngSource`s.  Bad entropy (even constant zero or
-    /// attacker-provided one) should not reduce the quality, but even
-    /// without that you should **consider `InnerRngSource` broken!**
-    pub fn add_entropy(self, entropy: Vec<u8>)
+    /// The whole request is served by the generator of the current
+    /// thread at once.
+    ///
+    /// This is **not** the **cryptographical** secure type of random!
+    ///
+    /// # Errors
+    /// It returns an error if the source couldn't be (re)seeded.
+    fn fill_bytes(&mut self, output: &mut [u8]) -> Result<(), RngError>
     {
-        let _ = self;
-        InnerRngSource::get().add_entropy(entropy)
+        LocalRng::with(|rng| rng.fill_bytes(output))
     }
 }
 
 #[cfg(test)]
 mod tests
 {
-    use super::RngSource;
+    use std::thread;
+
+    use super::{entropy, RngError, RngSource};
 
     #[test]
-    fn rng_test()
+    fn rng_test() -> Result<(), RngError>
     {
-        // We can't check on specific values, only that it doesn't panic
+        // We can't check on specific values, only that it doesn't fail
         for _ in 0..1000
         {
-            let rng = RngSource;
+            let mut rng = RngSource::new()?;
+
+            let _ = rng.get_u8()?;
+            let _ = rng.get_u32()?;
+            let entropy =
+                (0..100).map(|_| rng.get_u8()).collect::<Result<_, _>>()?;
+            rng.add_entropy(entropy)?;
+            let _ = rng.get_u8()?;
+            let _ = rng.get_u32()?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn entropy_test() -> Result<(), RngError>
+    {
+        let mut first = vec![];
+        let mut second = vec![];
+
+        entropy::gather_seed(&mut first)?;
+        entropy::gather_seed(&mut second)?;
+
+        assert_ne!(first, second);
+
+        Ok(())
+    }
+
+    #[test]
+    fn many_requests_test() -> Result<(), RngError>
+    {
+        // A single failing window of the health tests mustn't reach the
+        // caller.
+        for _ in 0..2000
+        {
+            let _ = RngSource::new()?.get_u32()?;
+            entropy::gather(&mut vec![])?;
+        }
+        for _ in 0..50
+        {
+            entropy::gather_seed(&mut vec![])?;
+        }
 
-            let _ = rng.get_u8();
-            let _ = rng.get_u32();
-            rng.add_entropy((0..100).map(|_| rng.get_u8()).collect());
-            let _ = rng.get_u8();
-            let _ = rng.get_u32();
+        Ok(())
+    }
+
+    // All threads have to be started before the first is joined.
+    #[allow(clippy::needless_collect)]
+    #[test]
+    fn threads_test()
+    {
+        let handles = (0..8)
+            .map(|_| {
+                thread::spawn(|| {
+                    let mut rng = RngSource::new()?;
+                    (0..2000).map(|_| rng.get_u32()).collect()
+                })
+            })
+            .collect::<Vec<_>>();
+        let streams = handles
+            .into_iter()
+            .map(|x| x.join().unwrap())
+            .collect::<Result<Vec<Vec<_>>, RngError>>()
+            .unwrap();
+
+        for (i, first) in streams.iter().enumerate()
+        {
+            for second in &streams[i + 1..]
+            {
+                assert_ne!(first, second);
+            }
         }
     }
+
+    #[test]
+    fn generation_test() -> Result<(), RngError>
+    {
+        let mut rng = RngSource::new()?;
+
+        let _ = rng.get_u32()?;
+        super::bump_generation();
+        let _ = rng.get_u32()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn self_test_test() -> Result<(), RngError>
+    {
+        super::self_test()
+    }
 }
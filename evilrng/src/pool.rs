@@ -0,0 +1,416 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The shared entropy accumulator
+//!
+//! It works like Fortuna's: events from every source are distributed
+//! round-robin over 32 pools, and the `n`th reseed uses pool `i` only
+//! if `2^i` divides `n`.  So even if an attacker knows or controls
+//! most of the events, some pool eventually collects enough unknown
+//! entropy before it is used.  The key is hashed with a counter with
+//! SHA-256 to get output and replaced after every request.
+//!
+//! The output isn't used directly, but only to seed the generators of
+//! the threads, so a normal mutex is fast enough.
+
+use std::convert::TryFrom;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use evilsecret::{wipe, SecretKey};
+use evilsha::{sha_2, ShaHash, ShaVersion};
+
+use crate::entropy::{self, Sink, Source};
+use crate::{seed_file, Epoch, RngError};
+
+const POOLS: usize = 32;
+/// How many bytes pool 0 needs before a reseed
+const MIN_POOL_LEN: usize = 64;
+/// Longer pools are condensed to their hash
+const MAX_POOL_LEN: usize = 4096;
+/// No event is longer, longer ones are hashed first
+const MAX_EVENT_LEN: usize = 32;
+/// The minimal time between two reseeds
+const RESEED_DELAY: Duration = Duration::from_millis(100);
+const KEY_LEN: usize = 32;
+
+static POOL: Mutex<Option<Accumulator>> = Mutex::new(None);
+
+fn sha256(data: &[u8]) -> [u8; KEY_LEN]
+{
+    match sha_2(data, ShaVersion::Sha256)
+    {
+        ShaHash::Sha256(x) => x,
+        _ => unreachable!(),
+    }
+}
+
+#[derive(Debug)]
+struct Accumulator
+{
+    key: SecretKey<KEY_LEN>,
+    counter: u128,
+    pools: [Vec<u8>; POOLS],
+    /// The pool the next event of every source goes to
+    next_pool: [u8; 256],
+    reseeds: u64,
+    last_reseed: Instant,
+    epoch: Epoch,
+}
+
+impl Accumulator
+{
+    fn new() -> Result<Self, RngError>
+    {
+        let mut rv = Self {
+            key: SecretKey::new([0; KEY_LEN]),
+            counter: 0,
+            pools: Default::default(),
+            next_pool: [0; 256],
+            reseeds: 0,
+            last_reseed: Instant::now(),
+            epoch: Epoch::now(),
+        };
+        rv.rekey_with_seed()?;
+
+        Ok(rv)
+    }
+
+    /// Mixes a full seed directly into the key
+    ///
+    /// Used at the start, when waiting for the pools isn't possible,
+    /// and after the process was duplicated.
+    fn rekey_with_seed(&mut self) -> Result<(), RngError>
+    {
+        let mut seed = vec![];
+        let rv = entropy::gather_seed(&mut seed);
+        if rv.is_ok()
+        {
+            self.rekey(&seed);
+        }
+        wipe(seed.as_mut_slice());
+
+        rv
+    }
+
+    /// Hashes `seed` into the key
+    fn rekey(&mut self, seed: &[u8])
+    {
+        let mut input = self.key.expose().to_vec();
+        input.extend_from_slice(seed);
+        self.key = SecretKey::from_mut(&mut sha256(&input));
+        self.counter += 1;
+        wipe(input.as_mut_slice());
+    }
+
+    /// Makes this copy of the accumulator differ from all others
+    ///
+    /// A full seed is gathered, which includes the new process id.
+    fn handle_epoch(&mut self) -> Result<(), RngError>
+    {
+        let epoch = Epoch::now();
+
+        if self.epoch != epoch
+        {
+            self.rekey_with_seed()?;
+            self.epoch = epoch;
+        }
+
+        Ok(())
+    }
+
+    /// Reseeds from the pools if pool 0 is full enough
+    fn try_reseed(&mut self)
+    {
+        if self.pools[0].len() < MIN_POOL_LEN
+            || self.last_reseed.elapsed() < RESEED_DELAY
+        {
+            return;
+        }
+
+        self.reseed(false);
+    }
+
+    /// Reseeds from the pools the counter allows, or all if `all` is set
+    fn reseed(&mut self, all: bool)
+    {
+        self.reseeds += 1;
+        self.last_reseed = Instant::now();
+
+        let mut seed = vec![];
+        for (i, pool) in self.pools.iter_mut().enumerate()
+        {
+            if !all && !self.reseeds.is_multiple_of(1 << i)
+            {
+                break;
+            }
+
+            let mut hash = sha256(pool);
+            seed.extend_from_slice(&hash);
+            wipe(&mut hash);
+            wipe(pool.as_mut_slice());
+            pool.clear();
+        }
+
+        self.rekey(&seed);
+        wipe(seed.as_mut_slice());
+    }
+
+    fn block(&mut self) -> [u8; KEY_LEN]
+    {
+        let mut input = [0; KEY_LEN + 16];
+        input[..KEY_LEN].copy_from_slice(self.key.expose());
+        input[KEY_LEN..].copy_from_slice(&self.counter.to_le_bytes());
+        self.counter += 1;
+
+        let rv = sha256(&input);
+        wipe(&mut input);
+
+        rv
+    }
+
+    /// Fills `output` and replaces the key afterwards
+    ///
+    /// That way the output can't be reconstructed even if the state is
+    /// leaked later.
+    fn generate(&mut self, output: &mut [u8])
+    {
+        for chunk in output.chunks_mut(KEY_LEN)
+        {
+            let mut block = self.block();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+            wipe(&mut block);
+        }
+
+        self.key = SecretKey::from_mut(&mut self.block());
+    }
+}
+
+impl Sink for Accumulator
+{
+    fn add(&mut self, source: Source, samples: &[u8])
+    {
+        if samples.len() > MAX_EVENT_LEN
+        {
+            let mut hash = sha256(samples);
+            self.add(source, &hash);
+            wipe(&mut hash);
+            return;
+        }
+
+        let next = &mut self.next_pool[source as usize];
+        let pool = &mut self.pools[usize::from(*next)];
+        *next = u8::try_from((usize::from(*next) + 1) % POOLS)
+            .expect("There are less than 256 pools");
+
+        pool.push(source as u8);
+        pool.push(u8::try_from(samples.len()).expect("Events are short"));
+        pool.extend_from_slice(samples);
+
+        // Pools with a high index are used only very rarely, so they
+        // are condensed instead of growing forever.
+        if pool.len() > MAX_POOL_LEN
+        {
+            let mut hash = sha256(pool);
+            wipe(pool.as_mut_slice());
+            pool.clear();
+            pool.extend_from_slice(&hash);
+            wipe(&mut hash);
+        }
+    }
+}
+
+impl Drop for Accumulator
+{
+    fn drop(&mut self)
+    {
+        for pool in &mut self.pools
+        {
+            wipe(pool.as_mut_slice());
+        }
+    }
+}
+
+fn with_pool<T>(
+    f: impl FnOnce(&mut Accumulator) -> Result<T, RngError>,
+) -> Result<T, RngError>
+{
+    // A panic while the accumulator was locked can at worst have left
+    // it half updated, which doesn't make it less random.
+    let mut pool = POOL.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let pool = match &mut *pool
+    {
+        Some(pool) =>
+        {
+            pool.handle_epoch()?;
+            pool
+        }
+        None => pool.get_or_insert(Accumulator::new()?),
+    };
+
+    f(pool)
+}
+
+/// Fills `seed` from the shared accumulator
+///
+/// A registered [`SeedFile`](crate::SeedFile) is loaded first, if that
+/// didn't happen yet.  Then a bit of fresh entropy is added to the
+/// pools and they are used if it's time for a reseed.
+///
+/// # Errors
+/// It returns an error if not enough entropy could be gathered, it
+/// failed a health test or the seed file couldn't be loaded.
+pub fn seed(seed: &mut [u8]) -> Result<(), RngError>
+{
+    seed_file::load_registered()?;
+
+    fill(seed)
+}
+
+/// Fills `seed` like [`seed`], but without loading the seed file
+///
+/// # Errors
+/// It returns an error if not enough entropy could be gathered or it
+/// failed a health test.
+pub fn fill(seed: &mut [u8]) -> Result<(), RngError>
+{
+    with_pool(|pool| {
+        entropy::gather(pool)?;
+        pool.try_reseed();
+        pool.generate(seed);
+
+        Ok(())
+    })
+}
+
+/// Adds `entropy` as an event of the user to the pools
+///
+/// # Errors
+/// It returns an error if the accumulator wasn't seeded yet and not
+/// enough entropy could be gathered.
+pub fn add_entropy(entropy: &[u8]) -> Result<(), RngError>
+{
+    with_pool(|pool| {
+        pool.add(Source::User, entropy);
+
+        Ok(())
+    })
+}
+
+/// Reseeds from all pools now
+///
+/// Only used after a seed file was added, so that it counts right
+/// away.  Doing it regularly would make the pools useless, since then
+/// the rare ones couldn't collect more entropy than an attacker can
+/// follow.
+///
+/// # Errors
+/// It returns an error if the accumulator wasn't seeded yet and not
+/// enough entropy could be gathered.
+pub fn reseed_all() -> Result<(), RngError>
+{
+    with_pool(|pool| {
+        pool.reseed(true);
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{Accumulator, MAX_POOL_LEN, MIN_POOL_LEN, POOLS};
+    use crate::entropy::{Sink, Source};
+    use crate::RngError;
+
+    #[test]
+    fn round_robin_test() -> Result<(), RngError>
+    {
+        let mut acc = Accumulator::new()?;
+
+        for _ in 0..=POOLS
+        {
+            acc.add(Source::User, &[1; 3]);
+        }
+        // Every source has its own turn, and long events are hashed.
+        acc.add(Source::Clock, &[2; 100]);
+
+        assert_eq!(acc.pools[0][..12], [0, 3, 1, 1, 1, 0, 3, 1, 1, 1, 1, 32]);
+        assert_eq!(acc.pools[0].len(), 12 + 32);
+        assert!(acc.pools[1..].iter().all(|x| x.len() == 5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reseed_test() -> Result<(), RngError>
+    {
+        let mut acc = Accumulator::new()?;
+        acc.last_reseed -= super::RESEED_DELAY;
+
+        for _ in 0..POOLS * MIN_POOL_LEN
+        {
+            acc.add(Source::User, &[0]);
+        }
+        let key = acc.key.expose().to_vec();
+        acc.try_reseed();
+
+        assert_ne!(acc.key.expose().to_vec(), key);
+        assert_eq!(acc.reseeds, 1);
+        assert!(acc.pools[0].is_empty());
+        assert!(!acc.pools[1].is_empty());
+
+        // Too early for the next one.
+        acc.try_reseed();
+        assert_eq!(acc.reseeds, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn condense_test() -> Result<(), RngError>
+    {
+        let mut acc = Accumulator::new()?;
+
+        for _ in 0..POOLS * MAX_POOL_LEN
+        {
+            acc.add(Source::User, &[0; 32]);
+        }
+
+        assert!(acc.pools.iter().all(|x| x.len() <= MAX_POOL_LEN));
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_test() -> Result<(), RngError>
+    {
+        let mut acc = Accumulator::new()?;
+        let mut first = [0; 100];
+        let mut second = [0; 100];
+
+        acc.generate(&mut first);
+        acc.generate(&mut second);
+
+        assert_ne!(first, second);
+        assert_ne!(first[..32], first[32..64]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Random big integers and probable primes
+
+use evilsecret::wipe;
+
+use crate::{BigUint, Random, RngError};
+
+/// Candidates are first divided by all primes below this
+const TRIAL_DIVISION_LIMIT: u32 = 2000;
+/// Miller–Rabin rounds for [`random_prime`]
+///
+/// A composite passes one round with a probability of at most 1/4.
+pub const PRIME_ROUNDS: usize = 64;
+
+/// The primes below [`TRIAL_DIVISION_LIMIT`]
+fn small_primes() -> impl Iterator<Item = u32>
+{
+    (2..TRIAL_DIVISION_LIMIT)
+        .filter(|&x| (2..x).take_while(|y| y * y <= x).all(|y| x % y != 0))
+}
+
+/// Generates a uniformly random number with at most `bits` bits
+///
+/// # Errors
+/// It returns an error if `rng` couldn't be (re)seeded.
+pub fn random_bits<R: Random + ?Sized>(
+    rng: &mut R,
+    bits: usize,
+) -> Result<BigUint, RngError>
+{
+    let mut bytes = vec![0; bits.div_ceil(8)];
+    rng.fill_bytes(&mut bytes)?;
+    if let Some(first) = bytes.first_mut()
+    {
+        *first &= 0xff >> (bits.div_ceil(8) * 8 - bits);
+    }
+
+    let rv = BigUint::from_bytes_be(&bytes);
+    wipe(bytes.as_mut_slice());
+
+    Ok(rv)
+}
+
+/// Generates a uniformly random number below `bound`
+///
+/// Numbers with as many bits as `bound` are drawn until one is small
+/// enough, which takes less than two tries on average.
+///
+/// # Errors
+/// It returns an error if `rng` couldn't be (re)seeded.
+///
+/// # Panics
+/// If `bound` is zero.
+pub fn random_below<R: Random + ?Sized>(
+    rng: &mut R,
+    bound: &BigUint,
+) -> Result<BigUint, RngError>
+{
+    assert!(!bound.is_zero(), "Empty range");
+
+    loop
+    {
+        let rv = random_bits(rng, bound.bits())?;
+        if rv < *bound
+        {
+            return Ok(rv);
+        }
+    }
+}
+
+/// The Miller–Rabin test with `rounds` random bases
+///
+/// `n` has to be odd and at least 5.
+fn miller_rabin<R: Random + ?Sized>(
+    n: &BigUint,
+    rng: &mut R,
+    rounds: usize,
+) -> Result<bool, RngError>
+{
+    let one = BigUint::one();
+    let minus_one = n - &one;
+    // n - 1 = d * 2^s with an odd d
+    let s = (0..minus_one.bits())
+        .find(|&i| minus_one.bit(i))
+        .expect("n is at least 5");
+    let d = &minus_one >> s;
+    let bases = n - BigUint::from(3_u32);
+
+    'rounds: for _ in 0..rounds
+    {
+        let base = random_below(rng, &bases)? + BigUint::from(2_u32);
+        let mut x = base.mod_pow(&d, n);
+        if x == one || x == minus_one
+        {
+            continue;
+        }
+
+        for _ in 1..s
+        {
+            x = x.mod_mul(&x, n);
+            if x == minus_one
+            {
+                continue 'rounds;
+            }
+        }
+
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Tests whether `n` is probably prime
+///
+/// After trial division by the small primes `rounds` rounds of the
+/// Miller–Rabin test with random bases are done, so a composite
+/// number is only taken for a prime with a probability of at most
+/// 4^-`rounds`.  Primes are always recognized.
+///
+/// # Errors
+/// It returns an error if `rng` couldn't be (re)seeded.
+pub fn is_probable_prime<R: Random + ?Sized>(
+    n: &BigUint,
+    rng: &mut R,
+    rounds: usize,
+) -> Result<bool, RngError>
+{
+    if let Some(n) = n.to_u64().filter(|&x| x < TRIAL_DIVISION_LIMIT.into())
+    {
+        return Ok(small_primes().any(|x| u64::from(x) == n));
+    }
+    if small_primes().any(|x| n.rem_u32(x) == 0)
+    {
+        return Ok(false);
+    }
+
+    miller_rabin(n, rng, rounds)
+}
+
+/// Generates a random prime with exactly `bits` bits
+///
+/// The two highest bits are always set, so that the product of two
+/// such primes has exactly twice as many bits, like RSA needs it.
+/// Every candidate is tested with [`PRIME_ROUNDS`] rounds of
+/// Miller–Rabin.
+///
+/// ```
+/// # use evilrng::{is_probable_prime, random_prime, RngError, RngSource};
+/// # fn main() -> Result<(), RngError>
+/// # {
+/// let mut rng = RngSource::new()?;
+/// let prime = random_prime(&mut rng, 128)?;
+///
+/// assert_eq!(prime.bits(), 128);
+/// assert!(is_probable_prime(&prime, &mut rng, 32)?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+/// It returns an error if `rng` couldn't be (re)seeded.
+///
+/// # Panics
+/// If `bits` is less than two.
+pub fn random_prime<R: Random + ?Sized>(
+    rng: &mut R,
+    bits: usize,
+) -> Result<BigUint, RngError>
+{
+    assert!(bits >= 2, "No primes with less than two bits");
+
+    loop
+    {
+        let mut candidate = random_bits(rng, bits)?;
+        candidate.set_bit(bits - 1);
+        candidate.set_bit(bits - 2);
+        candidate.set_bit(0);
+
+        if is_probable_prime(&candidate, rng, PRIME_ROUNDS)?
+        {
+            return Ok(candidate);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::convert::TryFrom;
+
+    use super::{
+        is_probable_prime, miller_rabin, random_below, random_bits,
+        random_prime, small_primes,
+    };
+    use crate::{BigUint, Rng, RngError};
+
+    #[test]
+    fn small_primes_test()
+    {
+        assert_eq!(
+            small_primes().take(6).collect::<Vec<_>>(),
+            [2, 3, 5, 7, 11, 13]
+        );
+        assert_eq!(small_primes().count(), 303);
+    }
+
+    #[test]
+    fn known_primes_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[23; 32]);
+        let mersenne = |x| (BigUint::one() << x) - BigUint::one();
+
+        for n in [2_u64, 3, 1999, 2003, 7919, 1_000_000_007]
+        {
+            assert!(is_probable_prime(&n.into(), &mut rng, 20)?, "{}", n);
+        }
+        for n in [0_u64, 1, 4, 1998, 2001, 1_000_000_007 * 3]
+        {
+            assert!(!is_probable_prime(&n.into(), &mut rng, 20)?, "{}", n);
+        }
+        for exponent in [61, 89, 107, 127, 521]
+        {
+            assert!(is_probable_prime(&mersenne(exponent), &mut rng, 20)?);
+        }
+        assert!(!is_probable_prime(&mersenne(67), &mut rng, 20)?);
+        assert!(!is_probable_prime(&mersenne(257), &mut rng, 20)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pseudoprime_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[24; 32]);
+
+        // Carmichael numbers fool the Fermat test with every base.
+        for n in [
+            561_u64,
+            1105,
+            1729,
+            2465,
+            2821,
+            6601,
+            8911,
+            41041,
+            825_265,
+            321_197_185,
+        ]
+        {
+            assert!(!miller_rabin(&n.into(), &mut rng, 20)?, "{}", n);
+            assert!(!is_probable_prime(&n.into(), &mut rng, 20)?, "{}", n);
+        }
+        // A strong pseudoprime to all bases up to 37, without small
+        // factors.
+        let n = BigUint::from(3_825_123_056_546_413_051_u64);
+        assert!(!is_probable_prime(&n, &mut rng, 20)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn random_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[25; 32]);
+        let bound = BigUint::from(1000_u64);
+        let mut seen = [false; 1000];
+
+        for _ in 0..20000
+        {
+            let x = random_below(&mut rng, &bound)?;
+            seen[usize::try_from(x.to_u64().unwrap()).unwrap()] = true;
+        }
+        assert!(seen.iter().all(|&x| x));
+
+        assert!(random_bits(&mut rng, 0)?.is_zero());
+        assert!(random_bits(&mut rng, 77)?.bits() <= 77);
+
+        for bits in [2, 3, 16, 64, 256]
+        {
+            let prime = random_prime(&mut rng, bits)?;
+            assert_eq!(prime.bits(), bits);
+            assert!(prime.bit(bits - 2));
+        }
+
+        Ok(())
+    }
+}
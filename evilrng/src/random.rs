@@ -0,0 +1,761 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Everything that can be built on top of random bytes
+
+use std::convert::TryFrom;
+use std::f64::consts::PI;
+use std::ops::{Range, RangeInclusive};
+
+use crate::RngError;
+
+macro_rules! get_uint {
+    ($(#[$doc:meta] $name:ident: $type:ty),*) => {
+        $(
+            #[$doc]
+            ///
+            /// This is **not** the **cryptographical** secure type of
+            /// random!
+            ///
+            /// # Errors
+            /// It returns an error if the generator couldn't be
+            /// (re)seeded.
+            fn $name(&mut self) -> Result<$type, RngError>
+            {
+                let mut rv = [0; std::mem::size_of::<$type>()];
+                self.fill_bytes(&mut rv)?;
+
+                Ok(<$type>::from_be_bytes(rv))
+            }
+        )*
+    };
+}
+
+/// A source of unsecure random numbers
+///
+/// Only [`Random::fill_bytes`] has to be implemented, everything else
+/// is built on it.  Implemented by both [`RngSource`](crate::RngSource)
+/// and [`Rng`](crate::Rng).
+pub trait Random
+{
+    /// Fills `output` with unsecure random bytes
+    ///
+    /// This is **not** the **cryptographical** secure type of random!
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    fn fill_bytes(&mut self, output: &mut [u8]) -> Result<(), RngError>;
+
+    get_uint!(
+        /// Gets an unsecure random [`u8`]
+        get_u8: u8,
+        /// Gets an unsecure random [`u16`]
+        get_u16: u16,
+        /// Gets an unsecure random [`u32`]
+        get_u32: u32,
+        /// Gets an unsecure random [`u64`]
+        get_u64: u64,
+        /// Gets an unsecure random [`u128`]
+        get_u128: u128
+    );
+
+    /// Gets a number uniformly distributed in `range`
+    ///
+    /// Works with `a..b` and `a..=b` for all integer types.  Numbers
+    /// that would make some results more likely (as a simple `%` does)
+    /// are rejected and drawn again.
+    ///
+    /// ```
+    /// # use evilrng::{Random, RngError, RngSource};
+    /// # fn main() -> Result<(), RngError>
+    /// # {
+    /// let die = RngSource::new()?.gen_range(1..=6_u8)?;
+    /// assert!((1..=6).contains(&die));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    ///
+    /// # Panics
+    /// If `range` is empty.
+    fn gen_range<R: SampleRange>(
+        &mut self,
+        range: R,
+    ) -> Result<R::Output, RngError>
+    where
+        Self: Sized,
+    {
+        range.sample(self)
+    }
+
+    /// Gets `true` with a probability of `p`
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    ///
+    /// # Panics
+    /// If `p` isn't between 0 and 1.
+    // `p` is scaled to the range of `u64` so that all bits are used;
+    // the rounding is much smaller than the resolution of a `f64`.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn gen_bool(&mut self, p: f64) -> Result<bool, RngError>
+    {
+        assert!((0.0..=1.0).contains(&p), "p isn't a probability");
+
+        let x = self.get_u64()?;
+
+        if p >= 1.0
+        {
+            Ok(true)
+        }
+        else
+        {
+            Ok(x < (p * (u64::MAX as f64 + 1.0)) as u64)
+        }
+    }
+
+    /// Gets a [`f64`] uniformly distributed in [0, 1)
+    ///
+    /// All 2^53 possible results are equally likely.
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    // 53 bits always fit exactly.
+    #[allow(clippy::cast_precision_loss)]
+    fn gen_f64(&mut self) -> Result<f64, RngError>
+    {
+        Ok((self.get_u64()? >> 11) as f64 / (1_u64 << 53) as f64)
+    }
+
+    /// Gets a [`f32`] uniformly distributed in [0, 1)
+    ///
+    /// All 2^24 possible results are equally likely.
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    // 24 bits always fit exactly.
+    #[allow(clippy::cast_precision_loss)]
+    fn gen_f32(&mut self) -> Result<f32, RngError>
+    {
+        Ok((self.get_u32()? >> 8) as f32 / (1_u32 << 24) as f32)
+    }
+
+    /// Gets a normally distributed number
+    ///
+    /// Uses the Box–Muller transform.
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    ///
+    /// # Panics
+    /// If `std_dev` is negative or not finite.
+    fn gen_normal(&mut self, mean: f64, std_dev: f64)
+        -> Result<f64, RngError>
+    {
+        assert!(
+            std_dev >= 0.0 && std_dev.is_finite(),
+            "Invalid standard deviation"
+        );
+
+        // In (0, 1], so that the logarithm is finite.
+        let u1 = 1.0 - self.gen_f64()?;
+        let u2 = self.gen_f64()?;
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+
+        Ok(std_dev.mul_add(z, mean))
+    }
+
+    /// Gets an exponentially distributed number
+    ///
+    /// `lambda` is the rate, so the mean is `1 / lambda`.
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    ///
+    /// # Panics
+    /// If `lambda` isn't positive.
+    fn gen_exp(&mut self, lambda: f64) -> Result<f64, RngError>
+    {
+        assert!(lambda > 0.0, "lambda has to be positive");
+
+        Ok(-(1.0 - self.gen_f64()?).ln() / lambda)
+    }
+
+    /// Gets a Poisson distributed number
+    ///
+    /// `lambda` is the mean.  For small `lambda` Knuth's multiplication
+    /// method is used, for large ones Hörmann's transformed rejection
+    /// (PTRS), which needs only a few numbers for every result.
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    ///
+    /// # Panics
+    /// If `lambda` is negative or not finite.
+    // `k` is non-negative and much smaller than 2^53.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn gen_poisson(&mut self, lambda: f64) -> Result<u64, RngError>
+    {
+        assert!(
+            lambda >= 0.0 && lambda.is_finite(),
+            "lambda has to be non-negative"
+        );
+
+        if lambda < 10.0
+        {
+            let limit = (-lambda).exp();
+            let mut k = 0;
+            let mut p = self.gen_f64()?;
+
+            // Multiplying uniform numbers until the product is below
+            // e^-lambda, compared with `>` so that NaN can't loop.
+            #[allow(clippy::while_float)]
+            while p > limit
+            {
+                k += 1;
+                p *= self.gen_f64()?;
+            }
+
+            return Ok(k);
+        }
+
+        let sqrt = lambda.sqrt();
+        let ln = lambda.ln();
+        let b = 2.53_f64.mul_add(sqrt, 0.931);
+        let a = 0.02483_f64.mul_add(b, -0.059);
+        let inv_alpha = 1.1239 + 1.1328 / (b - 3.4);
+        let v_r = 0.9277 - 3.6224 / (b - 2.0);
+
+        loop
+        {
+            let u = self.gen_f64()? - 0.5;
+            let v = self.gen_f64()?;
+            let us = 0.5 - u.abs();
+            let k = (2.0 * a / us + b).mul_add(u, lambda + 0.43).floor();
+
+            if us >= 0.07 && v <= v_r
+            {
+                return Ok(k as u64);
+            }
+            if k < 0.0 || (us < 0.013 && v > us)
+            {
+                continue;
+            }
+            if (v * inv_alpha / (a / (us * us) + b)).ln()
+                <= k.mul_add(ln, -lambda) - ln_factorial(k)
+            {
+                return Ok(k as u64);
+            }
+        }
+    }
+
+    /// Shuffles `slice`
+    ///
+    /// Uses the Fisher–Yates shuffle, so all orders are equally likely.
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.  The
+    /// slice is then shuffled only partly.
+    fn shuffle<T>(&mut self, slice: &mut [T]) -> Result<(), RngError>
+    {
+        for i in (1..slice.len()).rev()
+        {
+            let j = up_to(self, i as u128)?;
+            slice.swap(i, j as usize);
+        }
+
+        Ok(())
+    }
+
+    /// Chooses a random element of `slice`
+    ///
+    /// Returns [`None`] if `slice` is empty.
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    fn choose<'a, T>(
+        &mut self,
+        slice: &'a [T],
+    ) -> Result<Option<&'a T>, RngError>
+    {
+        if slice.is_empty()
+        {
+            return Ok(None);
+        }
+
+        Ok(slice.get(up_to(self, slice.len() as u128 - 1)? as usize))
+    }
+
+    /// Chooses `amount` indices without replacement
+    ///
+    /// Index `i` is chosen with a probability proportional to
+    /// `weights[i]`; the result is in the order they were drawn.  Uses
+    /// the algorithm of Efraimidis and Spirakis: every index gets an
+    /// exponentially distributed key with rate `weights[i]` and the
+    /// smallest keys win.
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    ///
+    /// # Panics
+    /// If a weight is negative or not finite or fewer than `amount`
+    /// weights are positive.
+    fn sample_weighted(
+        &mut self,
+        weights: &[f64],
+        amount: usize,
+    ) -> Result<Vec<usize>, RngError>
+    {
+        assert!(
+            weights.iter().all(|w| *w >= 0.0 && w.is_finite()),
+            "Invalid weight"
+        );
+        assert!(
+            weights.iter().filter(|w| **w > 0.0).count() >= amount,
+            "Not enough positive weights"
+        );
+
+        let mut keys = vec![];
+        for (i, &weight) in weights.iter().enumerate()
+        {
+            if weight > 0.0
+            {
+                keys.push((self.gen_exp(weight)?, i));
+            }
+        }
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Ok(keys.into_iter().take(amount).map(|(_, i)| i).collect())
+    }
+}
+
+/// The natural logarithm of `k!`
+///
+/// Exact for small `k`, otherwise by Stirling's series, which is then
+/// better than the precision of a [`f64`] anyway.
+fn ln_factorial(k: f64) -> f64
+{
+    if k < 10.0
+    {
+        // `k` is a small non-negative integer.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let k = k as u32;
+
+        return (2..=k).map(f64::from).product::<f64>().ln();
+    }
+
+    let x = k + 1.0;
+    let x2 = x * x;
+
+    let series = (1.0 / 12.0 - (1.0 / 360.0 - 1.0 / (1260.0 * x2)) / x2) / x;
+
+    (x - 0.5).mul_add(x.ln(), -x) + (2.0 * PI).sqrt().ln() + series
+}
+
+/// Gets a number uniformly distributed in `0..=max`
+///
+/// # Errors
+/// It returns an error if the generator couldn't be (re)seeded.
+fn up_to<R: Random + ?Sized>(rng: &mut R, max: u128)
+    -> Result<u128, RngError>
+{
+    if let Ok(max) = u64::try_from(max)
+    {
+        if max == u64::MAX
+        {
+            return Ok(u128::from(rng.get_u64()?));
+        }
+
+        // The lowest `rem` numbers are once more often possible than
+        // the others, so the highest `rem` numbers are rejected.
+        let len = max + 1;
+        let rem = (u64::MAX % len + 1) % len;
+        loop
+        {
+            let x = rng.get_u64()?;
+            if x <= u64::MAX - rem
+            {
+                return Ok(u128::from(x % len));
+            }
+        }
+    }
+
+    if max == u128::MAX
+    {
+        return rng.get_u128();
+    }
+
+    let len = max + 1;
+    let rem = (u128::MAX % len + 1) % len;
+    loop
+    {
+        let x = rng.get_u128()?;
+        if x <= u128::MAX - rem
+        {
+            return Ok(x % len);
+        }
+    }
+}
+
+/// Ranges [`Random::gen_range`] can draw numbers from
+pub trait SampleRange
+{
+    /// The type of the numbers in the range
+    type Output;
+
+    /// Draws a number uniformly from `self`
+    ///
+    /// # Errors
+    /// It returns an error if the generator couldn't be (re)seeded.
+    ///
+    /// # Panics
+    /// If `self` is empty.
+    fn sample<R: Random + ?Sized>(
+        self,
+        rng: &mut R,
+    ) -> Result<Self::Output, RngError>;
+}
+
+// All integers fit in an `i128` or `u128` and going through their two's
+// complement the offset from `start` is always right.
+macro_rules! sample_range {
+    ($wide:ty: $($type:ty),*) => {
+        $(
+            #[allow(
+                trivial_numeric_casts,
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                clippy::cast_possible_wrap,
+                clippy::cast_lossless
+            )]
+            impl SampleRange for Range<$type>
+            {
+                type Output = $type;
+
+                fn sample<R: Random + ?Sized>(
+                    self,
+                    rng: &mut R,
+                ) -> Result<$type, RngError>
+                {
+                    assert!(self.start < self.end, "Empty range");
+
+                    let max = (self.end as $wide)
+                        .wrapping_sub(self.start as $wide)
+                        as u128
+                        - 1;
+
+                    Ok((self.start as $wide)
+                        .wrapping_add(up_to(rng, max)? as $wide)
+                        as $type)
+                }
+            }
+
+            #[allow(
+                trivial_numeric_casts,
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                clippy::cast_possible_wrap,
+                clippy::cast_lossless
+            )]
+            impl SampleRange for RangeInclusive<$type>
+            {
+                type Output = $type;
+
+                fn sample<R: Random + ?Sized>(
+                    self,
+                    rng: &mut R,
+                ) -> Result<$type, RngError>
+                {
+                    let (start, end) = self.into_inner();
+                    assert!(start <= end, "Empty range");
+
+                    let max =
+                        (end as $wide).wrapping_sub(start as $wide) as u128;
+
+                    Ok((start as $wide)
+                        .wrapping_add(up_to(rng, max)? as $wide)
+                        as $type)
+                }
+            }
+        )*
+    };
+}
+
+sample_range!(u128: u8, u16, u32, u64, u128, usize);
+sample_range!(i128: i8, i16, i32, i64, i128, isize);
+
+#[cfg(test)]
+mod tests
+{
+    use super::{ln_factorial, Random};
+    use crate::{Rng, RngError};
+
+    /// The mean and variance of `n` samples
+    // The sample counts are small.
+    #[allow(clippy::cast_precision_loss)]
+    fn moments(
+        n: usize,
+        mut f: impl FnMut() -> Result<f64, RngError>,
+    ) -> Result<(f64, f64), RngError>
+    {
+        let samples = (0..n).map(|_| f()).collect::<Result<Vec<_>, _>>()?;
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>()
+            / (n - 1) as f64;
+
+        Ok((mean, var))
+    }
+
+    #[test]
+    fn range_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[4; 32]);
+        let mut counts = [0_u32; 6];
+
+        for _ in 0..6000
+        {
+            counts[rng.gen_range(0..6_usize)?] += 1;
+            assert!((-3..=3).contains(&rng.gen_range(-3..=3_i8)?));
+            assert!((250..=255).contains(&rng.gen_range(250..=u8::MAX)?));
+            assert_eq!(rng.gen_range(7..8_u64)?, 7);
+            assert_eq!(rng.gen_range(i128::MIN..=i128::MIN)?, i128::MIN);
+        }
+        let _ = rng.gen_range(i128::MIN..=i128::MAX)?;
+        let _ = rng.gen_range(0..=u64::MAX)?;
+        let _ = rng.gen_range(1..u128::MAX)?;
+
+        // Each is expected 1000 times with a standard deviation of
+        // about 29, so this fails practically never.
+        assert!(counts.iter().all(|&x| (850..1150).contains(&x)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bool_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[5; 32]);
+
+        let trues = (0..10000)
+            .map(|_| rng.gen_bool(0.3))
+            .filter(|x| matches!(x, Ok(true)))
+            .count();
+        // The standard deviation is about 46.
+        assert!((2700..3300).contains(&trues));
+        assert!(!rng.gen_bool(0.0)?);
+        assert!(rng.gen_bool(1.0)?);
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Empty range")]
+    fn empty_range_test()
+    {
+        let _ = Rng::from_seed(&[6; 32]).gen_range(3..3);
+    }
+
+    #[test]
+    fn float_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[8; 32]);
+
+        let (mean, var) = moments(20000, || rng.gen_f64())?;
+        // The standard error of the mean is about 0.002.
+        assert!((mean - 0.5).abs() < 0.01);
+        assert!((var - 1.0 / 12.0).abs() < 0.004);
+
+        let (mean, _) = moments(20000, || Ok(f64::from(rng.gen_f32()?)))?;
+        assert!((mean - 0.5).abs() < 0.01);
+
+        for _ in 0..10000
+        {
+            assert!((0.0..1.0).contains(&rng.gen_f64()?));
+            assert!((0.0..1.0).contains(&rng.gen_f32()?));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn normal_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[9; 32]);
+
+        let (mean, var) = moments(20000, || rng.gen_normal(3.0, 2.0))?;
+        // The standard error of the mean is about 0.014.
+        assert!((mean - 3.0).abs() < 0.07);
+        assert!((var - 4.0).abs() < 0.2);
+
+        let within = (0..10000)
+            .map(|_| rng.gen_normal(0.0, 1.0))
+            .filter(|x| matches!(x, Ok(x) if x.abs() < 1.0))
+            .count();
+        // About 68.3 % are within one standard deviation.
+        assert!((6650..7000).contains(&within));
+
+        Ok(())
+    }
+
+    #[test]
+    fn exp_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[10; 32]);
+
+        let (mean, var) = moments(20000, || rng.gen_exp(4.0))?;
+        assert!((mean - 0.25).abs() < 0.01);
+        assert!((var - 0.0625).abs() < 0.006);
+
+        Ok(())
+    }
+
+    // The results are far below 2^53.
+    #[allow(clippy::cast_precision_loss)]
+    #[test]
+    fn poisson_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[11; 32]);
+
+        for &lambda in &[0.5, 4.0, 9.9, 10.0, 30.0, 1000.0]
+        {
+            let (mean, var) =
+                moments(10000, || Ok(rng.gen_poisson(lambda)? as f64))?;
+            // Mean and variance are both lambda.
+            assert!(
+                (mean - lambda).abs() < 0.05_f64.mul_add(lambda.sqrt(), 0.01)
+            );
+            assert!((var - lambda).abs() < 0.1 * lambda);
+        }
+        assert_eq!(rng.gen_poisson(0.0)?, 0);
+
+        // P(X = 0) is e^-lambda for every lambda.
+        let zeros = (0..10000)
+            .map(|_| rng.gen_poisson(2.0))
+            .filter(|x| matches!(x, Ok(0)))
+            .count();
+        assert!((1250..1460).contains(&zeros));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ln_factorial_test()
+    {
+        let mut factorial = 1.0_f64;
+        for k in 1..30
+        {
+            factorial *= f64::from(k);
+            let expected = factorial.ln();
+            assert!((ln_factorial(f64::from(k)) - expected).abs() < 1e-10);
+        }
+        assert!(ln_factorial(0.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn shuffle_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[12; 32]);
+        let orders = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        let mut counts = [0_u32; 6];
+
+        for _ in 0..6000
+        {
+            let mut slice = [0, 1, 2];
+            rng.shuffle(&mut slice)?;
+            counts[orders.iter().position(|x| *x == slice).unwrap()] += 1;
+        }
+        assert!(counts.iter().all(|&x| (850..1150).contains(&x)));
+
+        let mut slice = (0..100).collect::<Vec<_>>();
+        rng.shuffle(&mut slice)?;
+        assert_ne!(slice, (0..100).collect::<Vec<_>>());
+        slice.sort_unstable();
+        assert_eq!(slice, (0..100).collect::<Vec<_>>());
+        rng.shuffle::<u8>(&mut [])?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn choose_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[13; 32]);
+        let slice = [0, 1, 2, 3, 4, 5];
+        let mut counts = [0_u32; 6];
+
+        for _ in 0..6000
+        {
+            counts[*rng.choose(&slice)?.unwrap()] += 1;
+        }
+        assert!(counts.iter().all(|&x| (850..1150).contains(&x)));
+        assert_eq!(rng.choose::<u8>(&[])?, None);
+        assert_eq!(rng.choose(&[7])?, Some(&7));
+
+        Ok(())
+    }
+
+    #[test]
+    fn weighted_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[14; 32]);
+        let weights = [1.0, 0.0, 2.0, 3.0, 4.0];
+        let mut counts = [0_u32; 5];
+
+        for _ in 0..10000
+        {
+            counts[rng.sample_weighted(&weights, 1)?[0]] += 1;
+        }
+        // Expected are 1000, 0, 2000, 3000 and 4000.
+        assert_eq!(counts[1], 0);
+        for (&count, &weight) in counts.iter().zip(&weights)
+        {
+            assert!(weight.mul_add(-1000.0, f64::from(count)).abs() < 150.0);
+        }
+
+        let mut all = rng.sample_weighted(&weights, 4)?;
+        all.sort_unstable();
+        assert_eq!(all, [0, 2, 3, 4]);
+        assert!(rng.sample_weighted(&weights, 0)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough positive weights")]
+    fn weighted_panic_test()
+    {
+        let _ = Rng::from_seed(&[15; 32]).sample_weighted(&[1.0, 0.0], 2);
+    }
+}
@@ -0,0 +1,189 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Deterministic replay of `RngSource` for tests
+//!
+//! With the `replay` feature and `EVILRNG_SEED` set, every thread's
+//! generator is seeded only from the value of the variable and the
+//! name of the thread, which for `cargo test` is the name of the test.
+//! The value is printed to stderr once, the first time `RngSource` is
+//! used, so that the output of a failed test shows it.
+//!
+//! Without the variable nothing changes.  Cargo enables a feature for
+//! everyone if a single crate asks for it, so the feature alone mustn't
+//! weaken the keys and nonces of all other crates.
+//!
+//! Nothing is ever reseeded then and [`RngSource::add_entropy`] is
+//! ignored.  [`Rng`]s seeded from entropy aren't affected.  After
+//! [`bump_generation`] the new generation is mixed in, which can still
+//! be replayed.  Only after a `fork` the new process id is mixed in,
+//! since otherwise both copies would give the same numbers.
+//!
+//! [`RngSource::add_entropy`]: crate::RngSource::add_entropy
+//! [`bump_generation`]: crate::bump_generation
+
+#[cfg(feature = "replay")]
+use std::env;
+#[cfg(feature = "replay")]
+use std::process;
+#[cfg(feature = "replay")]
+use std::sync::{Once, OnceLock};
+#[cfg(feature = "replay")]
+use std::thread;
+
+#[cfg(feature = "replay")]
+use evilsha::{sha_2, ShaHash, ShaVersion};
+
+use crate::{Epoch, Rng, RngError};
+
+/// The environment variable the value is read from
+#[cfg(feature = "replay")]
+const VARIABLE: &str = "EVILRNG_SEED";
+
+/// The value and the process id of the first use, if it was set
+#[cfg(feature = "replay")]
+static VALUE: OnceLock<Option<(String, u32)>> = OnceLock::new();
+
+/// Whether the generators are deterministic
+#[cfg(feature = "replay")]
+pub fn enabled() -> bool
+{
+    value().is_some()
+}
+
+/// Whether the generators are deterministic
+#[cfg(not(feature = "replay"))]
+pub const fn enabled() -> bool
+{
+    false
+}
+
+/// Derives the seed of a thread
+#[cfg(feature = "replay")]
+fn derive(value: &str, thread: &str, epoch: Epoch, first_pid: u32)
+    -> [u8; 32]
+{
+    let mut input = b"evilrng replay\0".to_vec();
+    input.extend_from_slice(value.as_bytes());
+    input.push(0);
+    input.extend_from_slice(thread.as_bytes());
+    input.push(0);
+    input.extend_from_slice(&epoch.generation.to_le_bytes());
+    // After a fork both copies have to differ, even if that can't be
+    // replayed.
+    if epoch.pid != first_pid
+    {
+        input.extend_from_slice(&epoch.pid.to_le_bytes());
+    }
+
+    match sha_2(&input, ShaVersion::Sha256)
+    {
+        ShaHash::Sha256(x) => x,
+        _ => unreachable!(),
+    }
+}
+
+/// Reads the value the first time
+#[cfg(feature = "replay")]
+fn value() -> Option<&'static (String, u32)>
+{
+    VALUE
+        .get_or_init(|| env::var(VARIABLE).ok().map(|x| (x, process::id())))
+        .as_ref()
+}
+
+/// Gets the deterministic generator of the current thread
+///
+/// Returns [`None`] if replay mode is off.
+///
+/// # Errors
+/// Never.
+#[cfg(feature = "replay")]
+// Has to be the same as without the feature.
+#[allow(clippy::unnecessary_wraps)]
+pub fn rng(epoch: Epoch) -> Result<Option<Rng>, RngError>
+{
+    // Once is enough and doesn't drown the output of the tests.
+    static ONCE: Once = Once::new();
+
+    let Some((value, first_pid)) = value()
+    else
+    {
+        return Ok(None);
+    };
+    let current = thread::current();
+    let name = current
+        .name()
+        .map_or_else(|| format!("{:?}", current.id()), ToOwned::to_owned);
+
+    ONCE.call_once(|| {
+        eprintln!("evilrng: replaying with {VARIABLE}={value}");
+    });
+
+    Ok(Some(Rng::from_seed(&derive(
+        value, &name, epoch, *first_pid,
+    ))))
+}
+
+/// Gets the deterministic generator of the current thread
+///
+/// Returns [`None`] if replay mode is off.
+///
+/// # Errors
+/// Never, since replay mode is off.
+#[cfg(not(feature = "replay"))]
+// Has to be the same as with the feature.
+#[allow(clippy::unnecessary_wraps)]
+pub const fn rng(_epoch: Epoch) -> Result<Option<Rng>, RngError>
+{
+    Ok(None)
+}
+
+#[cfg(all(test, feature = "replay"))]
+mod tests
+{
+    use super::derive;
+    use crate::Epoch;
+
+    #[test]
+    fn derive_test()
+    {
+        let epoch = Epoch {
+            pid: 1,
+            generation: 0,
+        };
+        let seed = derive("42", "test", epoch, 1);
+
+        assert_eq!(seed, derive("42", "test", epoch, 1));
+        assert_ne!(seed, derive("43", "test", epoch, 1));
+        assert_ne!(seed, derive("42", "other", epoch, 1));
+        assert_ne!(seed, derive("42", "test", epoch, 2));
+        assert_ne!(
+            seed,
+            derive(
+                "42",
+                "test",
+                Epoch {
+                    pid: 1,
+                    generation: 1
+                },
+                1
+            )
+        );
+    }
+}
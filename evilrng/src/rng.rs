@@ -0,0 +1,301 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Owned random number generators
+
+use evilsecret::{wipe, SecretKey};
+use evilsha::ShaVersion;
+
+use crate::{entropy, pool, HmacDrbg, Random, RngError, MAX_REQUEST_LEN};
+
+/// The length of a seed in bytes
+pub const SEED_LEN: usize = 32;
+
+const BUFFER_LEN: usize = 64;
+
+/// An owned unsecure random number generator
+///
+/// Unlike [`RngSource`](crate::RngSource) every `Rng` has its own
+/// state, a `HMAC_DRBG` with SHA-512, so independent streams are
+/// possible.  Seeded with [`Rng::from_seed`] it's deterministic, which
+/// is useful for tests; [`Rng::from_entropy`] seeds it from the shared
+/// entropy pool instead.  A clone continues with exactly the same
+/// numbers, while [`Rng::fork`] gives a new independent stream.
+///
+/// ```
+/// # use evilrng::{Random, Rng, RngError};
+/// # fn main() -> Result<(), RngError>
+/// # {
+/// let mut rng = Rng::from_seed(&[42; 32]);
+/// let mut child = rng.fork()?;
+/// let mut clone = rng.clone();
+///
+/// assert_eq!(rng.get_u32()?, clone.get_u32()?);
+/// assert_ne!(rng.get_u32()?, child.get_u32()?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rng
+{
+    drbg: HmacDrbg,
+    buffer: SecretKey<BUFFER_LEN>,
+    used: usize,
+}
+
+impl Rng
+{
+    const fn from_drbg(drbg: HmacDrbg) -> Self
+    {
+        Self {
+            drbg,
+            buffer: SecretKey::new([0; BUFFER_LEN]),
+            used: BUFFER_LEN,
+        }
+    }
+
+    /// Creates a deterministic generator
+    ///
+    /// The same `seed` always gives the same numbers, so **never use a
+    /// seed that isn't secret** for anything but tests.
+    ///
+    /// # Panics
+    /// Never, since [`SEED_LEN`] is enough for the `HMAC_DRBG`.
+    #[must_use]
+    pub fn from_seed(seed: &[u8; SEED_LEN]) -> Self
+    {
+        Self::from_drbg(
+            HmacDrbg::new(ShaVersion::Sha512, seed, &[], b"evilrng seed")
+                .expect("The seed is long enough"),
+        )
+    }
+
+    /// Creates a generator seeded from the entropy pool
+    ///
+    /// The seed is taken from the shared entropy pool and freshly
+    /// gathered entropy is used as nonce, so that even two generators
+    /// seeded directly after each other differ in more than the pool.
+    ///
+    /// # Errors
+    /// It returns an error if not enough entropy could be gathered or it
+    /// failed a health test.
+    pub fn from_entropy() -> Result<Self, RngError>
+    {
+        let mut seed = [0; SEED_LEN];
+        let mut nonce = vec![];
+        let rv = pool::seed(&mut seed)
+            .and_then(|()| entropy::gather_seed(&mut nonce))
+            .and_then(|()| {
+                HmacDrbg::new(ShaVersion::Sha512, &seed, &nonce, b"evilrng")
+            });
+        wipe(&mut seed);
+        wipe(nonce.as_mut_slice());
+
+        Ok(Self::from_drbg(rv?))
+    }
+
+    /// Moves bytes out of the buffer, which has to have enough
+    fn take_buffered(&mut self, output: &mut [u8])
+    {
+        let buffer = &mut self.buffer.expose_mut()[self.used..];
+
+        output.copy_from_slice(&buffer[..output.len()]);
+        wipe(&mut buffer[..output.len()]);
+        self.used += output.len();
+    }
+
+    /// Reseeds from the entropy pool
+    ///
+    /// Numbers that were already generated but not yet returned are
+    /// thrown away.  Afterwards the generator isn't deterministic
+    /// anymore, even if it was created with [`Rng::from_seed`].
+    ///
+    /// # Errors
+    /// It returns an error if not enough entropy could be gathered or it
+    /// failed a health test.
+    pub fn reseed(&mut self) -> Result<(), RngError>
+    {
+        let mut seed = [0; SEED_LEN];
+        let mut additional_input = vec![];
+        let rv = entropy::gather(&mut additional_input)
+            .and_then(|()| pool::seed(&mut seed))
+            .and_then(|()| self.drbg.reseed(&seed, &additional_input));
+        wipe(&mut seed);
+        wipe(additional_input.as_mut_slice());
+
+        wipe(self.buffer.expose_mut());
+        self.used = BUFFER_LEN;
+
+        rv
+    }
+
+    /// Splits off a child generator
+    ///
+    /// The child is seeded with output of `self`, so it's independent
+    /// from it, but still deterministic if `self` is.
+    ///
+    /// # Errors
+    /// It returns an error if `self` needs a reseed.
+    pub fn fork(&mut self) -> Result<Self, RngError>
+    {
+        let mut seed = [0; SEED_LEN];
+        let rv = self.fill_bytes(&mut seed).and_then(|()| {
+            HmacDrbg::new(ShaVersion::Sha512, &seed, &[], b"evilrng fork")
+        });
+        wipe(&mut seed);
+
+        Ok(Self::from_drbg(rv?))
+    }
+
+    /// Gets an unsecure random [`u8`]
+    ///
+    /// The same as [`Random::get_u8`], kept so that callers don't need
+    /// to import [`Random`] for it.
+    ///
+    /// # Errors
+    /// It returns an error if the generator needs a reseed.
+    pub fn get_u8(&mut self) -> Result<u8, RngError>
+    {
+        Random::get_u8(self)
+    }
+
+    /// Gets an unsecure random [`u32`]
+    ///
+    /// The same as [`Random::get_u32`], kept so that callers don't need
+    /// to import [`Random`] for it.
+    ///
+    /// # Errors
+    /// It returns an error if the generator needs a reseed.
+    pub fn get_u32(&mut self) -> Result<u32, RngError>
+    {
+        Random::get_u32(self)
+    }
+}
+
+impl Random for Rng
+{
+    /// Fills `output` with unsecure random bytes
+    ///
+    /// Whole blocks are generated directly into `output`, only the
+    /// rest goes through the internal buffer.
+    ///
+    /// This is **not** the **cryptographical** secure type of random!
+    ///
+    /// # Errors
+    /// It returns an error if the generator needs a reseed.
+    fn fill_bytes(&mut self, mut output: &mut [u8]) -> Result<(), RngError>
+    {
+        let buffered = output.len().min(BUFFER_LEN - self.used);
+        let (head, tail) = output.split_at_mut(buffered);
+        self.take_buffered(head);
+        output = tail;
+
+        while output.len() >= BUFFER_LEN
+        {
+            let len = (output.len() / BUFFER_LEN * BUFFER_LEN)
+                .min(MAX_REQUEST_LEN / BUFFER_LEN * BUFFER_LEN);
+            let (head, tail) = output.split_at_mut(len);
+            self.drbg.generate(head, &[])?;
+            output = tail;
+        }
+
+        if !output.is_empty()
+        {
+            self.drbg.generate(self.buffer.expose_mut(), &[])?;
+            self.used = 0;
+            self.take_buffered(output);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::Rng;
+    use crate::{Random, RngError};
+
+    fn take(rng: &mut Rng) -> Result<Vec<u32>, RngError>
+    {
+        (0..100).map(|_| rng.get_u32()).collect()
+    }
+
+    #[test]
+    fn seeded_test() -> Result<(), RngError>
+    {
+        let mut first = Rng::from_seed(&[1; 32]);
+        let mut second = Rng::from_seed(&[1; 32]);
+        let mut other = Rng::from_seed(&[2; 32]);
+
+        assert_eq!(first.get_u8()?, second.get_u8()?);
+        assert_eq!(take(&mut first)?, take(&mut second)?);
+        assert_ne!(take(&mut first)?, take(&mut other)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fork_test() -> Result<(), RngError>
+    {
+        let mut parent = Rng::from_seed(&[3; 32]);
+        let mut clone = parent.clone();
+        let mut child = parent.fork()?;
+        let mut clone_child = clone.fork()?;
+
+        assert_eq!(take(&mut parent)?, take(&mut clone)?);
+        assert_eq!(take(&mut child)?, take(&mut clone_child)?);
+        assert_ne!(take(&mut parent)?, take(&mut child)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn entropy_test() -> Result<(), RngError>
+    {
+        let mut first = Rng::from_entropy()?;
+        let mut second = Rng::from_entropy()?;
+
+        assert_ne!(take(&mut first)?, take(&mut second)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fill_bytes_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[7; 32]);
+        let mut clone = rng.clone();
+        let mut first = vec![0; 1000];
+        let mut second = vec![0; 1000];
+
+        let mut start = 0;
+        for len in [3, 61, 64, 200, 672]
+        {
+            rng.fill_bytes(&mut first[start..start + len])?;
+            clone.fill_bytes(&mut second[start..start + len])?;
+            start += len;
+        }
+
+        assert_eq!(first, second);
+        assert!(first.chunks(8).all(|x| x != [0; 8]));
+        assert_eq!(rng.get_u32()?, clone.get_u32()?);
+
+        Ok(())
+    }
+}
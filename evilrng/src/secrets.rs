@@ -0,0 +1,367 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Random identifiers, tokens and passwords
+//!
+//! Everything that picks from a set uses [`SampleRange::sample`], like
+//! [`Random::gen_range`], so no choice is more likely than another.
+
+use std::fmt;
+use std::time::SystemTime;
+
+use crate::{Random, RngError, SampleRange};
+
+/// The lowercase ASCII letters
+pub const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+/// The uppercase ASCII letters
+pub const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+/// The ASCII digits
+pub const DIGITS: &str = "0123456789";
+/// Printable ASCII symbols without quotes, backslash and space
+pub const SYMBOLS: &str = "!#$%&()*+,-./:;<=>?@[]^_{|}~";
+
+/// The word list for [`passphrase`], one word per line
+const WORDS: &str = include_str!("words.txt");
+
+const BASE64URL: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A random UUID
+///
+/// Either version 4, which is completely random, or version 7, which
+/// starts with the time in milliseconds, so that newer ones sort after
+/// older ones, e.g. for database keys.  Printed in the usual
+/// lowercase hexadecimal form.
+///
+/// ```
+/// # use evilrng::{RngError, RngSource, Uuid};
+/// # fn main() -> Result<(), RngError>
+/// # {
+/// let uuid = Uuid::new_v4(&mut RngSource::new()?)?;
+///
+/// assert_eq!(uuid.version(), 4);
+/// assert_eq!(uuid.to_string().len(), 36);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Uuid([u8; 16]);
+
+impl Uuid
+{
+    /// Sets the version and the variant of RFC 4122
+    const fn with_version(mut bytes: [u8; 16], version: u8) -> Self
+    {
+        bytes[6] = (bytes[6] & 0x0f) | (version << 4);
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        Self(bytes)
+    }
+
+    /// Generates a random UUID (version 4, RFC 4122)
+    ///
+    /// # Errors
+    /// It returns an error if `rng` couldn't be (re)seeded.
+    pub fn new_v4<R: Random + ?Sized>(rng: &mut R) -> Result<Self, RngError>
+    {
+        let mut bytes = [0; 16];
+        rng.fill_bytes(&mut bytes)?;
+
+        Ok(Self::with_version(bytes, 4))
+    }
+
+    /// Generates a time-ordered UUID (version 7, RFC 9562)
+    ///
+    /// The first 48 bits are the milliseconds since 1970, the rest
+    /// besides version and variant is random.  UUIDs from the same
+    /// millisecond aren't ordered.
+    ///
+    /// # Errors
+    /// It returns an error if `rng` couldn't be (re)seeded.
+    pub fn new_v7<R: Random + ?Sized>(rng: &mut R) -> Result<Self, RngError>
+    {
+        // A clock before 1970 is broken anyway.
+        let millis = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |x| x.as_millis());
+
+        let mut bytes = [0; 16];
+        bytes[..6].copy_from_slice(&millis.to_be_bytes()[10..]);
+        rng.fill_bytes(&mut bytes[6..])?;
+
+        Ok(Self::with_version(bytes, 7))
+    }
+
+    /// The 16 bytes of the UUID
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 16]
+    {
+        &self.0
+    }
+
+    /// The version, 4 or 7
+    #[must_use]
+    pub const fn version(&self) -> u8
+    {
+        self.0[6] >> 4
+    }
+}
+
+impl fmt::Display for Uuid
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        for (i, byte) in self.0.iter().enumerate()
+        {
+            if matches!(i, 4 | 6 | 8 | 10)
+            {
+                write!(fmt, "-")?;
+            }
+            write!(fmt, "{byte:02x}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Encodes with the URL-safe base64 alphabet and without padding
+fn base64url(bytes: &[u8]) -> String
+{
+    let mut rv = String::with_capacity((bytes.len() * 4).div_ceil(3));
+
+    for chunk in bytes.chunks(3)
+    {
+        let mut block = [0; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let block = u32::from_be_bytes([0, block[0], block[1], block[2]]);
+
+        for i in 0..=chunk.len()
+        {
+            let index = (block >> (18 - 6 * i)) & 0x3f;
+            rv.push(char::from(BASE64URL[index as usize]));
+        }
+    }
+
+    rv
+}
+
+/// Generates a URL-safe token with at least `bits` bits of entropy
+///
+/// The token is base64url encoded without padding, so it can be used
+/// in URLs and file names unchanged.  128 bits are plenty for session
+/// ids and the like.
+///
+/// # Errors
+/// It returns an error if `rng` couldn't be (re)seeded.
+pub fn token<R: Random + ?Sized>(
+    rng: &mut R,
+    bits: usize,
+) -> Result<String, RngError>
+{
+    let mut bytes = vec![0; bits.div_ceil(8)];
+    rng.fill_bytes(&mut bytes)?;
+
+    Ok(base64url(&bytes))
+}
+
+/// Generates a password with at least one character of every class
+///
+/// The characters are taken from all `classes` together, e.g.
+/// `&[LOWERCASE, UPPERCASE, DIGITS]`.  Passwords missing a class are
+/// thrown away and generated again, so every password that fulfils
+/// the rules is equally likely.
+///
+/// ```
+/// # use evilrng::{password, RngError, RngSource, DIGITS, LOWERCASE};
+/// # fn main() -> Result<(), RngError>
+/// # {
+/// let mut rng = RngSource::new()?;
+/// let password = password(&mut rng, 12, &[LOWERCASE, DIGITS])?;
+///
+/// assert_eq!(password.len(), 12);
+/// assert!(password.chars().any(|x| x.is_ascii_digit()));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+/// It returns an error if `rng` couldn't be (re)seeded.
+///
+/// # Panics
+/// If there are no classes, one of them is empty or `len` is too short
+/// to contain all of them.
+pub fn password<R: Random + ?Sized>(
+    rng: &mut R,
+    len: usize,
+    classes: &[&str],
+) -> Result<String, RngError>
+{
+    assert!(
+        !classes.is_empty() && classes.iter().all(|x| !x.is_empty()),
+        "Empty character class"
+    );
+    assert!(len >= classes.len(), "Password too short for all classes");
+
+    let mut alphabet =
+        classes.iter().flat_map(|x| x.chars()).collect::<Vec<_>>();
+    alphabet.sort_unstable();
+    alphabet.dedup();
+
+    loop
+    {
+        let rv = (0..len)
+            .map(|_| Ok(alphabet[(0..alphabet.len()).sample(rng)?]))
+            .collect::<Result<String, RngError>>()?;
+
+        if classes
+            .iter()
+            .all(|class| rv.chars().any(|x| class.contains(x)))
+        {
+            return Ok(rv);
+        }
+    }
+}
+
+/// Generates a diceware passphrase of `words` words
+///
+/// The words come from a bundled list of 2048 common English words,
+/// so every word adds 11 bits of entropy; six words give 66 bits,
+/// about as much as 11 random letters and digits.
+///
+/// # Errors
+/// It returns an error if `rng` couldn't be (re)seeded.
+pub fn passphrase<R: Random + ?Sized>(
+    rng: &mut R,
+    words: usize,
+    separator: &str,
+) -> Result<String, RngError>
+{
+    let list = WORDS.lines().collect::<Vec<_>>();
+
+    Ok((0..words)
+        .map(|_| Ok(list[(0..list.len()).sample(rng)?]))
+        .collect::<Result<Vec<_>, RngError>>()?
+        .join(separator))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::collections::HashSet;
+
+    use super::{
+        base64url, passphrase, password, token, Uuid, DIGITS, LOWERCASE,
+        SYMBOLS, UPPERCASE, WORDS,
+    };
+    use crate::{Rng, RngError};
+
+    #[test]
+    fn uuid_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[16; 32]);
+
+        let uuid = Uuid::new_v4(&mut rng)?;
+        let text = uuid.to_string();
+        assert_eq!(uuid.version(), 4);
+        assert_eq!(text.len(), 36);
+        assert_eq!(&text[14..15], "4");
+        assert!("89ab".contains(&text[19..20]));
+        assert_eq!(
+            text.match_indices('-').map(|x| x.0).collect::<Vec<_>>(),
+            [8, 13, 18, 23]
+        );
+        assert_ne!(uuid, Uuid::new_v4(&mut rng)?);
+
+        let first = Uuid::new_v7(&mut rng)?;
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let second = Uuid::new_v7(&mut rng)?;
+        assert_eq!(first.version(), 7);
+        assert_eq!(first.as_bytes()[8] & 0xc0, 0x80);
+        assert!(first < second);
+
+        Ok(())
+    }
+
+    #[test]
+    fn base64url_test()
+    {
+        // From RFC 4648, without the padding.
+        assert_eq!(base64url(b""), "");
+        assert_eq!(base64url(b"f"), "Zg");
+        assert_eq!(base64url(b"fo"), "Zm8");
+        assert_eq!(base64url(b"foo"), "Zm9v");
+        assert_eq!(base64url(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64url(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn token_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[17; 32]);
+
+        assert_eq!(token(&mut rng, 128)?.len(), 22);
+        assert_eq!(token(&mut rng, 129)?.len(), 23);
+        assert_ne!(token(&mut rng, 64)?, token(&mut rng, 64)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn password_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[18; 32]);
+        let classes = [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS];
+
+        for _ in 0..100
+        {
+            let password = password(&mut rng, 4, &classes)?;
+            assert_eq!(password.chars().count(), 4);
+            for class in &classes
+            {
+                assert!(password.chars().any(|x| class.contains(x)));
+            }
+        }
+        assert_eq!(password(&mut rng, 3, &["a", "a", "a"])?, "aaa");
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Password too short")]
+    fn password_panic_test()
+    {
+        let _ =
+            password(&mut Rng::from_seed(&[19; 32]), 1, &[DIGITS, SYMBOLS]);
+    }
+
+    #[test]
+    fn passphrase_test() -> Result<(), RngError>
+    {
+        let mut rng = Rng::from_seed(&[20; 32]);
+        let words = WORDS.lines().collect::<HashSet<_>>();
+
+        assert_eq!(WORDS.lines().count(), 2048);
+        assert_eq!(words.len(), 2048);
+
+        let phrase = passphrase(&mut rng, 6, "-")?;
+        assert_eq!(phrase.split('-').count(), 6);
+        assert!(phrase.split('-').all(|x| words.contains(x)));
+
+        Ok(())
+    }
+}
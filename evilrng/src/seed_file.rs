@@ -0,0 +1,337 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Seed files that carry entropy over restarts
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use evilsecret::wipe;
+
+use crate::{pool, LocalRng, RngError};
+
+/// How many bytes are saved
+pub const SEED_FILE_LEN: usize = 64;
+/// Longer files are only read up to here
+const MAX_SEED_FILE_LEN: u64 = 4096;
+
+/// The seed file that is loaded at the next use of the pool
+static REGISTERED: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// A seed file like systemd's `random-seed`
+///
+/// Right after a boot the timers have only little entropy, so the seed
+/// saved by the last run is loaded at the first use of the shared
+/// entropy pool after [`SeedFile::register`].  It's mixed in via
+/// [`RngSource::add_entropy`] and then all Fortuna pools are used for
+/// a reseed, so that it counts right away.  The file is immediately
+/// overwritten with fresh output, so that the same seed is never used
+/// twice, even after a crash.  A new seed is saved again when the
+/// `SeedFile` is dropped, which should be at the clean shutdown of
+/// your programme.
+///
+/// The file is always replaced atomically by writing a temporary file
+/// next to it and renaming that, and (on unix) only its owner may read
+/// it.  Anyone who can read the seed file can predict everything
+/// generated shortly after the next start, so keep it somewhere
+/// private.
+///
+/// [`RngSource::add_entropy`]: crate::RngSource::add_entropy
+///
+/// ```no_run
+/// # use evilrng::{Random, RngError, RngSource, SeedFile};
+/// # fn main() -> Result<(), RngError>
+/// # {
+/// let seed_file = SeedFile::register("/var/lib/myprogramme/random-seed");
+/// // Loads the seed file first.
+/// let number = RngSource::new()?.get_u64()?;
+/// // Saves the next seed.
+/// drop(seed_file);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SeedFile
+{
+    path: PathBuf,
+}
+
+impl SeedFile
+{
+    /// Registers the seed file at `path`
+    ///
+    /// It's loaded and replaced at the next use of the entropy pool,
+    /// which is usually the first one, so call this first thing in
+    /// `main`.  A missing file is no error, e.g. on the first start,
+    /// it's created then.  If the loading fails, that use returns the
+    /// error and the file is removed, since its seed might already
+    /// have been used.
+    ///
+    /// Only one seed file can be registered; a later one replaces it
+    /// if it wasn't loaded yet.
+    pub fn register(path: impl AsRef<Path>) -> Self
+    {
+        let path = path.as_ref().to_owned();
+        *REGISTERED.lock().unwrap_or_else(PoisonError::into_inner) =
+            Some(path.clone());
+
+        Self { path }
+    }
+
+    /// Registers the seed file at `path` and loads it now
+    ///
+    /// That's [`SeedFile::register`] for when you want to see the
+    /// errors right away.  The generator of the current thread is
+    /// reseeded immediately; other threads get the seed with their
+    /// next reseed.
+    ///
+    /// # Errors
+    /// It returns an error if the file couldn't be read or written or
+    /// not enough entropy could be gathered.  The file is removed then.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RngError>
+    {
+        let rv = Self::register(path);
+        load_registered()?;
+        LocalRng::reseed_now()?;
+
+        Ok(rv)
+    }
+
+    /// Saves a new seed now
+    ///
+    /// It's done automatically on drop, but there errors can only be
+    /// ignored.  It can also be called regularly, so that a crash
+    /// doesn't leave an old seed.
+    ///
+    /// # Errors
+    /// It returns an error if the file couldn't be written or the
+    /// entropy pool couldn't be (re)seeded.
+    pub fn save(&self) -> Result<(), RngError>
+    {
+        save(&self.path)
+    }
+}
+
+impl Drop for SeedFile
+{
+    fn drop(&mut self)
+    {
+        // Nothing else can be done here.
+        let _ = self.save();
+    }
+}
+
+/// Loads the registered seed file, if it wasn't loaded yet
+///
+/// The pool calls this before it hands out anything.  The lock is held
+/// while loading, so that other threads wait for the seed.
+///
+/// # Errors
+/// It returns an error if the file couldn't be read or written or not
+/// enough entropy could be gathered.  The file is removed then.
+// The lock is held on purpose until the seed is loaded.
+#[allow(clippy::significant_drop_tightening)]
+pub fn load_registered() -> Result<(), RngError>
+{
+    let mut registered =
+        REGISTERED.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(path) = registered.take()
+    else
+    {
+        return Ok(());
+    };
+
+    let mut seed = vec![];
+    let read = match File::open(&path)
+    {
+        Ok(file) => file.take(MAX_SEED_FILE_LEN).read_to_end(&mut seed),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err),
+    };
+    let mixed = read
+        .map_err(RngError::from)
+        .and_then(|_| pool::add_entropy(&seed))
+        .and_then(|()| pool::reseed_all());
+    wipe(seed.as_mut_slice());
+    if let Err(err) = mixed.and_then(|()| save(&path))
+    {
+        // Better no seed at the next start than the same one again
+        let _ = fs::remove_file(&path);
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// Saves a new seed to `path`
+///
+/// It can't use [`pool::seed`], since that would load the seed file.
+fn save(path: &Path) -> Result<(), RngError>
+{
+    let mut seed = [0; SEED_FILE_LEN];
+    let rv = pool::fill(&mut seed)
+        .and_then(|()| replace(path, &seed).map_err(RngError::from));
+    wipe(&mut seed);
+
+    rv
+}
+
+/// Atomically replaces the file with one containing `seed`
+fn replace(path: &Path, seed: &[u8]) -> io::Result<()>
+{
+    let mut name = path
+        .file_name()
+        .map_or_else(OsString::new, ToOwned::to_owned);
+    name.push(".new");
+    let temp = path.with_file_name(name);
+
+    // A leftover from a crash would keep its old permissions.
+    match fs::remove_file(&temp)
+    {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => (),
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(&temp)?;
+    file.write_all(seed)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temp, path)?;
+
+    // Makes the rename itself durable.
+    #[cfg(unix)]
+    if let Some(dir) = path.parent()
+    {
+        let dir = if dir.as_os_str().is_empty()
+        {
+            Path::new(".")
+        }
+        else
+        {
+            dir
+        };
+        File::open(dir)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::sync::{Mutex, PoisonError};
+
+    use super::{SeedFile, SEED_FILE_LEN};
+    use crate::{Rng, RngError};
+
+    /// Only one seed file can be registered at a time.
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn seed_file_test() -> Result<(), RngError>
+    {
+        let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = env::temp_dir()
+            .join(format!("evilrng-seed-file-test-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("random-seed");
+
+        let seed_file = SeedFile::load(&path)?;
+        let first = fs::read(&path)?;
+        assert_eq!(first.len(), SEED_FILE_LEN);
+        drop(seed_file);
+        let second = fs::read(&path)?;
+        assert_ne!(first, second);
+
+        let seed_file = SeedFile::load(&path)?;
+        assert_ne!(fs::read(&path)?, second);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(&path)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        drop(seed_file);
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_save_test() -> Result<(), RngError>
+    {
+        let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = env::temp_dir()
+            .join(format!("evilrng-failed-save-test-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("random-seed");
+        fs::write(&path, [1; SEED_FILE_LEN])?;
+        // The temporary file can't be replaced then.
+        fs::create_dir_all(dir.join("random-seed.new").join("evil"))?;
+
+        let rv = SeedFile::load(&path);
+        let exists = path.exists();
+        fs::remove_dir_all(&dir)?;
+        assert!(rv.is_err());
+        assert!(!exists);
+
+        Ok(())
+    }
+
+    #[test]
+    fn register_test() -> Result<(), RngError>
+    {
+        let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = env::temp_dir()
+            .join(format!("evilrng-register-test-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("random-seed");
+        let old = [1; SEED_FILE_LEN];
+        fs::write(&path, old)?;
+
+        let seed_file = SeedFile::register(&path);
+        Rng::from_entropy()?;
+        let new = fs::read(&path)?;
+        assert_eq!(new.len(), SEED_FILE_LEN);
+        assert_ne!(new, old);
+        // It's only loaded once.
+        Rng::from_entropy()?;
+        assert_eq!(fs::read(&path)?, new);
+
+        drop(seed_file);
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
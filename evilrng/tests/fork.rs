@@ -0,0 +1,88 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Checks that parent and child differ after a `fork`
+//!
+//! It's in its own file, so that no other tests run in parallel
+//! threads while forking.  Re-running the test binary wouldn't do,
+//! since the child has to start with a copy of the parent's state, and
+//! the three functions below aren't worth a dependency on `libc`.
+
+#![cfg(unix)]
+
+use std::ffi::c_int;
+use std::io::{self, Read, Write};
+
+use evilrng::{RngError, RngSource};
+
+// pid_t is an int everywhere we care about.
+extern "C" {
+    fn fork() -> c_int;
+    fn _exit(status: c_int) -> !;
+    fn waitpid(pid: c_int, status: *mut c_int, options: c_int) -> c_int;
+}
+
+fn numbers(rng: &mut RngSource) -> Result<Vec<u8>, RngError>
+{
+    let mut rv = vec![];
+
+    for _ in 0..64
+    {
+        rv.extend_from_slice(&rng.get_u32()?.to_be_bytes());
+    }
+
+    Ok(rv)
+}
+
+#[test]
+#[allow(unsafe_code)]
+fn fork_test()
+{
+    let mut rng = RngSource::new().unwrap();
+    // Makes sure that the generator of this thread is in use.
+    let _ = rng.get_u32().unwrap();
+
+    let (mut reader, mut writer) = io::pipe().unwrap();
+
+    // SAFETY: The child only uses evilrng and the pipe and then exits
+    // without running any destructors.
+    let pid = unsafe { fork() };
+    assert!(pid >= 0);
+
+    if pid == 0
+    {
+        let ok = numbers(&mut rng)
+            .is_ok_and(|numbers| writer.write_all(&numbers).is_ok());
+        // SAFETY: Exiting is always safe.
+        unsafe { _exit(c_int::from(!ok)) };
+    }
+
+    drop(writer);
+    let parent = numbers(&mut rng).unwrap();
+    let mut child = vec![];
+    reader.read_to_end(&mut child).unwrap();
+
+    let mut status = 0;
+    // SAFETY: `pid` is our child and `status` a valid pointer.
+    assert_eq!(unsafe { waitpid(pid, &mut status, 0) }, pid);
+    // Only a normal exit with code 0 gives 0.
+    assert_eq!(status, 0);
+
+    assert_eq!(parent.len(), child.len());
+    assert_ne!(parent, child);
+}
@@ -0,0 +1,59 @@
+/*
+ * evilrng – Unsecure random number source
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Checks the replay mode
+//!
+//! It's only built with `cargo test -p evilrng --features replay`.
+
+use std::env;
+use std::thread;
+
+use evilrng::{RngError, RngSource};
+
+fn numbers(name: &str) -> Vec<u32>
+{
+    let run = || -> Result<Vec<u32>, RngError> {
+        let mut rng = RngSource::new()?;
+        rng.add_entropy(vec![1, 2, 3])?;
+        (0..2000).map(|_| rng.get_u32()).collect()
+    };
+
+    thread::Builder::new()
+        .name(name.to_owned())
+        .spawn(run)
+        .unwrap()
+        .join()
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn replay_test()
+{
+    // Only set if a failed run isn't repeated.  This is the only test
+    // here, so no other thread reads the environment in the meantime.
+    if env::var_os("EVILRNG_SEED").is_none()
+    {
+        env::set_var("EVILRNG_SEED", "42");
+    }
+
+    // Even far more numbers than between two normal reseeds are the
+    // same for the same thread name.
+    assert_eq!(numbers("first"), numbers("first"));
+    assert_ne!(numbers("first"), numbers("second"));
+}
@@ -0,0 +1,203 @@
+/*
+ * evilrsa – Unsecure RSA implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Just enough DER for PKCS#1 keys
+//!
+//! Both key types are a single `SEQUENCE` of non-negative `INTEGER`s
+//! (RFC 8017, A.1), so that's all that's understood here.  Anything
+//! that isn't the one valid DER encoding is rejected.
+
+use std::convert::TryFrom;
+
+use evilrng::BigUint;
+use evilsecret::wipe;
+
+use crate::RsaError;
+
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+
+/// Appends a tag with its length
+fn push_header(tag: u8, len: usize, out: &mut Vec<u8>)
+{
+    let bytes = u64::try_from(len).expect("Too long").to_be_bytes();
+    let skip = bytes.iter().take_while(|&&x| x == 0).count();
+
+    out.push(tag);
+    // The long form starts with the number of bytes of the length.
+    if len >= 0x80
+    {
+        // At most eight, so it fits.
+        #[allow(clippy::cast_possible_truncation)]
+        out.push(0x80 | (bytes.len() - skip) as u8);
+    }
+    out.extend_from_slice(&bytes[skip.min(7)..]);
+}
+
+/// Encodes a `SEQUENCE` of `INTEGER`s
+pub fn encode_integers(integers: &[&BigUint]) -> Vec<u8>
+{
+    let mut content = vec![];
+
+    for integer in integers
+    {
+        let mut bytes = integer.to_bytes_be();
+        // Zero is a single zero byte, and a set highest bit would make
+        // it negative.
+        if bytes.first().is_none_or(|&x| x >= 0x80)
+        {
+            bytes.insert(0, 0);
+        }
+        push_header(INTEGER, bytes.len(), &mut content);
+        content.extend_from_slice(&bytes);
+        wipe(bytes.as_mut_slice());
+    }
+
+    let mut rv = vec![];
+    push_header(SEQUENCE, content.len(), &mut rv);
+    rv.extend_from_slice(&content);
+    wipe(content.as_mut_slice());
+
+    rv
+}
+
+/// Splits off an element with `tag` and returns its content
+fn split_element<'a>(
+    input: &mut &'a [u8],
+    tag: u8,
+) -> Result<&'a [u8], RsaError>
+{
+    let (&first, rest) = input.split_first().ok_or(RsaError::Der)?;
+    let (&len_byte, mut rest) = rest.split_first().ok_or(RsaError::Der)?;
+    if first != tag
+    {
+        return Err(RsaError::Der);
+    }
+
+    let len = if len_byte < 0x80
+    {
+        usize::from(len_byte)
+    }
+    else
+    {
+        let count = usize::from(len_byte & 0x7f);
+        if count == 0 || count > 4 || rest.len() < count || rest[0] == 0
+        {
+            return Err(RsaError::Der);
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0, |len, &x| len << 8 | usize::from(x));
+        rest = &rest[count..];
+        // The short form has to be used if possible.
+        if len < 0x80
+        {
+            return Err(RsaError::Der);
+        }
+        len
+    };
+
+    if rest.len() < len
+    {
+        return Err(RsaError::Der);
+    }
+    let (content, rest) = rest.split_at(len);
+    *input = rest;
+
+    Ok(content)
+}
+
+/// Decodes a `SEQUENCE` of `INTEGER`s, which has to be all of `input`
+pub fn decode_integers(mut input: &[u8]) -> Result<Vec<BigUint>, RsaError>
+{
+    let mut content = split_element(&mut input, SEQUENCE)?;
+    if !input.is_empty()
+    {
+        return Err(RsaError::Der);
+    }
+
+    let mut rv = vec![];
+    while !content.is_empty()
+    {
+        let integer = split_element(&mut content, INTEGER)?;
+        match integer
+        {
+            [] => return Err(RsaError::Der),
+            // Negative
+            [first, ..] if *first >= 0x80 => return Err(RsaError::Der),
+            // Not as short as possible
+            [0, second, ..] if *second < 0x80 => return Err(RsaError::Der),
+            _ => rv.push(BigUint::from_bytes_be(integer)),
+        }
+    }
+
+    Ok(rv)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use evilrng::BigUint;
+
+    use super::{decode_integers, encode_integers};
+
+    #[test]
+    fn roundtrip_test()
+    {
+        let zero = BigUint::zero();
+        let small = BigUint::from(0x7f_u32);
+        let high = BigUint::from(0x80_u32);
+        let long = BigUint::from_bytes_be(&[0xff; 200]);
+
+        let der = encode_integers(&[&zero, &small, &high]);
+        assert_eq!(
+            der,
+            [
+                0x30, 0x0a, 0x02, 0x01, 0x00, 0x02, 0x01, 0x7f, 0x02, 0x02,
+                0x00, 0x80
+            ]
+        );
+        assert_eq!(decode_integers(&der).unwrap(), [zero, small, high]);
+
+        let der = encode_integers(&[&long]);
+        assert_eq!(der[..7], [0x30, 0x81, 0xcc, 0x02, 0x81, 0xc9, 0x00]);
+        assert_eq!(decode_integers(&der).unwrap(), [long]);
+    }
+
+    #[test]
+    fn malformed_test()
+    {
+        for der in [
+            &[][..],
+            &[0x30],
+            &[0x31, 0x00],
+            &[0x30, 0x00, 0x00],
+            &[0x30, 0x02, 0x02, 0x00],
+            &[0x30, 0x03, 0x04, 0x01, 0x01],
+            &[0x30, 0x03, 0x02, 0x01, 0x80],
+            &[0x30, 0x04, 0x02, 0x02, 0x00, 0x7f],
+            &[0x30, 0x81, 0x03, 0x02, 0x01, 0x01],
+            &[0x30, 0x80, 0x02, 0x01, 0x01, 0x00, 0x00],
+            &[0x30, 0x05, 0x02, 0x01, 0x01],
+        ]
+        {
+            assert!(decode_integers(der).is_err(), "{:x?}", der);
+        }
+        assert!(decode_integers(&[0x30, 0x00]).unwrap().is_empty());
+    }
+}
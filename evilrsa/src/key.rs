@@ -0,0 +1,387 @@
+/*
+ * evilrsa – Unsecure RSA implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! RSA keys and the raw RSA operations
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use evilrng::{random_prime, BigUint, Random};
+
+use crate::der::{decode_integers, encode_integers};
+use crate::RsaError;
+
+/// The public exponent of generated keys, 2^16 + 1
+pub const PUBLIC_EXPONENT: u32 = 65537;
+/// Smaller keys can't be generated
+///
+/// Even that is far too small for real use, but handy for tests.
+const MIN_BITS: usize = 512;
+
+/// A RSA public key
+///
+/// Made of the modulus `n` and the public exponent `e`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RsaPublicKey
+{
+    n: BigUint,
+    e: BigUint,
+}
+
+/// A RSA private key
+///
+/// Besides the public key it keeps the primes and the values for the
+/// Chinese remainder theorem, which make decrypting and signing about
+/// four times faster.  Everything is wiped on drop and isn't printed
+/// by [`Debug`](fmt::Debug).
+#[derive(Clone, PartialEq, Eq)]
+pub struct RsaPrivateKey
+{
+    public: RsaPublicKey,
+    d: BigUint,
+    p: BigUint,
+    q: BigUint,
+    dp: BigUint,
+    dq: BigUint,
+    qinv: BigUint,
+}
+
+impl RsaPublicKey
+{
+    /// Creates a public key from modulus and exponent
+    ///
+    /// # Errors
+    /// It returns [`RsaError::InvalidKey`] if `n` or `e` are even or
+    /// `e` isn't between 3 and `n`.
+    pub fn new(n: BigUint, e: BigUint) -> Result<Self, RsaError>
+    {
+        if n.is_even() || e.is_even() || e < BigUint::from(3_u32) || e >= n
+        {
+            return Err(RsaError::InvalidKey);
+        }
+
+        Ok(Self { n, e })
+    }
+
+    /// The modulus
+    #[must_use]
+    pub const fn n(&self) -> &BigUint
+    {
+        &self.n
+    }
+
+    /// The public exponent
+    #[must_use]
+    pub const fn e(&self) -> &BigUint
+    {
+        &self.e
+    }
+
+    /// The length of the modulus in bytes
+    ///
+    /// Cipher texts and signatures always have this length.
+    #[must_use]
+    pub fn size(&self) -> usize
+    {
+        self.n.bits().div_ceil(8)
+    }
+
+    /// Loads a key from its PKCS#1 DER encoding (`RSAPublicKey`)
+    ///
+    /// # Errors
+    /// It returns [`RsaError::Der`] if `der` isn't valid DER of the
+    /// right structure and [`RsaError::InvalidKey`] if the key is
+    /// invalid.
+    pub fn from_pkcs1_der(der: &[u8]) -> Result<Self, RsaError>
+    {
+        match <[BigUint; 2]>::try_from(decode_integers(der)?)
+        {
+            Ok([n, e]) => Self::new(n, e),
+            Err(_) => Err(RsaError::Der),
+        }
+    }
+
+    /// Saves the key in its PKCS#1 DER encoding (`RSAPublicKey`)
+    #[must_use]
+    pub fn to_pkcs1_der(&self) -> Vec<u8>
+    {
+        encode_integers(&[&self.n, &self.e])
+    }
+
+    /// RSAEP, `m^e mod n`
+    pub(crate) fn encrypt_raw(&self, m: &BigUint) -> BigUint
+    {
+        m.mod_pow(&self.e, &self.n)
+    }
+}
+
+impl RsaPrivateKey
+{
+    /// Generates a new key with a modulus of `bits` bits
+    ///
+    /// The primes come from [`random_prime`], so they have 64 rounds
+    /// of Miller–Rabin behind them, and the public exponent is
+    /// [`PUBLIC_EXPONENT`].  2048 bits is the least you'd use if this
+    /// were real crypto; it takes a few seconds.
+    ///
+    /// # Errors
+    /// It returns an error if `rng` couldn't be (re)seeded.
+    ///
+    /// # Panics
+    /// If `bits` is less than 512.
+    pub fn generate<R: Random + ?Sized>(
+        rng: &mut R,
+        bits: usize,
+    ) -> Result<Self, RsaError>
+    {
+        assert!(bits >= MIN_BITS, "Key too small");
+
+        let e = BigUint::from(PUBLIC_EXPONENT);
+        let one = BigUint::one();
+        let mut prime = |bits| -> Result<BigUint, RsaError> {
+            loop
+            {
+                let prime = random_prime(rng, bits)?;
+                // Otherwise there is no private exponent.
+                if &prime % &e != one
+                {
+                    return Ok(prime);
+                }
+            }
+        };
+
+        loop
+        {
+            let p = prime(bits - bits / 2)?;
+            let q = prime(bits / 2)?;
+
+            if p != q
+            {
+                return Self::from_primes(p, q, e);
+            }
+        }
+    }
+
+    /// Creates a private key from its primes and the public exponent
+    ///
+    /// The private exponent is the inverse of `e` modulo
+    /// lcm(`p` - 1, `q` - 1), as in FIPS 186-5.  The primality of `p`
+    /// and `q` isn't checked.
+    ///
+    /// # Errors
+    /// It returns [`RsaError::InvalidKey`] if `p` and `q` are equal or
+    /// there is no private exponent.
+    pub fn from_primes(
+        p: BigUint,
+        q: BigUint,
+        e: BigUint,
+    ) -> Result<Self, RsaError>
+    {
+        let one = BigUint::one();
+        if p == q || p <= one || q <= one
+        {
+            return Err(RsaError::InvalidKey);
+        }
+
+        let public = RsaPublicKey::new(&p * &q, e)?;
+        let p1 = &p - &one;
+        let q1 = &q - &one;
+        let lambda = &p1 * &q1 / p1.gcd(&q1);
+        let d = public.e.mod_inverse(&lambda).ok_or(RsaError::InvalidKey)?;
+        let qinv = q.mod_inverse(&p).ok_or(RsaError::InvalidKey)?;
+
+        Ok(Self {
+            public,
+            dp: &d % &p1,
+            dq: &d % &q1,
+            d,
+            p,
+            q,
+            qinv,
+        })
+    }
+
+    /// The public half of the key
+    #[must_use]
+    pub const fn public_key(&self) -> &RsaPublicKey
+    {
+        &self.public
+    }
+
+    /// Loads a key from its PKCS#1 DER encoding (`RSAPrivateKey`)
+    ///
+    /// Only keys with two primes (version 0) are supported.  All the
+    /// redundant values are checked against each other.
+    ///
+    /// # Errors
+    /// It returns [`RsaError::Der`] if `der` isn't valid DER of the
+    /// right structure and [`RsaError::InvalidKey`] if the key is
+    /// invalid or uses more than two primes.
+    // Named like in RFC 8017.
+    #[allow(clippy::many_single_char_names)]
+    pub fn from_pkcs1_der(der: &[u8]) -> Result<Self, RsaError>
+    {
+        let [version, n, e, d, p, q, dp, dq, qinv] =
+            <[BigUint; 9]>::try_from(decode_integers(der)?)
+                .map_err(|_| RsaError::Der)?;
+        if !version.is_zero()
+        {
+            return Err(RsaError::InvalidKey);
+        }
+
+        let rv = Self::from_primes(p, q, e)?;
+        let one = BigUint::one();
+        let inverts_e = |x: &BigUint| (&d * &rv.public.e % x) == one;
+        // Keys with d modulo φ(n) instead of λ(n) are just as fine.
+        if rv.public.n != n
+            || d >= n
+            || !inverts_e(&(&rv.p - &one))
+            || !inverts_e(&(&rv.q - &one))
+            || rv.qinv != qinv
+            || &d % &(&rv.p - &one) != dp
+            || &d % &(&rv.q - &one) != dq
+        {
+            return Err(RsaError::InvalidKey);
+        }
+
+        Ok(Self { d, dp, dq, ..rv })
+    }
+
+    /// Saves the key in its PKCS#1 DER encoding (`RSAPrivateKey`)
+    ///
+    /// The result is as secret as the key itself.
+    #[must_use]
+    pub fn to_pkcs1_der(&self) -> Vec<u8>
+    {
+        encode_integers(&[
+            &BigUint::zero(),
+            &self.public.n,
+            &self.public.e,
+            &self.d,
+            &self.p,
+            &self.q,
+            &self.dp,
+            &self.dq,
+            &self.qinv,
+        ])
+    }
+
+    /// RSADP, `c^d mod n`, with the Chinese remainder theorem
+    pub(crate) fn decrypt_raw(&self, c: &BigUint) -> BigUint
+    {
+        let m1 = c.mod_pow(&self.dp, &self.p);
+        let m2 = c.mod_pow(&self.dq, &self.q);
+        let h = self.qinv.mod_mul(&m1.mod_sub(&m2, &self.p), &self.p);
+
+        m2 + &self.q * &h
+    }
+}
+
+impl fmt::Debug for RsaPrivateKey
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        fmt.debug_struct("RsaPrivateKey")
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use evilrng::{BigUint, Rng};
+
+    use super::{RsaPrivateKey, RsaPublicKey, PUBLIC_EXPONENT};
+    use crate::RsaError;
+
+    // Made with OpenSSL.
+    const KEY_1024: &[u8] = include_bytes!("testdata/key1024.der");
+    const PUBLIC_1024: &[u8] = include_bytes!("testdata/pub1024.der");
+    const KEY_2048: &[u8] = include_bytes!("testdata/key2048.der");
+    const PUBLIC_2048: &[u8] = include_bytes!("testdata/pub2048.der");
+
+    #[test]
+    fn der_test() -> Result<(), RsaError>
+    {
+        for (der, public) in [(KEY_1024, PUBLIC_1024), (KEY_2048, PUBLIC_2048)]
+        {
+            let key = RsaPrivateKey::from_pkcs1_der(der)?;
+            assert_eq!(key.to_pkcs1_der(), der);
+            assert_eq!(key.public_key().to_pkcs1_der(), public);
+            assert_eq!(
+                &RsaPublicKey::from_pkcs1_der(public)?,
+                key.public_key()
+            );
+            assert_eq!(key.public_key().e(), &BigUint::from(PUBLIC_EXPONENT));
+        }
+        assert_eq!(
+            RsaPrivateKey::from_pkcs1_der(KEY_1024)?.public_key().size(),
+            128
+        );
+
+        // One byte of q changed
+        let mut der = KEY_1024.to_vec();
+        let len = der.len();
+        der[len - 100] ^= 1;
+        assert!(RsaPrivateKey::from_pkcs1_der(&der).is_err());
+        assert!(RsaPrivateKey::from_pkcs1_der(PUBLIC_1024).is_err());
+        assert!(RsaPublicKey::from_pkcs1_der(KEY_1024).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_test() -> Result<(), RsaError>
+    {
+        let mut rng = Rng::from_seed(&[26; 32]);
+        let key = RsaPrivateKey::generate(&mut rng, 512)?;
+        let n = key.public_key().n();
+
+        assert_eq!(n.bits(), 512);
+        assert_eq!(&key.p * &key.q, *n);
+        assert_eq!(RsaPrivateKey::from_pkcs1_der(&key.to_pkcs1_der())?, key);
+
+        let m = BigUint::from(0xdead_beef_u64);
+        let c = key.public_key().encrypt_raw(&m);
+        assert_ne!(c, m);
+        assert_eq!(key.decrypt_raw(&c), m);
+
+        assert!(!format!("{key:?}").contains(&format!("{:#x}", key.d)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_test()
+    {
+        let small = |x: u64| BigUint::from(x);
+
+        assert!(RsaPublicKey::new(small(3233), small(17)).is_ok());
+        assert!(RsaPublicKey::new(small(3234), small(17)).is_err());
+        assert!(RsaPublicKey::new(small(3233), small(16)).is_err());
+        assert!(RsaPublicKey::new(small(3233), small(1)).is_err());
+        assert!(RsaPrivateKey::from_primes(small(61), small(53), small(17))
+            .is_ok());
+        assert!(RsaPrivateKey::from_primes(small(61), small(61), small(17))
+            .is_err());
+        // 3 divides 60
+        assert!(RsaPrivateKey::from_primes(small(61), small(53), small(3))
+            .is_err());
+    }
+}
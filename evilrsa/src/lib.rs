@@ -0,0 +1,205 @@
+/*
+ * evilrsa – Unsecure RSA implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![warn(
+    missing_docs,
+    missing_debug_implementations,
+    missing_copy_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_import_braces,
+    unused_qualifications,
+    rustdoc::missing_crate_level_docs,
+    rust_2018_idioms,
+    clippy::all,
+    clippy::pedantic,
+    clippy::nursery,
+    clippy::cargo
+)]
+#![allow(clippy::suspicious_else_formatting, clippy::match_like_matches_macro)]
+
+//! Unsecure implementation of RSA in pure rust
+//!
+//! evilrsa is a implementation of RSA (RFC 8017) written by an amateur
+//! for the sole purpose that he learns a bit about cryptography.  It
+//! is very probably *very* vulnerable – nothing here runs in constant
+//! time –, so **do not use evilrsa**.  The same applies to all other
+//! evil\* crates.
+//!
+//! Keys are generated with the primes from evilrng and can be saved
+//! and loaded as PKCS#1 DER.  Messages are encrypted with RSAES-OAEP
+//! and signed with RSASSA-PSS, both with a SHA-2 hash from evilsha
+//! and MGF1 with the same hash.
+//!
+//! ```
+//! # use evilrng::RngSource;
+//! # use evilrsa::{RsaError, RsaPrivateKey};
+//! # use evilsha::ShaVersion;
+//! # fn main() -> Result<(), RsaError>
+//! # {
+//! let mut rng = RngSource::new()?;
+//! let key = RsaPrivateKey::generate(&mut rng, 1024)?;
+//! let public = key.public_key();
+//!
+//! let c = public.encrypt_oaep(&mut rng, b"evil", b"", ShaVersion::Sha256)?;
+//! assert_eq!(key.decrypt_oaep(&c, b"", ShaVersion::Sha256)?, b"evil");
+//!
+//! let signature = key.sign_pss(&mut rng, b"evil", ShaVersion::Sha256)?;
+//! public.verify_pss(b"evil", &signature, ShaVersion::Sha256)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::error;
+use std::fmt;
+
+use evilrng::RngError;
+use evilsha::{sha_2, ShaVersion};
+
+pub use key::{RsaPrivateKey, RsaPublicKey, PUBLIC_EXPONENT};
+
+mod der;
+mod key;
+mod oaep;
+mod pss;
+
+/// Errors from evilrsa
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RsaError
+{
+    /// Random numbers couldn't be generated.
+    Rng(RngError),
+    /// The message is too long for the key.
+    MessageTooLong,
+    /// The key is too small for the hash.
+    KeyTooSmall,
+    /// A cipher text couldn't be decrypted.  On purpose it isn't told
+    /// why.
+    Decryption,
+    /// A signature is invalid.
+    Verification,
+    /// A key is malformed or its parts don't fit together.
+    InvalidKey,
+    /// The DER encoding is malformed.
+    Der,
+}
+
+impl fmt::Display for RsaError
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        match self
+        {
+            Self::Rng(err) => write!(fmt, "Random number error: {err}"),
+            Self::MessageTooLong =>
+            {
+                write!(fmt, "The message is too long for the key")
+            }
+            Self::KeyTooSmall => write!(fmt, "The key is too small"),
+            Self::Decryption => write!(fmt, "Decryption error"),
+            Self::Verification => write!(fmt, "Invalid signature"),
+            Self::InvalidKey => write!(fmt, "Invalid key"),
+            Self::Der => write!(fmt, "Malformed DER encoding"),
+        }
+    }
+}
+
+impl error::Error for RsaError
+{
+    fn source(&self) -> Option<&(dyn error::Error + 'static)>
+    {
+        match self
+        {
+            Self::Rng(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<RngError> for RsaError
+{
+    fn from(err: RngError) -> Self
+    {
+        Self::Rng(err)
+    }
+}
+
+/// Hashes `mes` with `version`
+fn hash(mes: &[u8], version: ShaVersion) -> Vec<u8>
+{
+    sha_2(mes, version).as_bytes().to_vec()
+}
+
+/// The mask generation function MGF1 (RFC 8017, B.2.1)
+fn mgf1(seed: &[u8], len: usize, version: ShaVersion) -> Vec<u8>
+{
+    let mut rv = Vec::with_capacity(len + version.output_len());
+    let mut input = seed.to_vec();
+
+    let mut counter = 0_u32;
+
+    while rv.len() < len
+    {
+        input.truncate(seed.len());
+        input.extend_from_slice(&counter.to_be_bytes());
+        rv.extend_from_slice(&hash(&input, version));
+        counter += 1;
+    }
+    rv.truncate(len);
+
+    rv
+}
+
+/// XORs `mask` into `buffer`
+fn xor(buffer: &mut [u8], mask: &[u8])
+{
+    for (x, y) in buffer.iter_mut().zip(mask)
+    {
+        *x ^= y;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use evilsha::ShaVersion;
+
+    use super::mgf1;
+
+    #[test]
+    fn mgf1_test()
+    {
+        // Computed with Python's hashlib.
+        let mask = mgf1(b"foo", 50, ShaVersion::Sha256);
+
+        assert_eq!(
+            mask[..8],
+            [0x3b, 0xda, 0xba, 0x83, 0xcf, 0xf1, 0x33, 0x37]
+        );
+        assert_eq!(
+            mask[32..40],
+            [0x86, 0x78, 0x39, 0x0d, 0x67, 0xe3, 0xc1, 0x2d]
+        );
+        assert_eq!(mask[48..], [0xb5, 0xa3]);
+        assert_eq!(mgf1(b"foo", 7, ShaVersion::Sha256), mask[..7]);
+        assert!(mgf1(b"foo", 0, ShaVersion::Sha512).is_empty());
+    }
+}
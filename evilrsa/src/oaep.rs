@@ -0,0 +1,317 @@
+/*
+ * evilrsa – Unsecure RSA implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! RSAES-OAEP (RFC 8017, 7.1)
+
+use evilrng::{BigUint, Random};
+use evilsecret::{barrier, wipe};
+use evilsha::ShaVersion;
+
+use crate::{hash, mgf1, xor, RsaError, RsaPrivateKey, RsaPublicKey};
+
+impl RsaPublicKey
+{
+    /// Encrypts `message` with RSAES-OAEP
+    ///
+    /// `version` is used both as hash and for MGF1.  The `label` is
+    /// authenticated, but not encrypted; it's usually empty.  At most
+    /// [`size`](Self::size) - 2 * hash length - 2 bytes can be
+    /// encrypted, e.g. 190 with a 2048 bit key and SHA-256.
+    ///
+    /// # Errors
+    /// It returns [`RsaError::MessageTooLong`] or
+    /// [`RsaError::KeyTooSmall`] if `message` doesn't fit and an error
+    /// if `rng` couldn't be (re)seeded.
+    pub fn encrypt_oaep<R: Random + ?Sized>(
+        &self,
+        rng: &mut R,
+        message: &[u8],
+        label: &[u8],
+        version: ShaVersion,
+    ) -> Result<Vec<u8>, RsaError>
+    {
+        let mut seed = vec![0; version.output_len()];
+        rng.fill_bytes(&mut seed)?;
+        let rv = self.encrypt_oaep_with_seed(message, label, &seed, version);
+        wipe(seed.as_mut_slice());
+
+        rv
+    }
+
+    fn encrypt_oaep_with_seed(
+        &self,
+        message: &[u8],
+        label: &[u8],
+        seed: &[u8],
+        version: ShaVersion,
+    ) -> Result<Vec<u8>, RsaError>
+    {
+        let k = self.size();
+        let h_len = version.output_len();
+        if k < 2 * h_len + 2
+        {
+            return Err(RsaError::KeyTooSmall);
+        }
+        if message.len() > k - 2 * h_len - 2
+        {
+            return Err(RsaError::MessageTooLong);
+        }
+
+        // DB = lHash || PS || 0x01 || M
+        let mut db = hash(label, version);
+        db.resize(k - message.len() - h_len - 2, 0);
+        db.push(1);
+        db.extend_from_slice(message);
+        let mask = mgf1(seed, db.len(), version);
+        xor(&mut db, &mask);
+
+        let mut em = seed.to_vec();
+        xor(&mut em, &mgf1(&db, h_len, version));
+        em.insert(0, 0);
+        em.extend_from_slice(&db);
+
+        let m = BigUint::from_bytes_be(&em);
+        wipe(db.as_mut_slice());
+        wipe(em.as_mut_slice());
+
+        Ok(self
+            .encrypt_raw(&m)
+            .to_bytes_be_padded(k)
+            .expect("Smaller than the modulus"))
+    }
+}
+
+impl RsaPrivateKey
+{
+    /// Decrypts `ciphertext` with RSAES-OAEP
+    ///
+    /// `label` and `version` have to be the same as for encrypting.
+    ///
+    /// # Errors
+    /// It returns [`RsaError::Decryption`] if `ciphertext` is invalid;
+    /// all the ways it can be invalid are checked without leaving
+    /// early, since telling them apart would help an attacker.
+    pub fn decrypt_oaep(
+        &self,
+        ciphertext: &[u8],
+        label: &[u8],
+        version: ShaVersion,
+    ) -> Result<Vec<u8>, RsaError>
+    {
+        let public = self.public_key();
+        let k = public.size();
+        let h_len = version.output_len();
+        if ciphertext.len() != k || k < 2 * h_len + 2
+        {
+            return Err(RsaError::Decryption);
+        }
+        let c = BigUint::from_bytes_be(ciphertext);
+        if c >= *public.n()
+        {
+            return Err(RsaError::Decryption);
+        }
+
+        let mut em = self
+            .decrypt_raw(&c)
+            .to_bytes_be_padded(k)
+            .ok_or(RsaError::Decryption)?;
+        let (y, rest) = em.split_at_mut(1);
+        let (seed, db) = rest.split_at_mut(h_len);
+        xor(seed, &mgf1(db, h_len, version));
+        xor(db, &mgf1(seed, db.len(), version));
+
+        let mut invalid = y[0];
+        for (x, y) in db.iter().zip(hash(label, version))
+        {
+            invalid |= x ^ y;
+        }
+        // The first byte after the zeros has to be 0x01.
+        let mut start = 0;
+        let mut found = 0;
+        for (i, &x) in db.iter().enumerate().skip(h_len)
+        {
+            let is_one = u8::from(x == 1);
+            let first = is_one & !found & 1;
+            start |= usize::from(first) * (i + 1);
+            invalid |= u8::from(x > 1) & !found & 1;
+            found |= is_one;
+        }
+        invalid |= !found & 1;
+
+        let rv = if barrier(invalid) == 0
+        {
+            Ok(db[start..].to_vec())
+        }
+        else
+        {
+            Err(RsaError::Decryption)
+        };
+        wipe(em.as_mut_slice());
+
+        rv
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use evilrng::{BigUint, Rng};
+    use evilsha::ShaVersion;
+
+    use crate::{RsaError, RsaPrivateKey};
+
+    // Made with OpenSSL from `evil message` and the label `evil label`.
+    const KEY_1024: &[u8] = include_bytes!("testdata/key1024.der");
+    const CIPHER_1024: &[u8] = include_bytes!("testdata/oaep1024.bin");
+    const KEY_2048: &[u8] = include_bytes!("testdata/key2048.der");
+    const CIPHER_2048: &[u8] = include_bytes!("testdata/oaep2048.bin");
+    // Encoded in Python from `evil` with the seed `[1; 32]`, SHA-256
+    // and no label, checked by decrypting it with OpenSSL
+    const SEED_1024: &[u8] = include_bytes!("testdata/oaep_seed1024.bin");
+    // The integers of oaep-int.txt from the PKCS#1 v2.1 test vectors
+    // (RFC 8017 doesn't include them), encoded with Python.  The OAEP
+    // part of that example needs SHA-1, which evilsha doesn't have, so
+    // only RSAEP and RSADP are checked.
+    const RFC_KEY: &[u8] = include_bytes!("testdata/oaep_int.der");
+
+    fn hex(s: &str) -> BigUint
+    {
+        let bytes: Vec<u8> = (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect();
+
+        BigUint::from_bytes_be(&bytes)
+    }
+
+    #[test]
+    fn openssl_test() -> Result<(), RsaError>
+    {
+        let key = RsaPrivateKey::from_pkcs1_der(KEY_1024)?;
+        assert_eq!(
+            key.decrypt_oaep(CIPHER_1024, b"evil label", ShaVersion::Sha256)?,
+            b"evil message"
+        );
+        assert!(key
+            .decrypt_oaep(CIPHER_1024, b"evil lapel", ShaVersion::Sha256)
+            .is_err());
+
+        let key = RsaPrivateKey::from_pkcs1_der(KEY_2048)?;
+        assert_eq!(
+            key.decrypt_oaep(CIPHER_2048, b"evil label", ShaVersion::Sha512)?,
+            b"evil message"
+        );
+        assert!(key
+            .decrypt_oaep(CIPHER_2048, b"evil label", ShaVersion::Sha384)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn roundtrip_test() -> Result<(), RsaError>
+    {
+        let mut rng = Rng::from_seed(&[27; 32]);
+        let key = RsaPrivateKey::from_pkcs1_der(KEY_1024)?;
+        let public = key.public_key();
+        let version = ShaVersion::Sha256;
+
+        for message in [&b""[..], b"\x00\x01", &[1; 62]]
+        {
+            let c = public.encrypt_oaep(&mut rng, message, b"", version)?;
+            assert_eq!(c.len(), 128);
+            assert_eq!(key.decrypt_oaep(&c, b"", version)?, message);
+
+            let mut tampered = c.clone();
+            tampered[100] ^= 0x10;
+            assert!(key.decrypt_oaep(&tampered, b"", version).is_err());
+            assert!(key.decrypt_oaep(&c[1..], b"", version).is_err());
+        }
+        assert!(matches!(
+            public.encrypt_oaep(&mut rng, &[1; 63], b"", version),
+            Err(RsaError::MessageTooLong)
+        ));
+        assert!(matches!(
+            public.encrypt_oaep(&mut rng, b"", b"", ShaVersion::Sha512),
+            Err(RsaError::KeyTooSmall)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn seed_test() -> Result<(), RsaError>
+    {
+        let key = RsaPrivateKey::from_pkcs1_der(KEY_1024)?;
+        let public = key.public_key();
+        let encrypt = |seed: &[u8]| {
+            public.encrypt_oaep_with_seed(
+                b"evil",
+                b"",
+                seed,
+                ShaVersion::Sha256,
+            )
+        };
+
+        let c = encrypt(&[1; 32])?;
+        assert_eq!(c, SEED_1024);
+        assert_ne!(c, encrypt(&[2; 32])?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rfc_test() -> Result<(), RsaError>
+    {
+        let key = RsaPrivateKey::from_pkcs1_der(RFC_KEY)?;
+        let from_primes = RsaPrivateKey::from_primes(
+            hex("eecfae81b1b9b3c908810b10a1b56001\
+                 99eb9f44aef4fda493b81a9e3d84f632\
+                 124ef0236e5d1e3b7e28fae7aa040a2d\
+                 5b252176459d1f397541ba2a58fb6599"),
+            hex("c97fb1f027f453f6341233eaaad1d935\
+                 3f6c42d08866b1d05a0f2035028b9d86\
+                 9840b41666b42e92ea0da3b43204b5cf\
+                 ce3352524d0416a5a441e700af461503"),
+            BigUint::from(0x11_u64),
+        )?;
+        assert_eq!(from_primes.public_key(), key.public_key());
+
+        let em = hex("00eb7a19ace9e3006350e329504b45e2\
+                      ca82310b26dcd87d5c68f1eea8f55267\
+                      c31b2e8bb4251f84d7e0b2c04626f5af\
+                      f93edcfb25c9c2b3ff8ae10e839a2ddb\
+                      4cdcfe4ff47728b4a1b7c1362baad29a\
+                      b48d2869d5024121435811591be392f9\
+                      82fb3e87d095aeb40448db972f3ac14f\
+                      7bc275195281ce32d2f1b76d4d353e2d");
+        let c = hex("1253e04dc0a5397bb44a7ab87e9bf2a0\
+                     39a33d1e996fc82a94ccd30074c95df7\
+                     63722017069e5268da5d1c0b4f872cf6\
+                     53c11df82314a67968dfeae28def04bb\
+                     6d84b1c31d654a1970e5783bd6eb96a0\
+                     24c2ca2f4a90fe9f2ef5c9c140e5bb48\
+                     da9536ad8700c84fc9130adea74e558d\
+                     51a74ddf85d8b50de96838d6063e0955");
+        assert_eq!(key.public_key().encrypt_raw(&em), c);
+        assert_eq!(key.decrypt_raw(&c), em);
+        assert_eq!(from_primes.decrypt_raw(&c), em);
+
+        Ok(())
+    }
+}
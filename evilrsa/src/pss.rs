@@ -0,0 +1,340 @@
+/*
+ * evilrsa – Unsecure RSA implementation
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! RSASSA-PSS (RFC 8017, 8.1 and 9.1)
+//!
+//! The salt is always as long as the hash, like most implementations
+//! do it by default.
+
+use evilrng::{BigUint, Random};
+use evilsha::ShaVersion;
+
+use crate::{hash, mgf1, xor, RsaError, RsaPrivateKey, RsaPublicKey};
+
+/// M' = 8 zero bytes || mHash || salt, hashed
+fn salted_hash(message: &[u8], salt: &[u8], version: ShaVersion) -> Vec<u8>
+{
+    let mut m_prime = vec![0; 8];
+    m_prime.extend_from_slice(&hash(message, version));
+    m_prime.extend_from_slice(salt);
+
+    hash(&m_prime, version)
+}
+
+/// EMSA-PSS-ENCODE
+fn encode(
+    message: &[u8],
+    salt: &[u8],
+    em_bits: usize,
+    version: ShaVersion,
+) -> Result<Vec<u8>, RsaError>
+{
+    let h_len = version.output_len();
+    let em_len = em_bits.div_ceil(8);
+    if em_len < h_len + salt.len() + 2
+    {
+        return Err(RsaError::KeyTooSmall);
+    }
+
+    let h = salted_hash(message, salt, version);
+    // DB = PS || 0x01 || salt
+    let mut em = vec![0; em_len - salt.len() - h_len - 2];
+    em.push(1);
+    em.extend_from_slice(salt);
+    xor(&mut em, &mgf1(&h, em_len - h_len - 1, version));
+    // The bits above emBits are cleared, so that it's below n.
+    em[0] &= 0xff >> (8 * em_len - em_bits);
+    em.extend_from_slice(&h);
+    em.push(0xbc);
+
+    Ok(em)
+}
+
+/// EMSA-PSS-VERIFY
+fn verify(
+    message: &[u8],
+    em: &[u8],
+    em_bits: usize,
+    version: ShaVersion,
+) -> Result<(), RsaError>
+{
+    let h_len = version.output_len();
+    let s_len = h_len;
+    let em_len = em.len();
+    let top_mask = 0xff >> (8 * em_len - em_bits);
+    if em_len < h_len + s_len + 2
+        || em[em_len - 1] != 0xbc
+        || em[0] & !top_mask != 0
+    {
+        return Err(RsaError::Verification);
+    }
+
+    let (db, h) = em[..em_len - 1].split_at(em_len - h_len - 1);
+    let mut db = db.to_vec();
+    let mask = mgf1(h, db.len(), version);
+    xor(&mut db, &mask);
+    db[0] &= top_mask;
+
+    let (ps, rest) = db.split_at(em_len - h_len - s_len - 2);
+    if ps.iter().any(|&x| x != 0)
+        || rest[0] != 1
+        || salted_hash(message, &rest[1..], version) != h
+    {
+        return Err(RsaError::Verification);
+    }
+
+    Ok(())
+}
+
+impl RsaPrivateKey
+{
+    /// Signs `message` with RSASSA-PSS
+    ///
+    /// `version` is used both as hash and for MGF1, and the salt is as
+    /// long as the hash.  The result is checked with the public key
+    /// before it's returned, since a wrong signature from a faulty
+    /// calculation with the Chinese remainder theorem would give away
+    /// the primes.
+    ///
+    /// # Errors
+    /// It returns [`RsaError::KeyTooSmall`] if the key is too small for
+    /// the hash, [`RsaError::InvalidKey`] if the check failed and an
+    /// error if `rng` couldn't be (re)seeded.
+    pub fn sign_pss<R: Random + ?Sized>(
+        &self,
+        rng: &mut R,
+        message: &[u8],
+        version: ShaVersion,
+    ) -> Result<Vec<u8>, RsaError>
+    {
+        let mut salt = vec![0; version.output_len()];
+        rng.fill_bytes(&mut salt)?;
+
+        self.sign_pss_with_salt(message, &salt, version)
+    }
+
+    fn sign_pss_with_salt(
+        &self,
+        message: &[u8],
+        salt: &[u8],
+        version: ShaVersion,
+    ) -> Result<Vec<u8>, RsaError>
+    {
+        let public = self.public_key();
+        let em = encode(message, salt, public.n().bits() - 1, version)?;
+        let m = BigUint::from_bytes_be(&em);
+        let s = self.decrypt_raw(&m);
+
+        if public.encrypt_raw(&s) != m
+        {
+            return Err(RsaError::InvalidKey);
+        }
+
+        Ok(s.to_bytes_be_padded(public.size())
+            .expect("Smaller than the modulus"))
+    }
+}
+
+impl RsaPublicKey
+{
+    /// Verifies a RSASSA-PSS `signature` of `message`
+    ///
+    /// Only signatures with a salt as long as the hash are accepted.
+    ///
+    /// # Errors
+    /// It returns [`RsaError::Verification`] if the signature is
+    /// invalid.
+    pub fn verify_pss(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        version: ShaVersion,
+    ) -> Result<(), RsaError>
+    {
+        let s = BigUint::from_bytes_be(signature);
+        if signature.len() != self.size() || s >= *self.n()
+        {
+            return Err(RsaError::Verification);
+        }
+
+        let em_bits = self.n().bits() - 1;
+        let em = self
+            .encrypt_raw(&s)
+            .to_bytes_be_padded(em_bits.div_ceil(8))
+            .ok_or(RsaError::Verification)?;
+
+        verify(message, &em, em_bits, version)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use evilrng::{BigUint, Rng};
+    use evilsha::ShaVersion;
+
+    use crate::{RsaError, RsaPrivateKey};
+
+    // Made with OpenSSL from `evil message`.
+    const KEY_1024: &[u8] = include_bytes!("testdata/key1024.der");
+    const SIGNATURE_1024: &[u8] = include_bytes!("testdata/pss1024.bin");
+    const KEY_2048: &[u8] = include_bytes!("testdata/key2048.der");
+    const SIGNATURE_2048: &[u8] = include_bytes!("testdata/pss2048.bin");
+    // Encoded in Python from `evil` with the salt `[3; 32]` and
+    // SHA-256, checked by verifying it with OpenSSL
+    const SALT_2048: &[u8] = include_bytes!("testdata/pss_salt2048.bin");
+    // The integers of pss-int.txt from the PKCS#1 v2.1 test vectors
+    // (RFC 8017 doesn't include them), encoded with Python.  The PSS
+    // part of that example needs SHA-1, which evilsha doesn't have, so
+    // only RSASP1 and RSAVP1 are checked.
+    const RFC_KEY: &[u8] = include_bytes!("testdata/pss_int.der");
+
+    fn hex(s: &str) -> BigUint
+    {
+        let bytes: Vec<u8> = (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect();
+
+        BigUint::from_bytes_be(&bytes)
+    }
+
+    #[test]
+    fn openssl_test() -> Result<(), RsaError>
+    {
+        let public = RsaPrivateKey::from_pkcs1_der(KEY_1024)?
+            .public_key()
+            .clone();
+        public.verify_pss(
+            b"evil message",
+            SIGNATURE_1024,
+            ShaVersion::Sha256,
+        )?;
+        assert!(public
+            .verify_pss(b"evil massage", SIGNATURE_1024, ShaVersion::Sha256)
+            .is_err());
+
+        let public = RsaPrivateKey::from_pkcs1_der(KEY_2048)?
+            .public_key()
+            .clone();
+        public.verify_pss(
+            b"evil message",
+            SIGNATURE_2048,
+            ShaVersion::Sha512,
+        )?;
+        assert!(public
+            .verify_pss(b"evil message", SIGNATURE_2048, ShaVersion::Sha256)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn roundtrip_test() -> Result<(), RsaError>
+    {
+        let mut rng = Rng::from_seed(&[28; 32]);
+        let key = RsaPrivateKey::from_pkcs1_der(KEY_2048)?;
+        let public = key.public_key();
+
+        for version in
+            [ShaVersion::Sha256, ShaVersion::Sha384, ShaVersion::Sha512]
+        {
+            let signature = key.sign_pss(&mut rng, b"evil", version)?;
+            assert_eq!(signature.len(), 256);
+            public.verify_pss(b"evil", &signature, version)?;
+            assert_ne!(signature, key.sign_pss(&mut rng, b"evil", version)?);
+
+            let mut tampered = signature.clone();
+            tampered[0] ^= 1;
+            assert!(public.verify_pss(b"evil", &tampered, version).is_err());
+            assert!(public
+                .verify_pss(b"evil", &signature[1..], version)
+                .is_err());
+        }
+
+        assert_eq!(
+            key.sign_pss_with_salt(b"evil", &[3; 32], ShaVersion::Sha256)?,
+            SALT_2048
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn small_key_test() -> Result<(), RsaError>
+    {
+        // With a modulus of 8k + 1 bits the encoded message is a byte
+        // shorter than the signature, which the other keys don't test.
+        let mut rng = Rng::from_seed(&[29; 32]);
+        let key = RsaPrivateKey::generate(&mut rng, 593)?;
+        let signature = key.sign_pss(&mut rng, b"evil", ShaVersion::Sha256)?;
+        key.public_key().verify_pss(
+            b"evil",
+            &signature,
+            ShaVersion::Sha256,
+        )?;
+
+        let key = RsaPrivateKey::from_pkcs1_der(KEY_1024)?;
+        assert!(matches!(
+            key.sign_pss(&mut rng, b"evil", ShaVersion::Sha512),
+            Err(RsaError::KeyTooSmall)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rfc_test() -> Result<(), RsaError>
+    {
+        let key = RsaPrivateKey::from_pkcs1_der(RFC_KEY)?;
+        let from_primes = RsaPrivateKey::from_primes(
+            hex("d17f655bf27c8b16d35462c905cc04a2\
+                 6f37e2a67fa9c0ce0dced472394a0df7\
+                 43fe7f929e378efdb368eddff453cf00\
+                 7af6d948e0ade757371f8a711e278f6b"),
+            hex("c6d92b6fee7414d1358ce1546fb62987\
+                 530b90bd15e0f14963a5e2635adb6934\
+                 7ec0c01b2ab1763fd8ac1a592fb22757\
+                 463a982425bb97a3a437c5bf86d03f2f"),
+            BigUint::from(0x1_0001_u64),
+        )?;
+        assert_eq!(from_primes.public_key(), key.public_key());
+
+        let em = hex("66e4672e836ad121ba244bed6576b867\
+                      d9a447c28a6e66a5b87dee7fbc7e65af\
+                      5057f86fae8984d9ba7f969ad6fe02a4\
+                      d75f7445fefdd85b6d3a477c28d24ba1\
+                      e3756f792dd1dce8ca94440ecb5279ec\
+                      d3183a311fc896da1cb39311af37ea4a\
+                      75e24bdbfd5c1da0de7cecdf1a896f9d\
+                      8bc816d97cd7a2c43bad546fbe8cfebc");
+        let s = hex("8daa627d3de7595d63056c7ec659e544\
+                     06f10610128baae821c8b2a0f3936d54\
+                     dc3bdce46689f6b7951bb18e84054276\
+                     9718d5715d210d85efbb596192032c42\
+                     be4c29972c856275eb6d5a45f05f5187\
+                     6fc6743deddd28caec9bb30ea99e02c3\
+                     488269604fe497f74ccd7c7fca167189\
+                     7123cbd30def5d54a2b5536ad90a747e");
+        assert_eq!(key.decrypt_raw(&em), s);
+        assert_eq!(from_primes.decrypt_raw(&em), s);
+        assert_eq!(key.public_key().encrypt_raw(&s), em);
+
+        Ok(())
+    }
+}
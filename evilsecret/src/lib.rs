@@ -0,0 +1,340 @@
+/*
+ * evilsecret – Unsecure handling of secret keys
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![warn(
+    missing_docs,
+    missing_debug_implementations,
+    missing_copy_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_import_braces,
+    unused_qualifications,
+    rustdoc::missing_crate_level_docs,
+    rust_2018_idioms,
+    clippy::all,
+    clippy::pedantic,
+    clippy::nursery,
+    clippy::cargo
+)]
+#![allow(
+    clippy::suspicious_else_formatting,
+    clippy::match_like_matches_macro,
+    unstable_features,
+    // Can't be done without, but there is nothing else in here.
+    unsafe_code
+)]
+#![cfg_attr(feature = "nightly", feature(core_intrinsics))]
+#![cfg_attr(feature = "nightly", allow(internal_features))]
+
+//! Unsecure handling of secret keys in pure rust
+//!
+//! evilsecret provides types for secret keys, that are overwritten
+//! with zeros when they are dropped, and is written by an amateur for
+//! the sole purpose that he learns a bit about cryptography.  It is
+//! very probably *very* vulnerable, so **do not use evilsecret**.  The
+//! same applies to all other evil\* crates.
+//!
+//! Wiping and the optimisation barrier work on stable rust by using
+//! volatile memory accesses, which the compiler mustn't remove or
+//! reorder with each other, and compiler fences, so that the
+//! surrounding code isn't moved over them.  With the `nightly`
+//! feature the (probably better) compiler intrinsics are used
+//! instead.  Neither is a real guarantee: copies the compiler made in
+//! registers or on the stack can't be found this way, so this is only
+//! **best effort**.
+
+use std::fmt;
+use std::ptr;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// Types for which all bits zero is a valid value
+///
+/// # Safety
+/// Only implement this for plain data without any padding or invalid
+/// bit patterns.
+pub unsafe trait Zeroable {}
+
+// SAFETY: Integers have no invalid bit patterns.
+unsafe impl Zeroable for u8 {}
+// SAFETY: Integers have no invalid bit patterns.
+unsafe impl Zeroable for u32 {}
+// SAFETY: Integers have no invalid bit patterns.
+unsafe impl Zeroable for u64 {}
+// SAFETY: Integers have no invalid bit patterns.
+unsafe impl Zeroable for u128 {}
+// SAFETY: Arrays of `Zeroable`s have no padding and no invalid bit
+// patterns.
+unsafe impl<T: Zeroable, const N: usize> Zeroable for [T; N] {}
+// SAFETY: Slices of `Zeroable`s have no padding and no invalid bit
+// patterns.
+unsafe impl<T: Zeroable> Zeroable for [T] {}
+
+/// Overwrites `secret` with zeros
+///
+/// The writes are volatile, so they aren't optimised away even if
+/// `secret` is never read again.
+#[cfg(not(feature = "nightly"))]
+pub fn wipe<T: Zeroable + ?Sized>(secret: &mut T)
+{
+    let len = size_of_val(secret);
+    let bytes = ptr::from_mut(secret).cast::<u8>();
+
+    for i in 0..len
+    {
+        // SAFETY: `secret` is a valid and exclusive reference, so every
+        // byte of it may be written and since `T` is `Zeroable`, zero
+        // bytes result in a valid value.
+        unsafe {
+            ptr::write_volatile(bytes.add(i), 0);
+        }
+    }
+
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrites `secret` with zeros
+///
+/// The writes are volatile, so they aren't optimised away even if
+/// `secret` is never read again.
+#[cfg(feature = "nightly")]
+pub fn wipe<T: Zeroable + ?Sized>(secret: &mut T)
+{
+    let len = size_of_val(secret);
+
+    // SAFETY: `secret` is a valid and exclusive reference and since
+    // `T` is `Zeroable`, zero bytes result in a valid value.
+    unsafe {
+        std::intrinsics::volatile_set_memory(
+            ptr::from_mut(secret).cast::<u8>(),
+            0,
+            len,
+        );
+    }
+
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Hides `value` from the optimiser
+///
+/// Returns `value` unchanged, but the compiler can't know that, so it
+/// can't e.g. replace a computation with masks by a branch.
+#[cfg(not(feature = "nightly"))]
+pub fn barrier<T: Zeroable + Copy>(value: T) -> T
+{
+    compiler_fence(Ordering::SeqCst);
+    // SAFETY: `value` is a local variable, so the pointer is valid,
+    // aligned and initialised.
+    let rv = unsafe { ptr::read_volatile(ptr::from_ref(&value)) };
+    compiler_fence(Ordering::SeqCst);
+
+    rv
+}
+
+/// Hides `value` from the optimiser
+///
+/// Returns `value` unchanged, but the compiler can't know that, so it
+/// can't e.g. replace a computation with masks by a branch.
+#[cfg(feature = "nightly")]
+pub fn barrier<T: Zeroable + Copy>(value: T) -> T
+{
+    std::hint::black_box(value)
+}
+
+/// An unsecure secret key
+///
+/// Holds `N` bytes of key material, which are overwritten with zeros
+/// when the key is dropped.  It's deliberately not [`Copy`], so that
+/// no untracked copies are made implicitly, and its [`Debug`]
+/// implementation doesn't show the key.  This is only **best effort**,
+/// so **consider it broken!**
+///
+/// ```
+/// # use evilsecret::SecretKey;
+/// let mut bytes = [42; 32];
+/// let key = SecretKey::from_mut(&mut bytes);
+///
+/// assert_eq!(bytes, [0; 32]);
+/// assert_eq!(key.expose(), &[42; 32]);
+/// assert_eq!(format!("{key:?}"), "SecretKey([REDACTED])");
+/// ```
+#[derive(Clone)]
+pub struct SecretKey<const N: usize>([u8; N]);
+
+impl<const N: usize> SecretKey<N>
+{
+    /// Wraps `key`
+    ///
+    /// `key` is moved, but since arrays are [`Copy`] the value you
+    /// passed still exists; prefer [`SecretKey::from_mut`].
+    #[must_use]
+    pub const fn new(key: [u8; N]) -> Self
+    {
+        Self(key)
+    }
+
+    /// Copies `key` and overwrites the original with zeros
+    #[must_use]
+    pub fn from_mut(key: &mut [u8; N]) -> Self
+    {
+        let rv = Self(*key);
+        wipe(key);
+
+        rv
+    }
+
+    /// Gives access to the key material
+    ///
+    /// Try not to copy it out of the returned reference.
+    #[must_use]
+    pub const fn expose(&self) -> &[u8; N]
+    {
+        &self.0
+    }
+
+    /// Gives mutable access to the key material
+    ///
+    /// Useful to fill a key in place, e.g. directly from a random
+    /// number generator.
+    #[must_use]
+    pub const fn expose_mut(&mut self) -> &mut [u8; N]
+    {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for SecretKey<N>
+{
+    fn from(key: [u8; N]) -> Self
+    {
+        Self::new(key)
+    }
+}
+
+impl<const N: usize> Drop for SecretKey<N>
+{
+    fn drop(&mut self)
+    {
+        wipe(&mut self.0);
+    }
+}
+
+impl<const N: usize> fmt::Debug for SecretKey<N>
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        write!(fmt, "SecretKey([REDACTED])")
+    }
+}
+
+/// An unsecure nonce
+///
+/// Holds a `N` byte nonce (or initialisation vector).  Nonces don't
+/// have to be secret, but since it's still better if they aren't
+/// known in advance, they're handled exactly like [`SecretKey`]s:
+/// they are overwritten with zeros when they're dropped, aren't
+/// [`Copy`] and aren't shown by [`Debug`].
+#[derive(Clone)]
+pub struct Nonce<const N: usize>([u8; N]);
+
+impl<const N: usize> Nonce<N>
+{
+    /// Wraps `nonce`
+    #[must_use]
+    pub const fn new(nonce: [u8; N]) -> Self
+    {
+        Self(nonce)
+    }
+
+    /// Gives access to the nonce
+    #[must_use]
+    pub const fn expose(&self) -> &[u8; N]
+    {
+        &self.0
+    }
+
+    /// Gives mutable access to the nonce
+    #[must_use]
+    pub const fn expose_mut(&mut self) -> &mut [u8; N]
+    {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Nonce<N>
+{
+    fn from(nonce: [u8; N]) -> Self
+    {
+        Self::new(nonce)
+    }
+}
+
+impl<const N: usize> Drop for Nonce<N>
+{
+    fn drop(&mut self)
+    {
+        wipe(&mut self.0);
+    }
+}
+
+impl<const N: usize> fmt::Debug for Nonce<N>
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        write!(fmt, "Nonce([REDACTED])")
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{barrier, wipe, Nonce, SecretKey};
+
+    #[test]
+    fn wipe_test()
+    {
+        let mut key = [0xff_u8; 32];
+        let mut longkey = [[0xaa_u8; 4]; 60];
+        let mut table = [u128::MAX; 16];
+        let mut buffer = vec![0x55_u8; 100];
+
+        wipe(&mut key);
+        wipe(&mut longkey);
+        wipe(&mut table);
+        wipe(buffer.as_mut_slice());
+
+        assert_eq!(key, [0; 32]);
+        assert_eq!(longkey, [[0; 4]; 60]);
+        assert_eq!(table, [0; 16]);
+        assert_eq!(buffer, [0; 100]);
+        assert_eq!(barrier(0x1234_5678_u32), 0x1234_5678);
+    }
+
+    #[test]
+    fn redacted_test()
+    {
+        let key = SecretKey::new([0x42; 16]);
+        let nonce = Nonce::new([0x42; 12]);
+
+        assert_eq!(format!("{key:?}"), "SecretKey([REDACTED])");
+        assert_eq!(format!("{nonce:?}"), "Nonce([REDACTED])");
+        assert_eq!(key.expose(), &[0x42; 16]);
+        assert_eq!(nonce.expose(), &[0x42; 12]);
+    }
+}
@@ -51,8 +51,12 @@
 //! is very probably *very* vulnerable, so **do not use evilsha**.
 //! The same applies to all other evil\* crates.
 
+use std::fmt;
 use std::num::Wrapping;
 
+use evilsecret::wipe;
+use eviltraits::Mac;
+
 /// Version token for evilsha
 ///
 /// Specifies which version of SHA evilsha should use. **The best version is
@@ -82,9 +86,58 @@ pub enum ShaHash
     Sha512([u8; 64]),
 }
 
+impl ShaVersion
+{
+    /// The length of the hash in bytes
+    #[must_use]
+    pub const fn output_len(self) -> usize
+    {
+        match self
+        {
+            Self::Sha256 => 32,
+            Self::Sha384 => 48,
+            Self::Sha512 => 64,
+        }
+    }
+
+    /// The length of the blocks the message is split into in bytes
+    #[must_use]
+    pub const fn block_len(self) -> usize
+    {
+        match self
+        {
+            Self::Sha256 => 64,
+            Self::Sha384 | Self::Sha512 => 128,
+        }
+    }
+}
+
+impl ShaHash
+{
+    /// The hash as bytes
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8]
+    {
+        match self
+        {
+            Self::Sha256(x) => x,
+            Self::Sha384(x) => x,
+            Self::Sha512(x) => x,
+        }
+    }
+}
+
+impl AsRef<[u8]> for ShaHash
+{
+    fn as_ref(&self) -> &[u8]
+    {
+        self.as_bytes()
+    }
+}
+
 struct PaddingWrapper256<'a>(&'a [u8]);
 
-impl<'a> PaddingWrapper256<'a>
+impl PaddingWrapper256<'_>
 {
     const fn get(&self, i: usize) -> u8
     {
@@ -145,7 +198,7 @@ fn sha_256_pad(mes: &[u8]) -> Vec<[u32; 16]>
 
 struct PaddingWrapper512<'a>(&'a [u8]);
 
-impl<'a> PaddingWrapper512<'a>
+impl PaddingWrapper512<'_>
 {
     const fn get(&self, i: usize) -> u8
     {
@@ -308,6 +361,8 @@ fn sha_256(mes: &[[u32; 16]]) -> [u8; 32]
     rv
 }
 
+// Most of the lines are the constants
+#[allow(clippy::too_many_lines)]
 fn sha_512(mes: &[[u64; 16]], version: ShaVersion) -> ShaHash
 {
     assert_ne!(version, ShaVersion::Sha256, "Use `sha_256` for 256 bit");
@@ -553,10 +608,147 @@ pub fn sha_2(mes: &[u8], version: ShaVersion) -> ShaHash
     }
 }
 
+/// HMAC implementation
+///
+/// An **unsecure** implementation of HMAC (RFC 2104) with SHA 2.  **DO
+/// NOT USE!**
+///
+/// Specify with a [`ShaVersion`] token which SHA 2 version you want.
+#[must_use]
+pub fn hmac(key: &[u8], mes: &[u8], version: ShaVersion) -> ShaHash
+{
+    let mut padded_key = vec![0; version.block_len()];
+
+    if key.len() > version.block_len()
+    {
+        let hash = sha_2(key, version);
+        padded_key[..version.output_len()].copy_from_slice(hash.as_bytes());
+    }
+    else
+    {
+        padded_key[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = padded_key.iter().map(|x| x ^ 0x36).collect::<Vec<_>>();
+    inner.extend_from_slice(mes);
+
+    let mut outer = padded_key.iter().map(|x| x ^ 0x5c).collect::<Vec<_>>();
+    outer.extend_from_slice(sha_2(&inner, version).as_bytes());
+
+    sha_2(&outer, version)
+}
+
+/// PBKDF2 implementation
+///
+/// An **unsecure** implementation of PBKDF2 (RFC 8018, 5.2) with HMAC
+/// using the SHA 2 `version`, which fills `output` with the key
+/// derived from `password`, `salt` and `iterations`.  **DO NOT USE!**
+///
+/// # Panics
+/// Panics if `iterations` is zero.
+pub fn pbkdf2(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    version: ShaVersion,
+    output: &mut [u8],
+)
+{
+    assert!(iterations > 0, "PBKDF2 needs at least one iteration");
+
+    let mut input = salt.to_vec();
+    for (i, block) in (1_u32..).zip(output.chunks_mut(version.output_len()))
+    {
+        input.truncate(salt.len());
+        input.extend_from_slice(&i.to_be_bytes());
+
+        let mut u = hmac(password, &input, version);
+        let mut t = u.as_bytes().to_vec();
+        for _ in 1..iterations
+        {
+            u = hmac(password, u.as_bytes(), version);
+            for (t, u) in t.iter_mut().zip(u.as_bytes())
+            {
+                *t ^= u;
+            }
+        }
+
+        block.copy_from_slice(&t[..block.len()]);
+        wipe(t.as_mut_slice());
+    }
+}
+
+/// Buffered HMAC
+///
+/// [`hmac`] as [`Mac`], so that the message can be given in pieces.
+/// It isn't streaming though: since the SHA 2 here can only hash whole
+/// messages, the whole message is kept in memory until
+/// [`finish`](Mac::finish) is called.  The key and the message are
+/// overwritten with zeros when it's dropped.  **DO NOT USE!**
+pub struct Hmac
+{
+    key: Vec<u8>,
+    mes: Vec<u8>,
+    version: ShaVersion,
+}
+
+impl Hmac
+{
+    /// Starts a new message with `key`
+    #[must_use]
+    pub fn new(key: &[u8], version: ShaVersion) -> Self
+    {
+        Self {
+            key: key.to_vec(),
+            mes: Vec::new(),
+            version,
+        }
+    }
+}
+
+impl Mac for Hmac
+{
+    type Tag = ShaHash;
+
+    fn update(&mut self, data: &[u8])
+    {
+        self.mes.extend_from_slice(data);
+    }
+
+    fn finish(self) -> ShaHash
+    {
+        hmac(&self.key, &self.mes, self.version)
+    }
+}
+
+impl Drop for Hmac
+{
+    fn drop(&mut self)
+    {
+        self.key.resize(self.key.capacity(), 0);
+        wipe(self.key.as_mut_slice());
+        self.mes.resize(self.mes.capacity(), 0);
+        wipe(self.mes.as_mut_slice());
+    }
+}
+
+impl fmt::Debug for Hmac
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        write!(fmt, "Hmac({:?}, [REDACTED])", self.version)
+    }
+}
+
 #[cfg(test)]
 mod tests
 {
-    use crate::{sha_2, sha_256_pad, sha_512_pad, ShaHash, ShaVersion};
+    use eviltraits::Mac;
+
+    use crate::{
+        hmac, pbkdf2, sha_2, sha_256_pad, sha_512_pad, Hmac, ShaHash,
+        ShaVersion,
+    };
 
     #[test]
     fn padding_tests()
@@ -642,4 +834,122 @@ hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu"
             ])
         );
     }
+
+    #[test]
+    fn hmac_test()
+    {
+        // Test cases 1, 6 and 2 from RFC 4231
+        assert_eq!(
+            hmac(&[0x0b; 20], b"Hi There", ShaVersion::Sha256),
+            ShaHash::Sha256([
+                0xb0, 0x34, 0x4c, 0x61, 0xd8, 0xdb, 0x38, 0x53, 0x5c, 0xa8,
+                0xaf, 0xce, 0xaf, 0x0b, 0xf1, 0x2b, 0x88, 0x1d, 0xc2, 0x00,
+                0xc9, 0x83, 0x3d, 0xa7, 0x26, 0xe9, 0x37, 0x6c, 0x2e, 0x32,
+                0xcf, 0xf7
+            ])
+        );
+        assert_eq!(
+            hmac(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                ShaVersion::Sha512
+            ),
+            ShaHash::Sha512([
+                0x80, 0xb2, 0x42, 0x63, 0xc7, 0xc1, 0xa3, 0xeb, 0xb7, 0x14,
+                0x93, 0xc1, 0xdd, 0x7b, 0xe8, 0xb4, 0x9b, 0x46, 0xd1, 0xf4,
+                0x1b, 0x4a, 0xee, 0xc1, 0x12, 0x1b, 0x01, 0x37, 0x83, 0xf8,
+                0xf3, 0x52, 0x6b, 0x56, 0xd0, 0x37, 0xe0, 0x5f, 0x25, 0x98,
+                0xbd, 0x0f, 0xd2, 0x21, 0x5d, 0x6a, 0x1e, 0x52, 0x95, 0xe6,
+                0x4f, 0x73, 0xf6, 0x3f, 0x0a, 0xec, 0x8b, 0x91, 0x5a, 0x98,
+                0x5d, 0x78, 0x65, 0x98
+            ])
+        );
+        assert_eq!(
+            hmac(b"Jefe", b"what do ya want for nothing?", ShaVersion::Sha384),
+            ShaHash::Sha384([
+                0xaf, 0x45, 0xd2, 0xe3, 0x76, 0x48, 0x40, 0x31, 0x61, 0x7f,
+                0x78, 0xd2, 0xb5, 0x8a, 0x6b, 0x1b, 0x9c, 0x7e, 0xf4, 0x64,
+                0xf5, 0xa0, 0x1b, 0x47, 0xe4, 0x2e, 0xc3, 0x73, 0x63, 0x22,
+                0x44, 0x5e, 0x8e, 0x22, 0x40, 0xca, 0x5e, 0x69, 0xe2, 0xc7,
+                0x8b, 0x32, 0x39, 0xec, 0xfa, 0xb2, 0x16, 0x49
+            ])
+        );
+    }
+
+    #[test]
+    fn buffered_hmac_test()
+    {
+        let mut mac = Hmac::new(b"Jefe", ShaVersion::Sha384);
+        for piece in [&b"what do ya "[..], b"", b"want for nothing?"]
+        {
+            mac.update(piece);
+        }
+        let expected =
+            hmac(b"Jefe", b"what do ya want for nothing?", ShaVersion::Sha384);
+        assert_eq!(mac.finish(), expected);
+
+        let mut mac = Hmac::new(b"Jefe", ShaVersion::Sha384);
+        mac.update(&[0x42; 1000]);
+        assert_eq!(
+            mac.finish(),
+            hmac(b"Jefe", &[0x42; 1000], ShaVersion::Sha384)
+        );
+
+        let mut mac = Hmac::new(b"Jefe", ShaVersion::Sha384);
+        mac.update(b"what do ya want for nothing?");
+        assert!(mac.verify(expected.as_bytes()).is_ok());
+        let mac = Hmac::new(b"Jefe", ShaVersion::Sha384);
+        assert!(mac.verify(expected.as_bytes()).is_err());
+        assert_eq!(
+            format!("{:?}", Hmac::new(b"Jefe", ShaVersion::Sha256)),
+            "Hmac(Sha256, [REDACTED])"
+        );
+    }
+
+    #[test]
+    fn pbkdf2_test()
+    {
+        let hex = |s: &str| {
+            (0..s.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // From RFC 7914, 11
+        let mut output = [0; 64];
+        pbkdf2(b"passwd", b"salt", 1, ShaVersion::Sha256, &mut output);
+        assert_eq!(
+            output[..],
+            hex("55ac046e56e3089fec1691c22544b605\
+                 f94185216dde0465e68b9d57c20dacbc\
+                 49ca9cccf179b645991664b39d77ef31\
+                 7c71b845b1e30bd509112041d3a19783")
+        );
+
+        // Computed with Python's hashlib
+        let mut output = [0; 20];
+        pbkdf2(b"password", b"salt", 2, ShaVersion::Sha512, &mut output);
+        assert_eq!(
+            output[..],
+            hex("e1d9c16aa681708a45f5c7c4e215ceb66e011a2e")
+        );
+
+        let mut output = [0; 70];
+        pbkdf2(
+            b"passwordPASSWORDpassword",
+            b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+            100,
+            ShaVersion::Sha384,
+            &mut output,
+        );
+        assert_eq!(
+            output[..],
+            hex("0075d57842e830f44817821aba8dc486\
+                 ae32b89b6a31902840aae942b21d3681\
+                 a971330810f088b454b4d78307d164cd\
+                 e841f03fc2a4c2865475cb6da7e2c684\
+                 8d3f404da227")
+        );
+    }
 }
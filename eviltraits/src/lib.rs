@@ -0,0 +1,382 @@
+/*
+ * eviltraits – Common traits of the evil crates
+ * Copyright (C) 2021 Matthias Kaak
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed without the hope that it will be useful,
+ * and WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![warn(
+    missing_docs,
+    missing_debug_implementations,
+    missing_copy_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_import_braces,
+    unused_qualifications,
+    rustdoc::missing_crate_level_docs,
+    rust_2018_idioms,
+    clippy::all,
+    clippy::pedantic,
+    clippy::nursery,
+    clippy::cargo
+)]
+#![allow(clippy::suspicious_else_formatting, clippy::match_like_matches_macro)]
+
+//! Common traits of the evil\* crates
+//!
+//! eviltraits has the traits the ciphers and MACs of the other evil\*
+//! crates implement, so that code can be generic over them: a
+//! [`BlockCipher`] encrypts whole blocks, an [`Aead`] encrypts and
+//! authenticates with a nonce, and a [`Mac`] only authenticates.  Like
+//! everything else it's written by an amateur for the sole purpose
+//! that he learns a bit about cryptography, so **do not use it**.
+//!
+//! The implementations are in the crates of the algorithms, e.g.
+//! evilaes has `Aes256`, `Aes256Gcm`, `ChaCha20Poly1305` and
+//! `Poly1305`, and evilsha has `Hmac`.
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+
+use evilsecret::barrier;
+
+/// Errors of the traits
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum CipherError
+{
+    /// The buffer couldn't be split in a whole number of blocks.
+    NotWholeBlock,
+    /// The tag couldn't be verified.
+    Verification,
+}
+
+impl fmt::Display for CipherError
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error>
+    {
+        match self
+        {
+            Self::NotWholeBlock => write!(
+                fmt,
+                "The buffer couldn't be split in a whole number of blocks"
+            ),
+            Self::Verification =>
+            {
+                write!(fmt, "Authentication tag couldn't be verified")
+            }
+        }
+    }
+}
+
+impl error::Error for CipherError {}
+
+/// Compares two byte strings without leaving early at the first
+/// difference
+///
+/// Only the content is secret, the lengths aren't.
+#[must_use]
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool
+{
+    let diff = a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b));
+
+    a.len() == b.len() && barrier(diff) == 0
+}
+
+/// A block cipher with a fixed key
+///
+/// This alone is only useful as a building block, since encrypting the
+/// blocks one by one (ECB) shows which blocks are equal.
+pub trait BlockCipher
+{
+    /// The length of a block in bytes
+    const BLOCK_LEN: usize;
+
+    /// Encrypts every block of `buffer` in place
+    ///
+    /// # Errors
+    /// It returns [`CipherError::NotWholeBlock`] if `buffer` isn't a
+    /// whole number of blocks; it's left untouched then.
+    fn encrypt_blocks(&self, buffer: &mut [u8]) -> Result<(), CipherError>;
+
+    /// Decrypts every block of `buffer` in place
+    ///
+    /// # Errors
+    /// It returns [`CipherError::NotWholeBlock`] if `buffer` isn't a
+    /// whole number of blocks; it's left untouched then.
+    fn decrypt_blocks(&self, buffer: &mut [u8]) -> Result<(), CipherError>;
+}
+
+/// Authenticated encryption with additional data and a fixed key
+///
+/// Only the two in-place methods with a detached tag have to be
+/// implemented, the others are built on them.  The combined format
+/// is the cipher text followed by the tag.  **Never** use a nonce
+/// twice with the same key.
+pub trait Aead
+{
+    /// The nonce, e.g. [`Nonce<12>`](evilsecret::Nonce)
+    type Nonce;
+    /// The tag, e.g. `[u8; 16]`
+    type Tag: Copy + AsRef<[u8]> + for<'a> TryFrom<&'a [u8]>;
+
+    /// The length of [`Self::Tag`] in bytes
+    const TAG_LEN: usize;
+
+    /// Encrypts `buffer` in place and authenticates it and `aad`
+    ///
+    /// Returns the detached tag.
+    fn encrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        aad: &[u8],
+        nonce: &Self::Nonce,
+    ) -> Self::Tag;
+
+    /// Verifies `tag` and decrypts `buffer` in place
+    ///
+    /// # Errors
+    /// It returns [`CipherError::Verification`] if the tag doesn't fit
+    /// to `buffer` and `aad`; `buffer` still contains the cipher text
+    /// then.
+    fn decrypt_in_place_detached(
+        &self,
+        buffer: &mut [u8],
+        aad: &[u8],
+        nonce: &Self::Nonce,
+        tag: Self::Tag,
+    ) -> Result<(), CipherError>;
+
+    /// Encrypts `input` and returns the cipher text and the tag
+    fn encrypt_detached(
+        &self,
+        input: &[u8],
+        aad: &[u8],
+        nonce: &Self::Nonce,
+    ) -> (Vec<u8>, Self::Tag)
+    {
+        let mut c = input.to_vec();
+        let tag = self.encrypt_in_place_detached(&mut c, aad, nonce);
+
+        (c, tag)
+    }
+
+    /// Verifies `tag` and decrypts `c`
+    ///
+    /// # Errors
+    /// It returns [`CipherError::Verification`] if the tag doesn't fit
+    /// to `c` and `aad`.
+    fn decrypt_detached(
+        &self,
+        c: &[u8],
+        aad: &[u8],
+        nonce: &Self::Nonce,
+        tag: Self::Tag,
+    ) -> Result<Vec<u8>, CipherError>
+    {
+        let mut p = c.to_vec();
+        self.decrypt_in_place_detached(&mut p, aad, nonce, tag)?;
+
+        Ok(p)
+    }
+
+    /// Encrypts `input` and returns the cipher text with the tag
+    /// appended
+    fn encrypt(&self, input: &[u8], aad: &[u8], nonce: &Self::Nonce)
+        -> Vec<u8>
+    {
+        let (mut c, tag) = self.encrypt_detached(input, aad, nonce);
+        c.extend_from_slice(tag.as_ref());
+
+        c
+    }
+
+    /// Decrypts the output of [`encrypt`](Self::encrypt)
+    ///
+    /// # Errors
+    /// It returns [`CipherError::Verification`] if `c` is shorter than
+    /// a tag or the tag doesn't fit to it and `aad`.
+    fn decrypt(
+        &self,
+        c: &[u8],
+        aad: &[u8],
+        nonce: &Self::Nonce,
+    ) -> Result<Vec<u8>, CipherError>
+    {
+        let split = c
+            .len()
+            .checked_sub(Self::TAG_LEN)
+            .ok_or(CipherError::Verification)?;
+        let (c, tag) = c.split_at(split);
+        let tag =
+            Self::Tag::try_from(tag).map_err(|_| CipherError::Verification)?;
+
+        self.decrypt_detached(c, aad, nonce, tag)
+    }
+}
+
+/// A message authentication code
+///
+/// The message can be given in pieces with [`update`](Self::update).
+pub trait Mac
+{
+    /// The tag, e.g. `[u8; 16]`
+    type Tag: AsRef<[u8]>;
+
+    /// Adds `data` to the message
+    fn update(&mut self, data: &[u8]);
+
+    /// Calculates the tag of the message
+    fn finish(self) -> Self::Tag;
+
+    /// Checks whether `tag` is the tag of the message
+    ///
+    /// The comparison doesn't leave early at the first difference.
+    ///
+    /// # Errors
+    /// It returns [`CipherError::Verification`] if it isn't.
+    fn verify(self, tag: &[u8]) -> Result<(), CipherError>
+    where
+        Self: Sized,
+    {
+        if constant_time_eq(self.finish().as_ref(), tag)
+        {
+            Ok(())
+        }
+        else
+        {
+            Err(CipherError::Verification)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{constant_time_eq, Aead, CipherError, Mac};
+
+    // Not encryption at all, just enough to test the provided methods
+    struct Xor(u8);
+
+    impl Aead for Xor
+    {
+        type Nonce = u8;
+        type Tag = [u8; 2];
+
+        const TAG_LEN: usize = 2;
+
+        fn encrypt_in_place_detached(
+            &self,
+            buffer: &mut [u8],
+            aad: &[u8],
+            nonce: &u8,
+        ) -> [u8; 2]
+        {
+            for x in buffer.iter_mut()
+            {
+                *x ^= self.0 ^ nonce;
+            }
+            let mut sum = Sum(0);
+            sum.update(aad);
+            sum.update(buffer);
+
+            [sum.finish()[0], *nonce]
+        }
+
+        fn decrypt_in_place_detached(
+            &self,
+            buffer: &mut [u8],
+            aad: &[u8],
+            nonce: &u8,
+            tag: [u8; 2],
+        ) -> Result<(), CipherError>
+        {
+            let mut sum = Sum(0);
+            sum.update(aad);
+            sum.update(buffer);
+            if [sum.finish()[0], *nonce] != tag
+            {
+                return Err(CipherError::Verification);
+            }
+            for x in buffer.iter_mut()
+            {
+                *x ^= self.0 ^ nonce;
+            }
+
+            Ok(())
+        }
+    }
+
+    struct Sum(u8);
+
+    impl Mac for Sum
+    {
+        type Tag = [u8; 1];
+
+        fn update(&mut self, data: &[u8])
+        {
+            self.0 = data.iter().fold(self.0, |acc, x| acc.wrapping_add(*x));
+        }
+
+        fn finish(self) -> [u8; 1]
+        {
+            [self.0]
+        }
+    }
+
+    #[test]
+    fn aead_test() -> Result<(), CipherError>
+    {
+        let cipher = Xor(0x55);
+        let (c, tag) = cipher.encrypt_detached(b"evil", b"aad", &3);
+        let combined = cipher.encrypt(b"evil", b"aad", &3);
+
+        assert_eq!(combined[..4], c[..]);
+        assert_eq!(combined[4..], tag);
+        assert_eq!(cipher.decrypt_detached(&c, b"aad", &3, tag)?, b"evil");
+        assert_eq!(cipher.decrypt(&combined, b"aad", &3)?, b"evil");
+        assert_eq!(
+            cipher.decrypt(&combined, b"bad", &3),
+            Err(CipherError::Verification)
+        );
+        assert_eq!(
+            cipher.decrypt(&combined[..1], b"aad", &3),
+            Err(CipherError::Verification)
+        );
+        assert_eq!(
+            cipher.decrypt(&cipher.encrypt(b"", b"", &0), b"", &0)?,
+            b""
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mac_test()
+    {
+        let mut sum = Sum(0);
+        sum.update(&[1, 2]);
+        sum.update(&[3]);
+        assert!(sum.verify(&[6]).is_ok());
+        assert_eq!(Sum(0).verify(&[1]), Err(CipherError::Verification));
+        assert_eq!(Sum(0).verify(&[0, 0]), Err(CipherError::Verification));
+
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"a", b"ab"));
+        assert!(!constant_time_eq(b"ab", b"ac"));
+    }
+}